@@ -0,0 +1,5 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use synacorvm::engine::svm_fuzz::fuzz_load;
+
+fuzz_target!(|data: &[u8]| fuzz_load(data));
@@ -1,9 +0,0 @@
-pub mod svm_engine_state;
-pub mod svm_error;
-pub mod svm_constants;
-pub mod opcode;
-
-mod memory;
-mod extensions;
-mod registers;
-mod instruction_pointer;
\ No newline at end of file
@@ -1,3 +0,0 @@
-
-pub const MEMORY_SIZE_MAX: usize = std::i16::MAX as usize;
-pub const NUM_OF_REGISTERS: usize = 8;
\ No newline at end of file
@@ -1,8 +0,0 @@
-pub enum SVMError {
-    InvalidMemory,
-    InvalidRegister,
-    InvalidOpCode,
-    StackEmpty,
-    WriteError,
-    ReadError,
-}
\ No newline at end of file
@@ -1,3 +1,34 @@
 pub mod svm_program;
+pub mod svm_image_formats;
 pub mod svm_engine;
-mod internals;
\ No newline at end of file
+pub mod svm_code_tracker;
+pub mod svm_snapshot;
+pub mod svm_state_diff;
+pub mod svm_debugger;
+pub mod svm_tui;
+pub mod svm_gdb_stub;
+pub mod svm_json;
+pub mod svm_assembler;
+pub mod svm_patch;
+pub mod svm_dap_server;
+pub mod svm_memory_search;
+pub mod svm_symbols;
+pub mod svm_disassembler;
+pub mod svm_profiler;
+pub mod svm_coverage;
+pub mod svm_backtrace;
+pub mod svm_fault_report;
+pub mod svm_limits;
+pub mod svm_console;
+pub mod svm_devices;
+pub mod svm_random;
+#[cfg(feature = "host-calls")]
+pub mod svm_host_calls;
+pub mod svm_meta_commands;
+pub mod svm_checkpoints;
+pub mod svm_save_slots;
+pub mod svm_explorer;
+pub mod svm_server;
+pub mod svm_reference;
+pub mod svm_differential;
+pub mod svm_fuzz;
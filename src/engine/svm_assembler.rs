@@ -0,0 +1,276 @@
+use std::collections::BTreeMap;
+use synacorvm_core::opcode::{ExtensionSet, OpcodeValue, SVMOpCode, NUM_OF_OPCODES};
+use super::svm_program::SVMProgram;
+use super::svm_symbols::{SVMSymbols, parse_number};
+
+//  Also the number of words of memory
+const REGISTER_BASE: u16 = 32768;
+
+struct Statement {
+    line: usize,
+    address: u16,
+    opcode: Option<SVMOpCode>,
+    operands: Vec<String>,
+}
+
+//  Assembles the syntax the disassembler prints:
+//
+//      function main:
+//      loop:   out 'A'             ; comments start with a semicolon
+//              add r0 r0 1
+//              jmp loop+0
+//      text:   data "Hi\n" 0 text
+//              .org 100
+//
+//  Labels end with a colon, operands are r0-r7, numbers, character literals or
+//  label[+offset], and data takes any mix of those and strings. The numeric
+//  address prefix of a disassembly listing is accepted and ignored. Extension
+//  mnemonics are only known when their extension is in the set.
+pub struct SVMAssembly {
+    words: Vec<u16>,
+    labels: BTreeMap<String, u16>,
+    line_addresses: BTreeMap<usize, u16>,
+}
+
+impl SVMAssembly {
+    pub fn assemble(source: &str, extensions: ExtensionSet) -> Result<SVMAssembly, String> {
+        let mut labels = BTreeMap::new();
+        let mut statements = Vec::new();
+        let mut address: usize = 0;
+
+        for (index, text) in source.lines().enumerate() {
+            let line = index + 1;
+            let mut tokens = tokenize(text).map_err(|error| format!("line {}: {}", line, error))?;
+            while let Some(label) = take_label(&mut tokens) {
+                if parse_number(&label).is_ok() {
+                    continue;
+                }
+                if labels.insert(label.clone(), address as u16).is_some() {
+                    return Err(format!("line {}: {} is defined twice", line, label));
+                }
+            }
+            let mnemonic = match tokens.first() {
+                Some(mnemonic) => mnemonic.clone(),
+                None => continue,
+            };
+            let operands = tokens.split_off(1);
+
+            let (opcode, length) = match mnemonic.as_str() {
+                ".org" => {
+                    let origin = operands.first().ok_or_else(|| format!("line {}: .org requires an address", line))
+                        .and_then(|origin| parse_number(origin).map_err(|error| format!("line {}: {}", line, error)))?;
+                    if (origin as usize) < address {
+                        return Err(format!("line {}: .org {} is behind the current address {}", line, origin, address));
+                    }
+                    if origin > REGISTER_BASE {
+                        return Err(format!("line {}: .org {} is past the end of memory", line, origin));
+                    }
+                    address = origin as usize;
+                    continue;
+                },
+                "data" => {
+                    let mut length = 0;
+                    for operand in operands.iter() {
+                        length += parse_string(operand).map(|text| text.len()).unwrap_or(1);
+                    }
+                    (None, length)
+                },
+                _ => {
+                    let opcode = find_opcode(&mnemonic, extensions).ok_or_else(|| format!("line {}: unknown instruction {}", line, mnemonic))?;
+                    if operands.len() != opcode.get_operand_count() as usize {
+                        return Err(format!("line {}: {} takes {} operand(s)", line, mnemonic, opcode.get_operand_count()));
+                    }
+                    (Some(opcode), operands.len() + 1)
+                },
+            };
+            statements.push(Statement { line, address: address as u16, opcode, operands });
+            address += length;
+            if address > REGISTER_BASE as usize {
+                return Err(format!("line {}: program does not fit in memory", line));
+            }
+        }
+
+        let mut words = vec![0; address];
+        let mut line_addresses = BTreeMap::new();
+        for statement in statements.iter() {
+            let mut encoded = Vec::new();
+            if let Some(ref opcode) = statement.opcode {
+                encoded.push(opcode.get_value());
+            }
+            for operand in statement.operands.iter() {
+                match (statement.opcode.is_none(), parse_string(operand)) {
+                    (true, Some(text)) => encoded.extend(text),
+                    _ => encoded.push(resolve_operand(operand, &labels, statement.opcode.is_none())
+                        .map_err(|error| format!("line {}: {}", statement.line, error))?),
+                }
+            }
+            let start = statement.address as usize;
+            words[start..start + encoded.len()].copy_from_slice(&encoded);
+            line_addresses.insert(statement.line, statement.address);
+        }
+
+        Ok(SVMAssembly { words, labels, line_addresses })
+    }
+
+    pub fn get_words(&self) -> &[u16] {
+        &self.words
+    }
+
+    pub fn get_program(&self) -> SVMProgram {
+        SVMProgram::from_words(self.words.clone())
+    }
+
+    pub fn get_label(&self, label: &str) -> Option<u16> {
+        self.labels.get(label).cloned()
+    }
+
+    //  The first statement on or after the line, so a breakpoint on a comment lands on the next instruction
+    pub fn get_address_for_line(&self, line: usize) -> Option<(usize, u16)> {
+        self.line_addresses.range(line..).next().map(|(line, address)| (*line, *address))
+    }
+
+    //  The line of the statement that covers the address
+    pub fn get_line_for_address(&self, address: u16) -> Option<usize> {
+        self.line_addresses.iter()
+            .filter(|(_, start)| **start <= address)
+            .max_by_key(|(_, start)| **start)
+            .map(|(line, _)| *line)
+    }
+
+    pub fn get_symbols(&self) -> SVMSymbols {
+        let mut symbols = SVMSymbols::new();
+        for (label, address) in self.labels.iter() {
+            symbols.set_label(*address, label);
+        }
+        symbols
+    }
+}
+
+fn find_opcode(mnemonic: &str, extensions: ExtensionSet) -> Option<SVMOpCode> {
+    (0..NUM_OF_OPCODES)
+        .filter_map(|value| value.get_opcode_with(extensions).ok())
+        .find(|opcode| opcode.get_mnemonic() == mnemonic)
+}
+
+//  Splits a line into tokens, keeping quoted literals whole and dropping the comment
+fn tokenize(text: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() || c == ',' {
+            chars.next();
+        } else if c == ';' {
+            break;
+        } else if c == '"' || c == '\'' {
+            let mut token = String::new();
+            token.push(c);
+            chars.next();
+            let mut closed = false;
+            while let Some(next) = chars.next() {
+                token.push(next);
+                if next == '\\' {
+                    if let Some(escaped) = chars.next() {
+                        token.push(escaped);
+                    }
+                } else if next == c {
+                    closed = true;
+                    break;
+                }
+            }
+            if !closed {
+                return Err(format!("unterminated literal {}", token));
+            }
+            tokens.push(token);
+        } else {
+            let mut token = String::new();
+            while let Some(&next) = chars.peek() {
+                if next.is_whitespace() || next == ',' || next == ';' {
+                    break;
+                }
+                token.push(next);
+                chars.next();
+                if next == ':' {
+                    break;
+                }
+            }
+            tokens.push(token);
+        }
+    }
+    Ok(tokens)
+}
+
+//  Removes a leading "name:" or "function name:" from the tokens
+fn take_label(tokens: &mut Vec<String>) -> Option<String> {
+    if tokens.len() >= 2 && tokens[0] == "function" && tokens[1].ends_with(':') && tokens[1].len() > 1 {
+        let label = tokens.remove(1);
+        tokens.remove(0);
+        return Some(label.trim_end_matches(':').to_string());
+    }
+    match tokens.first() {
+        Some(token) if token.ends_with(':') && token.len() > 1 && !token.starts_with('\'') => {
+            Some(tokens.remove(0).trim_end_matches(':').to_string())
+        },
+        _ => None,
+    }
+}
+
+fn resolve_operand(operand: &str, labels: &BTreeMap<String, u16>, is_data: bool) -> Result<u16, String> {
+    if let Some(register) = operand.strip_prefix('r').and_then(|index| index.parse::<u16>().ok()) {
+        if register < 8 {
+            return Ok(REGISTER_BASE + register);
+        }
+    }
+    if operand.starts_with('\'') {
+        let text = unescape(&operand[1..operand.len() - 1])?;
+        return match text[..] {
+            [value] => Ok(value),
+            _ => Err(format!("{} is not a single character", operand)),
+        };
+    }
+    let value = match parse_number(operand) {
+        Ok(value) => value,
+        Err(_) => {
+            let (label, offset) = match operand.split_once('+') {
+                Some((label, offset)) => (label, parse_number(offset)?),
+                None => (operand, 0),
+            };
+            let address = labels.get(label).ok_or_else(|| format!("unknown label {}", label))?;
+            address.wrapping_add(offset)
+        },
+    };
+    //  Operands must be literals or registers, data words can be anything
+    if !is_data && value > REGISTER_BASE + 7 {
+        return Err(format!("{} is not a valid operand", operand));
+    }
+    Ok(value)
+}
+
+fn parse_string(operand: &str) -> Option<Vec<u16>> {
+    if operand.len() >= 2 && operand.starts_with('"') && operand.ends_with('"') {
+        unescape(&operand[1..operand.len() - 1]).ok()
+    } else {
+        None
+    }
+}
+
+//  The escapes the disassembler prints: \n, \', \", \\ and \xNN
+fn unescape(text: &str) -> Result<Vec<u16>, String> {
+    let mut values = Vec::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            values.push(c as u16);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => values.push(0x0A),
+            Some('x') => {
+                let digits: String = chars.by_ref().take(2).collect();
+                values.push(u16::from_str_radix(&digits, 16).map_err(|_| format!("invalid escape \\x{}", digits))?);
+            },
+            Some(other) => values.push(other as u16),
+            None => return Err("escape at end of literal".to_string()),
+        }
+    }
+    Ok(values)
+}
@@ -0,0 +1,31 @@
+use synacorvm_core::svm_engine_state::{SVMEngineState, StackViolation, StackViolationKind};
+use super::svm_symbols::SVMSymbols;
+
+//  Innermost frame first, built from the shadow call stack
+pub fn format_backtrace(engine_state: &SVMEngineState, symbols: &SVMSymbols) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut location = engine_state.instruction_pointer.get_ip();
+    for (depth, frame) in engine_state.call_stack.iter().rev().enumerate() {
+        lines.push(format!("#{:<3} {:>16} in {}", depth, symbols.format_address(location), symbols.format_address(frame.target)));
+        location = frame.call_site;
+    }
+    lines.push(format!("#{:<3} {:>16} in <root>", engine_state.call_stack.len(), symbols.format_address(location)));
+    lines
+}
+
+//  Stack slots holding return addresses pushed by call, keyed by stack index
+pub fn get_return_address_slots(engine_state: &SVMEngineState) -> Vec<usize> {
+    engine_state.call_stack.iter()
+        .filter(|frame| engine_state.stack.get(frame.stack_depth) == Some(&frame.return_address))
+        .map(|frame| frame.stack_depth)
+        .collect()
+}
+
+pub fn format_stack_violation(violation: &StackViolation, symbols: &SVMSymbols) -> String {
+    let description = match violation.kind {
+        StackViolationKind::ReturnedToData => "ret to a value that is not a return address",
+        StackViolationKind::OverwrittenReturn => "ret to an overwritten return address",
+        StackViolationKind::PoppedReturn => "pop of a return address",
+    };
+    format!("{}: {} ({})", symbols.format_address(violation.address), description, symbols.format_address(violation.value))
+}
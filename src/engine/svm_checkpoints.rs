@@ -0,0 +1,50 @@
+use std::collections::VecDeque;
+use synacorvm_core::svm_engine_state::SVMEngineState;
+
+//  How many player commands can be undone when the host does not say
+pub const DEFAULT_UNDO_DEPTH: usize = 256;
+
+//  Machine states from just before each input line was consumed, newest last.
+//  Memory pages are shared between clones until written, so a checkpoint costs
+//  little more than the pages the command went on to change.
+pub struct SVMCheckpoints {
+    states: VecDeque<SVMEngineState>,
+    capacity: usize,
+}
+
+impl SVMCheckpoints {
+    pub fn new(capacity: usize) -> SVMCheckpoints {
+        SVMCheckpoints {
+            states: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    pub fn record(&mut self, engine_state: &SVMEngineState) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.states.len() == self.capacity {
+            self.states.pop_front();
+        }
+        self.states.push_back(engine_state.clone());
+    }
+
+    //  The state from before the count-th most recent line, dropping it and every
+    //  newer checkpoint. None when fewer than count lines are remembered.
+    pub fn undo(&mut self, count: usize) -> Option<SVMEngineState> {
+        if count == 0 || count > self.states.len() {
+            return None;
+        }
+        self.states.truncate(self.states.len() - count + 1);
+        self.states.pop_back()
+    }
+
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use byteorder::{LittleEndian, WriteBytesExt};
+use synacorvm_core::memory::Memory;
+use synacorvm_core::svm_constants::MEMORY_SIZE_MAX;
+use synacorvm_core::svm_engine_state::MemoryWrite;
+
+//  Everything we know about a single word that has been written since the program was loaded
+pub struct WrittenWord {
+    pub original_value: u16,
+    pub current_value: u16,
+    pub writer_ips: BTreeSet<u16>,
+    pub written_after_execution: bool,
+    pub executed_after_write: bool,
+}
+
+impl WrittenWord {
+    pub fn is_code_modification(&self) -> bool {
+        self.written_after_execution || self.executed_after_write
+    }
+}
+
+//  A contiguous run of modified code words, used for reporting
+pub struct ModifiedRegion {
+    pub start: u16,
+    pub end: u16,
+    pub writer_ips: BTreeSet<u16>,
+    pub changed_words: usize,
+    pub written_after_execution: bool,
+    pub executed_after_write: bool,
+}
+
+pub struct SVMCodeTracker {
+    executed: Vec<bool>,
+    written: BTreeMap<u16, WrittenWord>,
+    dumped: BTreeSet<u16>,
+    dumps: Vec<(u16, PathBuf)>,
+    dump_directory: Option<PathBuf>,
+}
+
+impl SVMCodeTracker {
+    pub fn new(dump_directory: Option<PathBuf>) -> SVMCodeTracker {
+        SVMCodeTracker {
+            executed: vec![false; MEMORY_SIZE_MAX],
+            written: BTreeMap::new(),
+            dumped: BTreeSet::new(),
+            dumps: Vec::new(),
+            dump_directory,
+        }
+    }
+
+    //  Called before an instruction is dispatched with the address and the number of words it occupies
+    pub fn record_execution(&mut self, address: u16, length: u16, memory: &Memory) {
+        let mut first_decrypted_execution = false;
+        for word_address in address..address.saturating_add(length) {
+            let index = word_address as usize;
+            if index >= self.executed.len() {
+                break;
+            }
+            self.executed[index] = true;
+            if let Some(word) = self.written.get_mut(&word_address) {
+                word.executed_after_write = true;
+                if !self.dumped.contains(&word_address) {
+                    first_decrypted_execution = true;
+                }
+            }
+        }
+
+        if first_decrypted_execution {
+            self.dump_region(address, memory);
+        }
+    }
+
+    pub fn record_write(&mut self, writer_ip: u16, write: &MemoryWrite) {
+        let executed = self.executed.get(write.address as usize).cloned().unwrap_or(false);
+        let word = self.written.entry(write.address).or_insert(WrittenWord {
+            original_value: write.old_value,
+            current_value: write.new_value,
+            writer_ips: BTreeSet::new(),
+            written_after_execution: false,
+            executed_after_write: false,
+        });
+        word.current_value = write.new_value;
+        word.writer_ips.insert(writer_ip);
+        if executed {
+            word.written_after_execution = true;
+        }
+    }
+
+    pub fn get_modified_regions(&self) -> Vec<ModifiedRegion> {
+        let mut regions: Vec<ModifiedRegion> = Vec::new();
+        for (address, word) in self.written.iter().filter(|(_, word)| word.is_code_modification()) {
+            let changed = if word.original_value != word.current_value { 1 } else { 0 };
+            match regions.last_mut() {
+                Some(region) if region.end + 1 == *address => {
+                    region.end = *address;
+                    region.writer_ips.extend(word.writer_ips.iter());
+                    region.changed_words += changed;
+                    region.written_after_execution |= word.written_after_execution;
+                    region.executed_after_write |= word.executed_after_write;
+                },
+                _ => regions.push(ModifiedRegion {
+                    start: *address,
+                    end: *address,
+                    writer_ips: word.writer_ips.clone(),
+                    changed_words: changed,
+                    written_after_execution: word.written_after_execution,
+                    executed_after_write: word.executed_after_write,
+                }),
+            }
+        }
+        regions
+    }
+
+    pub fn print_report(&self) {
+        let regions = self.get_modified_regions();
+        println!("Self-modification report: {} modified code region(s)", regions.len());
+        for region in regions.iter() {
+            let writers: Vec<String> = region.writer_ips.iter().map(|ip| ip.to_string()).collect();
+            let mut kinds = Vec::new();
+            if region.written_after_execution {
+                kinds.push("overwrote executed code");
+            }
+            if region.executed_after_write {
+                kinds.push("executed after write");
+            }
+            println!("  {}-{} ({} words, {} changed): {}; written by instructions at {}",
+                region.start, region.end, region.end - region.start + 1, region.changed_words,
+                kinds.join(", "), writers.join(", "));
+        }
+        for (address, path) in self.dumps.iter() {
+            println!("  Memory image dumped at first execution of {}: {}", address, path.display());
+        }
+    }
+
+    //  Dump the whole memory image the first time a written region runs, so the
+    //  decrypted code can be disassembled, and mark the surrounding written words as dumped
+    fn dump_region(&mut self, address: u16, memory: &Memory) {
+        let mut start = address;
+        while start > 0 && self.written.contains_key(&(start - 1)) {
+            start -= 1;
+        }
+        let mut end = address;
+        while self.written.contains_key(&(end + 1)) {
+            end += 1;
+        }
+        for word_address in start..=end {
+            if self.written.contains_key(&word_address) {
+                self.dumped.insert(word_address);
+            }
+        }
+
+        let directory = match self.dump_directory {
+            Some(ref directory) => directory.clone(),
+            None => return,
+        };
+        let path = directory.join(format!("decrypted_{}.bin", address));
+        match write_memory_image(&path, memory) {
+            Ok(_) => self.dumps.push((address, path)),
+            Err(error) => println!("Failed to dump memory image to {}: {}", path.display(), error),
+        }
+    }
+}
+
+fn write_memory_image(path: &PathBuf, memory: &Memory) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    for address in 0..MEMORY_SIZE_MAX {
+        let value = memory.load_memory(address as u16).unwrap_or(0);
+        writer.write_u16::<LittleEndian>(value)?;
+    }
+    writer.flush()
+}
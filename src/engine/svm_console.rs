@@ -0,0 +1,36 @@
+use std::io::{self, BufRead, Write};
+
+//  Where the engine sends output and gets input from. Input is requested a
+//  chunk at a time, usually a line, whenever `in` finds the input buffer empty.
+pub trait SVMConsole {
+    fn write_output(&mut self, data: &[u8]) -> io::Result<()>;
+
+    //  Ok(None) means no input is available yet and the engine should wait
+    fn read_input(&mut self) -> io::Result<Option<Vec<u8>>>;
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub struct SVMStdConsole;
+
+impl SVMConsole for SVMStdConsole {
+    fn write_output(&mut self, data: &[u8]) -> io::Result<()> {
+        io::stdout().write_all(data)
+    }
+
+    fn read_input(&mut self) -> io::Result<Option<Vec<u8>>> {
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if io::stdin().lock().read_line(&mut line)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "end of input"));
+        }
+        //  We need to drop carriage returns if we're on Windows
+        Ok(Some(line.bytes().filter(|byte| *byte != b'\r').collect()))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use synacorvm_core::memory::Memory;
+use synacorvm_core::opcode::{ExtensionSet, OpcodeValue, SVMOpCode};
+use synacorvm_core::svm_constants::MEMORY_SIZE_MAX;
+use super::svm_disassembler::disassemble_instruction;
+use super::svm_json::JsonValue;
+use super::svm_symbols::SVMSymbols;
+
+const EXECUTED: u8 = 1;
+const OPERAND: u8 = 2;
+
+//  How often a jt or jf jumped and how often it fell through
+#[derive(Default, Clone, Copy)]
+pub struct BranchCoverage {
+    pub taken: u64,
+    pub not_taken: u64,
+}
+
+//  Which words ran as opcodes, which were read as operands of those, and
+//  which way each conditional jump went. Words that are neither were never
+//  used as code, which tells data apart from code in unknown images.
+pub struct SVMCoverage {
+    flags: Vec<u8>,
+    branches: BTreeMap<u16, BranchCoverage>,
+}
+
+impl Default for SVMCoverage {
+    fn default() -> SVMCoverage {
+        SVMCoverage::new()
+    }
+}
+
+impl SVMCoverage {
+    pub fn new() -> SVMCoverage {
+        SVMCoverage {
+            flags: vec![0; MEMORY_SIZE_MAX],
+            branches: BTreeMap::new(),
+        }
+    }
+
+    //  Called after the instruction ran, with the instruction pointer it left behind
+    pub fn record_instruction(&mut self, address: u16, opcode: &SVMOpCode, next_ip: u16) {
+        let operand_count = opcode.get_operand_count();
+        self.mark(address, EXECUTED);
+        for offset in 1..=operand_count {
+            self.mark(address.wrapping_add(offset), OPERAND);
+        }
+        if let SVMOpCode::Jt | SVMOpCode::Jf = opcode {
+            let branch = self.branches.entry(address).or_default();
+            if next_ip == address.wrapping_add(1 + operand_count) {
+                branch.not_taken += 1;
+            } else {
+                branch.taken += 1;
+            }
+        }
+    }
+
+    pub fn is_executed(&self, address: u16) -> bool {
+        self.get_flags(address) & EXECUTED != 0
+    }
+
+    pub fn is_operand(&self, address: u16) -> bool {
+        self.get_flags(address) & OPERAND != 0
+    }
+
+    pub fn get_branches(&self) -> &BTreeMap<u16, BranchCoverage> {
+        &self.branches
+    }
+
+    //  Annotated disassembly of [0, end). Executed instructions are marked +, words
+    //  never run are disassembled where they would start an instruction and marked -,
+    //  and conditional jumps show which of their directions were never taken.
+    pub fn write_report<W: Write>(&self, writer: &mut W, memory: &Memory, end: u16, symbols: &SVMSymbols, extensions: ExtensionSet) -> io::Result<()> {
+        let mut address = 0;
+        while address < end {
+            if let Some(label) = symbols.get(address).and_then(|annotation| annotation.get_name()) {
+                writeln!(writer, "{}:", label)?;
+            }
+            let instruction = disassemble_instruction(memory, address, symbols, extensions);
+            let length = if self.is_executed(address) { instruction.length } else { self.unexecuted_length(address, instruction.length) };
+            let word = memory.load_memory(address).unwrap_or(0);
+            let (marker, text) = if self.is_executed(address) {
+                ('+', instruction.text)
+            } else if length == instruction.length && word.get_opcode_with(extensions).is_ok() && !self.is_operand(address) {
+                ('-', instruction.text)
+            } else {
+                (' ', format!("data {}", word))
+            };
+            let branch = match self.branches.get(&address) {
+                Some(branch) if branch.taken == 0 => "  [never taken]",
+                Some(branch) if branch.not_taken == 0 => "  [never falls through]",
+                _ => "",
+            };
+            writeln!(writer, "{} {:>16}: {}{}", marker, symbols.format_address(address), text, branch)?;
+            address = address.saturating_add(length.max(1));
+        }
+        Ok(())
+    }
+
+    //  A summary of the report for scripts, as JSON
+    pub fn get_summary(&self, end: u16) -> JsonValue {
+        let executed = (0..end).filter(|address| self.is_executed(*address)).count() as u64;
+        let code_words = (0..end).filter(|address| self.get_flags(*address) != 0).count() as u64;
+        let partial_branches: Vec<JsonValue> = self.branches.iter()
+            .filter(|(_, branch)| branch.taken == 0 || branch.not_taken == 0)
+            .map(|(address, branch)| JsonValue::object(vec![
+                ("address", (*address as u64).into()),
+                ("taken", branch.taken.into()),
+                ("not_taken", branch.not_taken.into()),
+            ]))
+            .collect();
+        let executed_ranges: Vec<JsonValue> = self.get_code_ranges(end).into_iter()
+            .map(|(start, end)| JsonValue::Array(vec![(start as u64).into(), (end as u64).into()]))
+            .collect();
+        JsonValue::object(vec![
+            ("program_words", (end as u64).into()),
+            ("executed_instructions", executed.into()),
+            ("code_words", code_words.into()),
+            ("data_words", (end as u64 - code_words).into()),
+            ("branches", (self.branches.len() as u64).into()),
+            ("fully_covered_branches", (self.branches.len() as u64 - partial_branches.len() as u64).into()),
+            ("partial_branches", JsonValue::Array(partial_branches)),
+            ("code_ranges", JsonValue::Array(executed_ranges)),
+        ])
+    }
+
+    //  Runs of words used as code, as [start, end) pairs
+    pub fn get_code_ranges(&self, end: u16) -> Vec<(u16, u16)> {
+        let mut ranges: Vec<(u16, u16)> = Vec::new();
+        for address in (0..end).filter(|address| self.get_flags(*address) != 0) {
+            match ranges.last_mut() {
+                Some(range) if range.1 == address => range.1 = address + 1,
+                _ => ranges.push((address, address + 1)),
+            }
+        }
+        ranges
+    }
+
+    //  One past the last word that is non-zero or was used as code, where a report of the image can stop
+    pub fn get_end(&self, memory: &Memory) -> u16 {
+        (0..MEMORY_SIZE_MAX as u16).rev()
+            .find(|address| self.get_flags(*address) != 0 || memory.load_memory(*address).unwrap_or(0) != 0)
+            .map(|address| address + 1)
+            .unwrap_or(0)
+    }
+
+    //  An unexecuted instruction is only shown as one when it does not run into executed code
+    fn unexecuted_length(&self, address: u16, length: u16) -> u16 {
+        let overlaps = (1..length).any(|offset| self.is_executed(address.saturating_add(offset)));
+        if overlaps { 1 } else { length }
+    }
+
+    fn get_flags(&self, address: u16) -> u8 {
+        self.flags.get(address as usize).cloned().unwrap_or(0)
+    }
+
+    fn mark(&mut self, address: u16, flag: u8) {
+        if let Some(flags) = self.flags.get_mut(address as usize) {
+            *flags |= flag;
+        }
+    }
+}
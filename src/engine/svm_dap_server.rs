@@ -0,0 +1,674 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender, TryRecvError};
+use std::thread;
+use synacorvm_core::opcode::{ExtensionSet, SVMOpCode};
+use synacorvm_core::svm_constants::{MEMORY_SIZE_MAX, NUM_OF_REGISTERS};
+use synacorvm_core::svm_error::SVMError;
+use super::svm_assembler::SVMAssembly;
+use super::svm_backtrace::get_return_address_slots;
+use super::svm_disassembler::disassemble_instruction;
+use super::svm_engine::{SVMEngine, SVMTermination};
+use super::svm_json::JsonValue;
+use super::svm_program::SVMProgram;
+use super::svm_snapshot::load_state_or_program;
+use super::svm_symbols::SVMSymbols;
+
+const THREAD_ID: u64 = 1;
+const REGISTERS_REFERENCE: u64 = 1;
+const STACK_REFERENCE: u64 = 2;
+const MEMORY_REFERENCE: u64 = 3;
+//  Memory is shown in pages of words, each page has its own reference above this
+const MEMORY_PAGE_REFERENCE_BASE: u64 = 1000;
+const MEMORY_PAGE_SIZE: usize = 256;
+//  Instructions run between checks for a pause or any other request
+const SLICE_LENGTH: u64 = 100_000;
+
+//  The program, and the assembler source it came from if it was launched from one
+struct Session {
+    engine: SVMEngine,
+    source: Option<(PathBuf, SVMAssembly)>,
+    stop_on_entry: bool,
+}
+
+//  Where a continue or step request stops the program, besides breakpoints
+#[derive(Clone, Copy)]
+enum Resumption {
+    Continue,
+    //  Once the call stack is shallower than this depth
+    StepOut(usize),
+    //  Once a call made at this depth returns to this address
+    StepOver(usize, u16),
+}
+
+//  A Debug Adapter Protocol server. Requests are read on their own thread, and a
+//  running program is run in slices with any request that arrived handled between
+//  them, so it can be paused. Output from `out` is sent as output events; when the
+//  program waits for input, lines typed into the debug console are sent to it, and
+//  `>` sends a line any time.
+pub struct SVMDapServer {
+    session: Option<Session>,
+    source_breakpoints: BTreeSet<u16>,
+    instruction_breakpoints: BTreeSet<u16>,
+    awaiting_input: bool,
+    resume_pending: bool,
+    running: Option<Resumption>,
+    sequence: u64,
+    outgoing: Vec<JsonValue>,
+}
+
+impl Default for SVMDapServer {
+    fn default() -> SVMDapServer {
+        SVMDapServer::new()
+    }
+}
+
+impl SVMDapServer {
+    pub fn new() -> SVMDapServer {
+        SVMDapServer {
+            session: None,
+            source_breakpoints: BTreeSet::new(),
+            instruction_breakpoints: BTreeSet::new(),
+            awaiting_input: false,
+            resume_pending: false,
+            running: None,
+            sequence: 0,
+            outgoing: Vec::new(),
+        }
+    }
+
+    //  Serves one client until it disconnects
+    pub fn serve<R: BufRead + Send, W: Write>(&mut self, reader: &mut R, writer: &mut W) -> io::Result<()> {
+        let (sender, receiver) = mpsc::channel();
+        thread::scope(|scope| {
+            scope.spawn(move || forward_requests(reader, sender));
+            loop {
+                let message = if self.running.is_some() {
+                    match receiver.try_recv() {
+                        Ok(message) => Some(message?),
+                        Err(TryRecvError::Empty) => None,
+                        Err(TryRecvError::Disconnected) => break,
+                    }
+                } else {
+                    match receiver.recv() {
+                        Ok(message) => Some(message?),
+                        Err(_) => break,
+                    }
+                };
+                let keep_serving = match message {
+                    Some(message) => self.handle_request(&message),
+                    None => {
+                        self.run_slice();
+                        true
+                    },
+                };
+                for message in self.outgoing.drain(..) {
+                    write_message(writer, &message)?;
+                }
+                if !keep_serving {
+                    break;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    //  Returns false once the client has disconnected
+    fn handle_request(&mut self, request: &JsonValue) -> bool {
+        let command = request.get("command").and_then(|command| command.as_str()).unwrap_or("").to_string();
+        let request_sequence = request.get("seq").and_then(|sequence| sequence.as_u64()).unwrap_or(0);
+        let empty = JsonValue::Object(Vec::new());
+        let arguments = request.get("arguments").unwrap_or(&empty);
+
+        let mut events = Vec::new();
+        let result = match command.as_str() {
+            "initialize" => {
+                events.push(("initialized", JsonValue::Object(Vec::new())));
+                Ok(get_capabilities())
+            },
+            "launch" => self.launch(arguments),
+            "setBreakpoints" => self.set_breakpoints(arguments),
+            "setInstructionBreakpoints" => self.set_instruction_breakpoints(arguments),
+            "setExceptionBreakpoints" => Ok(JsonValue::object(vec![("breakpoints", JsonValue::Array(Vec::new()))])),
+            "configurationDone" | "threads" | "stackTrace" | "scopes" | "variables" | "evaluate" | "disassemble"
+                | "continue" | "next" | "stepIn" | "stepOut" | "pause" if self.session.is_none() => Err("No program has been launched".to_string()),
+            "configurationDone" => Ok(JsonValue::Null),
+            "threads" => Ok(JsonValue::object(vec![("threads", JsonValue::Array(vec![
+                JsonValue::object(vec![("id", THREAD_ID.into()), ("name", "main".into())]),
+            ]))])),
+            "stackTrace" => Ok(self.stack_trace()),
+            "scopes" => Ok(get_scopes()),
+            "variables" => self.variables(arguments),
+            "evaluate" => self.evaluate(arguments),
+            "disassemble" => self.disassemble(arguments),
+            "continue" => Ok(JsonValue::object(vec![("allThreadsContinued", true.into())])),
+            "next" | "stepIn" | "stepOut" => Ok(JsonValue::Null),
+            "pause" if self.awaiting_input || self.running.is_some() => Ok(JsonValue::Null),
+            "pause" => Err("The program is not running".to_string()),
+            "disconnect" | "terminate" => Ok(JsonValue::Null),
+            _ => Err(format!("Unsupported request {}", command)),
+        };
+
+        let succeeded = result.is_ok();
+        self.send_response(request_sequence, &command, result);
+        for (event, body) in events {
+            self.send_event(event, body);
+        }
+        if !succeeded {
+            return true;
+        }
+
+        //  The effects that follow the response, in the order a client expects them
+        match command.as_str() {
+            "configurationDone" => {
+                if self.session.as_ref().map(|session| session.stop_on_entry).unwrap_or(false) {
+                    self.send_stopped("entry", None);
+                } else {
+                    self.resume(Resumption::Continue);
+                }
+            },
+            "continue" => self.resume(Resumption::Continue),
+            "next" => {
+                let engine_state = self.get_engine().get_state();
+                let ip = engine_state.instruction_pointer.get_ip();
+                if engine_state.memory.load_memory(ip).ok() == Some(SVMOpCode::Call.get_value()) {
+                    let return_address = ip.wrapping_add(1 + SVMOpCode::Call.get_operand_count());
+                    self.resume(Resumption::StepOver(engine_state.call_stack.len(), return_address));
+                } else {
+                    self.step();
+                }
+            },
+            "stepIn" => self.step(),
+            "stepOut" => {
+                let depth = self.get_engine().get_state().call_stack.len();
+                self.resume(Resumption::StepOut(depth));
+            },
+            "evaluate" if self.resume_pending => {
+                self.resume_pending = false;
+                self.send_event("continued", JsonValue::object(vec![("threadId", THREAD_ID.into())]));
+                self.resume(Resumption::Continue);
+            },
+            "pause" => {
+                self.running = None;
+                self.awaiting_input = false;
+                self.send_program_output();
+                self.send_stopped("pause", None);
+            },
+            "disconnect" | "terminate" => {
+                self.send_event("terminated", JsonValue::Object(Vec::new()));
+                return false;
+            },
+            _ => {},
+        }
+        true
+    }
+
+    fn launch(&mut self, arguments: &JsonValue) -> Result<JsonValue, String> {
+        let path = arguments.get("program").and_then(|program| program.as_str())
+            .ok_or("launch requires a program")?;
+        let path = PathBuf::from(path);
+        let is_source = matches!(path.extension().and_then(|extension| extension.to_str()), Some("asm") | Some("s"));
+
+        let mut session = if is_source {
+            let text = fs::read_to_string(&path).map_err(|error| format!("{}: {}", path.display(), error))?;
+            let assembly = SVMAssembly::assemble(&text, ExtensionSet::STRICT).map_err(|error| format!("{}: {}", path.display(), error))?;
+            let mut engine = SVMEngine::new(assembly.get_program());
+            engine.set_symbols(assembly.get_symbols());
+            Session { engine, source: Some((path, assembly)), stop_on_entry: false }
+        } else {
+            let engine_state = load_state_or_program(&path).map_err(|error| format!("{}: {}", path.display(), error))?;
+            let mut engine = SVMEngine::new(SVMProgram::from_words(Vec::new()));
+            engine.set_state(engine_state);
+            Session { engine, source: None, stop_on_entry: false }
+        };
+        if let Some(symbols) = arguments.get("symbols").and_then(|symbols| symbols.as_str()) {
+            let symbols = SVMSymbols::load(Path::new(symbols)).map_err(|error| format!("{}: {}", symbols, error))?;
+            session.engine.set_symbols(symbols);
+        }
+        session.stop_on_entry = arguments.get("stopOnEntry").and_then(|stop| stop.as_bool()).unwrap_or(false);
+        session.engine.detach_console();
+        self.session = Some(session);
+        Ok(JsonValue::Null)
+    }
+
+    //  Source breakpoints only resolve in the assembler source the program was launched from
+    fn set_breakpoints(&mut self, arguments: &JsonValue) -> Result<JsonValue, String> {
+        let path = arguments.get("source").and_then(|source| source.get("path")).and_then(|path| path.as_str());
+        let assembly = match (path, self.session.as_ref().and_then(|session| session.source.as_ref())) {
+            (Some(path), Some((source_path, assembly))) if is_same_file(Path::new(path), source_path) => Some(assembly),
+            _ => None,
+        };
+
+        let mut addresses = BTreeSet::new();
+        let mut results = Vec::new();
+        let requested = arguments.get("breakpoints").and_then(|breakpoints| breakpoints.as_array()).unwrap_or(&[]);
+        for breakpoint in requested {
+            let line = breakpoint.get("line").and_then(|line| line.as_u64()).unwrap_or(0) as usize;
+            match assembly.and_then(|assembly| assembly.get_address_for_line(line)) {
+                Some((line, address)) => {
+                    addresses.insert(address);
+                    results.push(JsonValue::object(vec![
+                        ("verified", true.into()),
+                        ("line", (line as u64).into()),
+                        ("instructionReference", address.to_string().into()),
+                    ]));
+                },
+                None => results.push(JsonValue::object(vec![
+                    ("verified", false.into()),
+                    ("line", (line as u64).into()),
+                    ("message", "No code at this line".into()),
+                ])),
+            }
+        }
+        self.source_breakpoints = addresses;
+        Ok(JsonValue::object(vec![("breakpoints", results.into())]))
+    }
+
+    fn set_instruction_breakpoints(&mut self, arguments: &JsonValue) -> Result<JsonValue, String> {
+        let mut addresses = BTreeSet::new();
+        let mut results = Vec::new();
+        let requested = arguments.get("breakpoints").and_then(|breakpoints| breakpoints.as_array()).unwrap_or(&[]);
+        for breakpoint in requested {
+            let reference = breakpoint.get("instructionReference").and_then(|reference| reference.as_str()).unwrap_or("");
+            let offset = breakpoint.get("offset").and_then(|offset| offset.as_u64()).unwrap_or(0);
+            let address = self.parse_address(reference).ok()
+                .map(|address| address as u64 + offset)
+                .filter(|address| (*address as usize) < MEMORY_SIZE_MAX);
+            match address {
+                Some(address) => {
+                    addresses.insert(address as u16);
+                    results.push(JsonValue::object(vec![
+                        ("verified", true.into()),
+                        ("instructionReference", address.to_string().into()),
+                    ]));
+                },
+                None => results.push(JsonValue::object(vec![
+                    ("verified", false.into()),
+                    ("message", format!("Unknown address {}", reference).into()),
+                ])),
+            }
+        }
+        self.instruction_breakpoints = addresses;
+        Ok(JsonValue::object(vec![("breakpoints", results.into())]))
+    }
+
+    //  Innermost frame first, from the shadow call stack
+    fn stack_trace(&self) -> JsonValue {
+        let engine = self.get_engine();
+        let engine_state = engine.get_state();
+        let symbols = engine.get_symbols();
+
+        let mut locations = Vec::new();
+        let mut location = engine_state.instruction_pointer.get_ip();
+        for frame in engine_state.call_stack.iter().rev() {
+            locations.push((location, symbols.format_address(frame.target)));
+            location = frame.call_site;
+        }
+        locations.push((location, "<root>".to_string()));
+
+        let frames: Vec<JsonValue> = locations.iter().enumerate().map(|(index, (address, function))| {
+            let mut frame = vec![
+                ("id", (index as u64 + 1).into()),
+                ("name", format!("{} at {}", function, symbols.format_address(*address)).into()),
+                ("instructionPointerReference", address.to_string().into()),
+                ("line", 0.into()),
+                ("column", 0.into()),
+            ];
+            if let Some((path, line)) = self.get_source_line(*address) {
+                frame[3] = ("line", (line as u64).into());
+                frame[4] = ("column", 1.into());
+                frame.push(("source", get_source(&path)));
+            }
+            JsonValue::object(frame)
+        }).collect();
+        let count = frames.len() as u64;
+        JsonValue::object(vec![("stackFrames", frames.into()), ("totalFrames", count.into())])
+    }
+
+    fn variables(&self, arguments: &JsonValue) -> Result<JsonValue, String> {
+        let reference = arguments.get("variablesReference").and_then(|reference| reference.as_u64())
+            .ok_or("variables requires a variablesReference")?;
+        let engine = self.get_engine();
+        let engine_state = engine.get_state();
+        let variables = match reference {
+            REGISTERS_REFERENCE => {
+                let ip = engine_state.instruction_pointer.get_ip();
+                let mut variables = vec![variable("ip", engine.get_symbols().format_address(ip), 0)];
+                for index in 0..NUM_OF_REGISTERS {
+                    let value = engine_state.registers.get_register_by_index(index);
+                    variables.push(variable(&format!("r{}", index), format_word(value), 0));
+                }
+                variables
+            },
+            STACK_REFERENCE => {
+                let return_slots = get_return_address_slots(engine_state);
+                engine_state.stack.iter().enumerate().rev().map(|(index, value)| {
+                    let text = if return_slots.contains(&index) {
+                        format!("{} (return to {})", value, engine.get_symbols().format_address(*value))
+                    } else {
+                        format_word(*value)
+                    };
+                    variable(&format!("[{}]", index), text, 0)
+                }).collect()
+            },
+            MEMORY_REFERENCE => (0..MEMORY_SIZE_MAX.div_ceil(MEMORY_PAGE_SIZE)).map(|page| {
+                let start = page * MEMORY_PAGE_SIZE;
+                let end = (start + MEMORY_PAGE_SIZE).min(MEMORY_SIZE_MAX) - 1;
+                variable(&format!("{}-{}", start, end), String::new(), MEMORY_PAGE_REFERENCE_BASE + page as u64)
+            }).collect(),
+            _ if reference >= MEMORY_PAGE_REFERENCE_BASE => {
+                let start = (reference - MEMORY_PAGE_REFERENCE_BASE) as usize * MEMORY_PAGE_SIZE;
+                (start..(start + MEMORY_PAGE_SIZE).min(MEMORY_SIZE_MAX)).filter_map(|address| {
+                    let value = engine_state.memory.load_memory(address as u16).ok()?;
+                    Some(variable(&address.to_string(), format_word(value), 0))
+                }).collect()
+            },
+            _ => return Err(format!("Unknown variables reference {}", reference)),
+        };
+        Ok(JsonValue::object(vec![("variables", variables.into())]))
+    }
+
+    //  Registers and memory can be inspected; anything else typed while the
+    //  program waits for input, or after a `>`, is sent to the program
+    fn evaluate(&mut self, arguments: &JsonValue) -> Result<JsonValue, String> {
+        let expression = arguments.get("expression").and_then(|expression| expression.as_str()).unwrap_or("");
+        let context = arguments.get("context").and_then(|context| context.as_str()).unwrap_or("repl");
+
+        let input = match expression.strip_prefix('>') {
+            Some(input) => Some(input.strip_prefix(' ').unwrap_or(input)),
+            None if context == "repl" && self.awaiting_input => Some(expression),
+            None => None,
+        };
+        if let Some(input) = input {
+            self.get_engine_mut().push_input(format!("{}\n", input).as_bytes());
+            //  The program resumes once the response has been sent
+            self.resume_pending = self.awaiting_input;
+            self.awaiting_input = false;
+            return Ok(JsonValue::object(vec![("result", String::new().into()), ("variablesReference", 0.into())]));
+        }
+
+        let engine_state = self.get_engine().get_state();
+        let value = match expression.trim() {
+            "ip" | "pc" => engine_state.instruction_pointer.get_ip(),
+            register if register.len() == 2 && register.starts_with('r') => {
+                let index = register[1..].parse::<usize>().ok().filter(|index| *index < NUM_OF_REGISTERS)
+                    .ok_or_else(|| format!("Unknown register {}", register))?;
+                engine_state.registers.get_register_by_index(index)
+            },
+            address => {
+                let address = self.parse_address(address.trim_start_matches('[').trim_end_matches(']'))?;
+                engine_state.memory.load_memory(address).map_err(|_| format!("{} is not a memory address", address))?
+            },
+        };
+        Ok(JsonValue::object(vec![("result", format_word(value).into()), ("variablesReference", 0.into())]))
+    }
+
+    fn disassemble(&self, arguments: &JsonValue) -> Result<JsonValue, String> {
+        let reference = arguments.get("memoryReference").and_then(|reference| reference.as_str()).unwrap_or("0");
+        let offset = arguments.get("instructionOffset").and_then(|offset| offset.as_u64()).unwrap_or(0);
+        let count = arguments.get("instructionCount").and_then(|count| count.as_u64()).unwrap_or(0);
+        let engine = self.get_engine();
+        let mut address = self.parse_address(reference)?;
+        for _ in 0..offset {
+            address = address.saturating_add(disassemble_instruction(&engine.get_state().memory, address, engine.get_symbols(), engine.get_extensions()).length);
+        }
+
+        let mut instructions = Vec::new();
+        for _ in 0..count {
+            let instruction = disassemble_instruction(&engine.get_state().memory, address, engine.get_symbols(), engine.get_extensions());
+            let mut entry = vec![
+                ("address", address.to_string().into()),
+                ("instruction", instruction.text.into()),
+            ];
+            if let Some((path, line)) = self.get_source_line(address) {
+                entry.push(("line", (line as u64).into()));
+                entry.push(("location", get_source(&path)));
+            }
+            instructions.push(JsonValue::object(entry));
+            address = address.saturating_add(instruction.length);
+        }
+        Ok(JsonValue::object(vec![("instructions", instructions.into())]))
+    }
+
+    //  Runs the first slice straight away, so a program that stops quickly has
+    //  stopped before the next request is handled
+    fn resume(&mut self, resumption: Resumption) {
+        self.running = Some(resumption);
+        self.run_slice();
+    }
+
+    //  Runs until a breakpoint or the stop for the current request, or until the
+    //  slice is used up and any pending request can be handled
+    fn run_slice(&mut self) {
+        let (session, resumption) = match (self.session.as_mut(), self.running) {
+            (Some(session), Some(resumption)) => (session, resumption),
+            _ => return,
+        };
+        let source_breakpoints = &self.source_breakpoints;
+        let instruction_breakpoints = &self.instruction_breakpoints;
+        let mut executed = 0;
+        let mut sliced = false;
+        let termination = session.engine.run_until(|engine_state| {
+            let ip = engine_state.instruction_pointer.get_ip();
+            let depth = engine_state.call_stack.len();
+            let should_stop = source_breakpoints.contains(&ip) || instruction_breakpoints.contains(&ip) || match resumption {
+                Resumption::Continue => false,
+                Resumption::StepOut(out_of) => depth < out_of,
+                Resumption::StepOver(over, return_address) => depth < over || (depth == over && ip == return_address),
+            };
+            executed += 1;
+            sliced = !should_stop && executed >= SLICE_LENGTH;
+            should_stop || sliced
+        });
+        if sliced {
+            self.send_program_output();
+            return;
+        }
+        self.running = None;
+        let reason = match resumption {
+            Resumption::Continue => "breakpoint",
+            _ => "step",
+        };
+        self.report_termination(termination, reason);
+    }
+
+    fn step(&mut self) {
+        let engine = self.get_engine_mut();
+        let termination = match engine.step() {
+            Ok(_) if engine.get_state().halted => SVMTermination::Halted,
+            Ok(_) => SVMTermination::Stopped,
+            Err(SVMError::AwaitingInput) => SVMTermination::AwaitingInput,
+            Err(error) => SVMTermination::Error(error),
+        };
+        self.report_termination(termination, "step");
+    }
+
+    fn report_termination(&mut self, termination: SVMTermination, reason: &str) {
+        self.send_program_output();
+        match termination {
+            SVMTermination::Stopped => self.send_stopped(reason, None),
+            SVMTermination::AwaitingInput => {
+                self.awaiting_input = true;
+                self.send_output("console", "Waiting for input, type a line in the debug console\n");
+            },
+            SVMTermination::Halted => {
+                self.send_event("exited", JsonValue::object(vec![("exitCode", 0.into())]));
+                self.send_event("terminated", JsonValue::Object(Vec::new()));
+            },
+            SVMTermination::Error(ref error) => self.send_stopped("exception", Some(error.get_description())),
+            other => self.send_stopped("pause", Some(other.get_description())),
+        }
+    }
+
+    fn send_program_output(&mut self) {
+        let output = self.get_engine_mut().take_output();
+        if !output.is_empty() {
+            self.send_output("stdout", &String::from_utf8_lossy(&output));
+        }
+    }
+
+    fn get_engine(&self) -> &SVMEngine {
+        &self.session.as_ref().expect("no program launched").engine
+    }
+
+    fn get_engine_mut(&mut self) -> &mut SVMEngine {
+        &mut self.session.as_mut().expect("no program launched").engine
+    }
+
+    fn get_source_line(&self, address: u16) -> Option<(PathBuf, usize)> {
+        let (path, assembly) = self.session.as_ref()?.source.as_ref()?;
+        assembly.get_line_for_address(address).map(|line| (path.clone(), line))
+    }
+
+    fn parse_address(&self, text: &str) -> Result<u16, String> {
+        match self.session {
+            Some(ref session) => session.engine.get_symbols().parse_address(text),
+            None => SVMSymbols::new().parse_address(text),
+        }
+    }
+
+    fn send_response(&mut self, request_sequence: u64, command: &str, result: Result<JsonValue, String>) {
+        self.sequence += 1;
+        let mut response = vec![
+            ("seq", self.sequence.into()),
+            ("type", "response".into()),
+            ("request_seq", request_sequence.into()),
+            ("success", result.is_ok().into()),
+            ("command", command.into()),
+        ];
+        match result {
+            Ok(JsonValue::Null) => {},
+            Ok(body) => response.push(("body", body)),
+            Err(message) => response.push(("message", message.into())),
+        }
+        self.outgoing.push(JsonValue::object(response));
+    }
+
+    fn send_event(&mut self, event: &str, body: JsonValue) {
+        self.sequence += 1;
+        self.outgoing.push(JsonValue::object(vec![
+            ("seq", self.sequence.into()),
+            ("type", "event".into()),
+            ("event", event.into()),
+            ("body", body),
+        ]));
+    }
+
+    fn send_output(&mut self, category: &str, output: &str) {
+        self.send_event("output", JsonValue::object(vec![("category", category.into()), ("output", output.into())]));
+    }
+
+    fn send_stopped(&mut self, reason: &str, description: Option<&str>) {
+        let mut body = vec![
+            ("reason", reason.into()),
+            ("threadId", THREAD_ID.into()),
+            ("allThreadsStopped", true.into()),
+        ];
+        if let Some(description) = description {
+            body.push(("description", description.into()));
+            body.push(("text", description.into()));
+        }
+        self.send_event("stopped", JsonValue::object(body));
+    }
+}
+
+fn get_capabilities() -> JsonValue {
+    JsonValue::object(vec![
+        ("supportsConfigurationDoneRequest", true.into()),
+        ("supportsInstructionBreakpoints", true.into()),
+        ("supportsDisassembleRequest", true.into()),
+        ("supportsTerminateRequest", true.into()),
+        ("supportsSteppingGranularity", false.into()),
+    ])
+}
+
+fn get_scopes() -> JsonValue {
+    let scope = |name: &str, reference: u64, expensive: bool| JsonValue::object(vec![
+        ("name", name.into()),
+        ("variablesReference", reference.into()),
+        ("expensive", expensive.into()),
+    ]);
+    JsonValue::object(vec![("scopes", JsonValue::Array(vec![
+        scope("Registers", REGISTERS_REFERENCE, false),
+        scope("Stack", STACK_REFERENCE, false),
+        scope("Memory", MEMORY_REFERENCE, true),
+    ]))])
+}
+
+fn get_source(path: &Path) -> JsonValue {
+    let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    JsonValue::object(vec![("name", name.into()), ("path", path.to_string_lossy().into_owned().into())])
+}
+
+fn variable(name: &str, value: String, reference: u64) -> JsonValue {
+    JsonValue::object(vec![
+        ("name", name.into()),
+        ("value", value.into()),
+        ("variablesReference", reference.into()),
+    ])
+}
+
+fn format_word(value: u16) -> String {
+    format!("{} (0x{:04x})", value, value)
+}
+
+fn is_same_file(left: &Path, right: &Path) -> bool {
+    match (fs::canonicalize(left), fs::canonicalize(right)) {
+        (Ok(left), Ok(right)) => left == right,
+        _ => left == right,
+    }
+}
+
+//  Sends each request to the server until the input ends or a request ends the
+//  session, as a client may keep its end open until the server exits
+fn forward_requests<R: BufRead>(reader: &mut R, sender: Sender<io::Result<JsonValue>>) {
+    loop {
+        let (message, is_last) = match read_message(reader) {
+            Ok(Some(message)) => {
+                let command = message.get("command").and_then(|command| command.as_str());
+                let is_last = matches!(command, Some("disconnect") | Some("terminate"));
+                (Ok(message), is_last)
+            },
+            Ok(None) => return,
+            Err(error) => (Err(error), true),
+        };
+        if sender.send(message).is_err() || is_last {
+            return;
+        }
+    }
+}
+
+//  Messages are JSON bodies behind a Content-Length header. Returns None at the end of the input
+fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<JsonValue>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            if length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let mut body = vec![0; length.unwrap_or(0)];
+    reader.read_exact(&mut body)?;
+    JsonValue::parse(&String::from_utf8_lossy(&body))
+        .map(Some)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+fn write_message<W: Write>(writer: &mut W, message: &JsonValue) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
@@ -0,0 +1,435 @@
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use synacorvm_core::svm_constants::NUM_OF_REGISTERS;
+use synacorvm_core::svm_engine_state::SVMEngineState;
+use super::svm_backtrace::{format_backtrace, format_stack_violation, get_return_address_slots};
+use super::svm_disassembler::{disassemble_instruction, disassemble_range};
+use super::svm_engine::{SVMEngine, SVMTermination};
+use super::svm_memory_search::{SVMMemorySearch, SearchFilter, parse_value};
+use super::svm_program::SVMProgram;
+use super::svm_snapshot::{save_snapshot, load_snapshot};
+use super::svm_state_diff::SVMStateDiff;
+use super::svm_symbols::{DataType, parse_number};
+
+const HELP_TEXT: &str = "Commands (addresses can be numbers, labels or label+offset):
+  step [n]              Execute n instructions (default 1)
+  continue              Run until a breakpoint, halt or error
+  disasm [addr] [n]     Disassemble n instructions (default 10) from addr or the IP
+  break <addr>          Set a breakpoint
+  delete <addr>         Remove a breakpoint
+  breakpoints           List breakpoints
+  regs                  Show the instruction pointer and registers
+  status                Show execution counters and memory sharing with the mark
+  stack                 Show the stack, top first, with return addresses marked
+  backtrace             Show the call stack
+  violations            Show recent stack discipline violations
+  mem <addr> [count]    Show memory words
+  mark                  Remember the current state for diff
+  diff [program]        Show what changed since the mark, or since the program was loaded
+  search <value|any>    Start a memory search for a value, or for any value
+  filter <condition>    Narrow the search: changed, unchanged, increased, decreased, equals <n>
+  candidates [n]        List up to n remaining search candidates (default 20)
+  label <addr> <name>   Name an address
+  function <addr> <name>  Mark the start of a function
+  comment <addr> <text> Attach a comment to an address
+  type <addr> <code|string|table> [length]  Set the data type of a region
+  unannotate <addr>     Remove all annotations at an address
+  symbols [save [file]] List annotations, or save them back to the symbol file
+  save <file>           Save a snapshot of the current state
+  load <file>           Restore a snapshot
+  quit                  Leave the debugger";
+
+pub struct SVMDebugger {
+    program: SVMProgram,
+    engine: SVMEngine,
+    breakpoints: BTreeSet<u16>,
+    mark: Option<SVMEngineState>,
+    search: Option<SVMMemorySearch>,
+}
+
+impl SVMDebugger {
+    pub fn new(program: SVMProgram) -> SVMDebugger {
+        SVMDebugger {
+            engine: SVMEngine::new(program.clone()),
+            program,
+            breakpoints: BTreeSet::new(),
+            mark: None,
+            search: None,
+        }
+    }
+
+    pub fn get_engine_mut(&mut self) -> &mut SVMEngine {
+        &mut self.engine
+    }
+
+    pub fn run(&mut self) {
+        let stdin = io::stdin();
+        loop {
+            print!("(svm {}) ", self.engine.get_state().instruction_pointer.get_ip());
+            io::stdout().flush().unwrap_or_default();
+            let mut line = String::new();
+            match stdin.lock().read_line(&mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {},
+            }
+            if !self.execute_command(line.trim()) {
+                break;
+            }
+        }
+    }
+
+    //  Returns false when the user asked to leave the debugger
+    fn execute_command(&mut self, line: &str) -> bool {
+        let arguments: Vec<&str> = line.split_whitespace().collect();
+        let command = match arguments.first() {
+            Some(command) => *command,
+            None => return true,
+        };
+
+        let result = match command {
+            "s" | "step" => self.step(optional_number(arguments.get(1), 1)),
+            "c" | "continue" => self.continue_execution(),
+            "b" | "break" => self.required_address(arguments.get(1)).map(|address| {
+                self.breakpoints.insert(address);
+            }),
+            "d" | "delete" => self.required_address(arguments.get(1)).map(|address| {
+                self.breakpoints.remove(&address);
+            }),
+            "breakpoints" => {
+                for address in self.breakpoints.iter() {
+                    println!("  {} ({})", address, self.engine.get_symbols().format_address(*address));
+                }
+                Ok(())
+            },
+            "disasm" => self.disassemble(arguments.get(1), arguments.get(2)),
+            "r" | "regs" => {
+                self.print_registers();
+                Ok(())
+            },
+            "status" => {
+                self.print_status();
+                Ok(())
+            },
+            "stack" => {
+                self.print_stack();
+                Ok(())
+            },
+            "bt" | "backtrace" => {
+                for line in format_backtrace(self.engine.get_state(), self.engine.get_symbols()) {
+                    println!("{}", line);
+                }
+                Ok(())
+            },
+            "violations" => {
+                let engine_state = self.engine.get_state();
+                println!("{} stack discipline violation(s)", engine_state.stack_violation_count);
+                for violation in engine_state.stack_violations.iter() {
+                    println!("  {}", format_stack_violation(violation, self.engine.get_symbols()));
+                }
+                Ok(())
+            },
+            "m" | "mem" => self.required_address(arguments.get(1)).and_then(|address| {
+                self.print_memory(address, optional_number(arguments.get(2), 8)?);
+                Ok(())
+            }),
+            "mark" => {
+                self.mark = Some(self.engine.get_state().clone());
+                Ok(())
+            },
+            "diff" => self.diff(arguments.get(1).cloned()),
+            "search" => self.start_search(arguments.get(1).cloned()),
+            "filter" => self.filter_search(&arguments[1..]),
+            "candidates" => optional_number(arguments.get(1), 20).map(|count| self.print_candidates(count as usize)),
+            "label" | "function" | "comment" | "type" => self.annotate(command, &arguments[1..], line),
+            "unannotate" => self.required_address(arguments.get(1)).map(|address| {
+                self.engine.get_symbols_mut().remove(address);
+            }),
+            "symbols" => self.symbols(&arguments[1..]),
+            "save" => match arguments.get(1) {
+                Some(path) => save_snapshot(self.engine.get_state(), Path::new(path)).map_err(|error| error.to_string()),
+                None => Err("save requires a file name".to_string()),
+            },
+            "load" => match arguments.get(1) {
+                Some(path) => load_snapshot(Path::new(path))
+                    .map(|engine_state| self.engine.set_state(engine_state))
+                    .map_err(|error| error.to_string()),
+                None => Err("load requires a file name".to_string()),
+            },
+            "h" | "help" => {
+                println!("{}", HELP_TEXT);
+                Ok(())
+            },
+            "q" | "quit" => return false,
+            _ => Err(format!("Unknown command {}, try help", command)),
+        };
+
+        if let Err(error) = result {
+            println!("{}", error);
+        }
+        true
+    }
+
+    fn step(&mut self, count: Result<u16, String>) -> Result<(), String> {
+        for _ in 0..count? {
+            if !self.single_step() {
+                break;
+            }
+        }
+        self.print_current_instruction();
+        Ok(())
+    }
+
+    fn continue_execution(&mut self) -> Result<(), String> {
+        if self.engine.get_state().halted {
+            println!("The program has halted.");
+            return Ok(());
+        }
+        let violation_count = self.engine.get_state().stack_violation_count;
+        let breakpoints = &self.breakpoints;
+        let termination = self.engine.run_until(|engine_state| {
+            breakpoints.contains(&engine_state.instruction_pointer.get_ip())
+                || engine_state.stack_violation_count != violation_count
+        });
+        let engine_state = self.engine.get_state();
+        match termination {
+            SVMTermination::Stopped if engine_state.stack_violation_count != violation_count => {
+                if let Some(violation) = engine_state.stack_violations.back() {
+                    println!("Stack discipline violation at {}", format_stack_violation(violation, self.engine.get_symbols()));
+                }
+            },
+            SVMTermination::Stopped => {
+                println!("Breakpoint at {}", self.engine.get_symbols().format_address(engine_state.instruction_pointer.get_ip()));
+            },
+            SVMTermination::Halted => println!("Halted."),
+            SVMTermination::Error(ref error) => self.engine.print_error(error),
+            other => println!("Stopped: {}", other.get_description()),
+        }
+        self.print_current_instruction();
+        Ok(())
+    }
+
+    fn print_current_instruction(&self) {
+        let engine_state = self.engine.get_state();
+        if engine_state.halted {
+            return;
+        }
+        let ip = engine_state.instruction_pointer.get_ip();
+        let instruction = disassemble_instruction(&engine_state.memory, ip, self.engine.get_symbols(), self.engine.get_extensions());
+        println!("{}: {}", self.engine.get_symbols().format_address(ip), instruction.text);
+    }
+
+    fn disassemble(&self, address: Option<&&str>, count: Option<&&str>) -> Result<(), String> {
+        let engine_state = self.engine.get_state();
+        let start = match address {
+            Some(_) => self.required_address(address)?,
+            None => engine_state.instruction_pointer.get_ip(),
+        };
+        let count = optional_number(count, 10)? as usize;
+        let ip = engine_state.instruction_pointer.get_ip();
+        let mut printed = 0;
+        for line in disassemble_range(&engine_state.memory, start, u16::MAX, self.engine.get_symbols(), self.engine.get_extensions()) {
+            let is_instruction = !line.is_empty() && !line.ends_with(':');
+            if is_instruction && printed == count {
+                break;
+            }
+            let at_ip = is_instruction && line.trim_start().starts_with(&format!("{}:", ip));
+            println!("{} {}", if at_ip { "=>" } else { "  " }, line);
+            if is_instruction {
+                printed += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn annotate(&mut self, command: &str, arguments: &[&str], line: &str) -> Result<(), String> {
+        let address = self.required_address(arguments.first())?;
+        let symbols = self.engine.get_symbols_mut();
+        match (command, arguments.get(1)) {
+            ("label", Some(name)) => symbols.set_label(address, name),
+            ("function", Some(name)) => symbols.set_function(address, name),
+            ("comment", Some(_)) => {
+                //  Keep the comment text as typed, including its spacing
+                let text = skip_word(skip_word(line)).trim();
+                symbols.set_comment(address, text);
+            },
+            ("type", Some(kind)) => {
+                let data_type = DataType::parse(kind).ok_or("Type must be code, string or table")?;
+                symbols.set_data_type(address, data_type, optional_number(arguments.get(2), 1)?);
+            },
+            _ => return Err(format!("{} requires an address and a value", command)),
+        }
+        Ok(())
+    }
+
+    fn symbols(&mut self, arguments: &[&str]) -> Result<(), String> {
+        match arguments.first() {
+            Some(&"save") => self.engine.get_symbols_mut()
+                .save(arguments.get(1).map(Path::new))
+                .map_err(|error| error.to_string()),
+            Some(other) => Err(format!("Unknown symbols command {}", other)),
+            None => {
+                for (address, annotation) in self.engine.get_symbols().iter() {
+                    let mut parts = Vec::new();
+                    if let Some(ref label) = annotation.label {
+                        parts.push(format!("label {}", label));
+                    }
+                    if let Some(ref function) = annotation.function {
+                        parts.push(format!("function {}", function));
+                    }
+                    if let Some((data_type, length)) = annotation.data_type {
+                        parts.push(format!("{} {}", data_type.get_name(), length));
+                    }
+                    let mut line = format!("  {:5}: {}", address, parts.join(", "));
+                    if let Some(ref comment) = annotation.comment {
+                        line = format!("{} ; {}", line, comment);
+                    }
+                    println!("{}", line);
+                }
+                Ok(())
+            },
+        }
+    }
+
+    fn required_address(&self, argument: Option<&&str>) -> Result<u16, String> {
+        match argument {
+            Some(text) => self.engine.get_symbols().parse_address(text),
+            None => Err("Missing address".to_string()),
+        }
+    }
+
+    //  Returns false when execution cannot continue
+    fn single_step(&mut self) -> bool {
+        if self.engine.get_state().halted {
+            println!("The program has halted.");
+            return false;
+        }
+        match self.engine.step() {
+            Ok(_) if self.engine.get_state().halted => {
+                println!("Halted.");
+                false
+            },
+            Ok(_) => true,
+            Err(error) => {
+                self.engine.print_error(&error);
+                false
+            },
+        }
+    }
+
+    fn diff(&self, against: Option<&str>) -> Result<(), String> {
+        let diff = match against {
+            Some("program") => SVMStateDiff::from_program(&self.program, self.engine.get_state()),
+            Some(other) => return Err(format!("Cannot diff against {}", other)),
+            None => match self.mark {
+                Some(ref mark) => SVMStateDiff::between(mark, self.engine.get_state()),
+                None => return Err("No mark set, use mark first".to_string()),
+            },
+        };
+        diff.print();
+        Ok(())
+    }
+
+    fn start_search(&mut self, value: Option<&str>) -> Result<(), String> {
+        let memory = &self.engine.get_state().memory;
+        let search = match value {
+            Some("any") => SVMMemorySearch::scan_all(memory),
+            Some(value) => SVMMemorySearch::scan_exact(memory, parse_value(value)?),
+            None => return Err("search requires a value or any".to_string()),
+        };
+        println!("{} candidate(s)", search.get_candidates().len());
+        self.search = Some(search);
+        Ok(())
+    }
+
+    fn filter_search(&mut self, arguments: &[&str]) -> Result<(), String> {
+        let filter = SearchFilter::parse(arguments)?;
+        match self.search {
+            Some(ref mut search) => {
+                search.refine(&self.engine.get_state().memory, &filter);
+                println!("{} candidate(s)", search.get_candidates().len());
+                Ok(())
+            },
+            None => Err("No search in progress, use search first".to_string()),
+        }
+    }
+
+    fn print_candidates(&self, count: usize) {
+        match self.search {
+            Some(ref search) => {
+                for candidate in search.get_candidates().iter().take(count) {
+                    println!("  {}: {}", candidate.address, candidate.value);
+                }
+                if search.get_candidates().len() > count {
+                    println!("  ... {} more", search.get_candidates().len() - count);
+                }
+            },
+            None => println!("No search in progress, use search first"),
+        }
+    }
+
+    fn print_registers(&self) {
+        let engine_state = self.engine.get_state();
+        println!("ip: {}", engine_state.instruction_pointer.get_ip());
+        for index in 0..NUM_OF_REGISTERS {
+            println!("r{}: {}", index, engine_state.registers.get_register_by_index(index));
+        }
+    }
+
+    fn print_status(&self) {
+        let engine_state = self.engine.get_state();
+        println!("Instructions executed: {}", engine_state.instruction_count);
+        println!("Output bytes: {}, input reads: {}", engine_state.output_bytes, engine_state.input_reads);
+        println!("Halted: {}", engine_state.halted);
+        if let Some(ref mark) = self.mark {
+            println!("Memory pages shared with the mark: {}", engine_state.memory.get_pages_shared_with(&mark.memory));
+        }
+    }
+
+    fn print_stack(&self) {
+        let engine_state = self.engine.get_state();
+        let return_slots = get_return_address_slots(engine_state);
+        println!("Stack depth {}", engine_state.stack.len());
+        for (index, value) in engine_state.stack.iter().enumerate().rev() {
+            if return_slots.contains(&index) {
+                println!("  [{}]: {} <- return to {}", index, value, self.engine.get_symbols().format_address(*value));
+            } else {
+                println!("  [{}]: {}", index, value);
+            }
+        }
+    }
+
+    fn print_memory(&self, address: u16, count: u16) {
+        let memory = &self.engine.get_state().memory;
+        let mut line = String::new();
+        for offset in 0..count {
+            let current = address.saturating_add(offset);
+            if offset % 8 == 0 {
+                if !line.is_empty() {
+                    println!("{}", line);
+                }
+                line = format!("{:5}:", current);
+            }
+            match memory.load_memory(current) {
+                Ok(value) => line.push_str(&format!(" {:5}", value)),
+                Err(_) => break,
+            }
+        }
+        if !line.is_empty() {
+            println!("{}", line);
+        }
+    }
+}
+
+//  The rest of the line after its first word, however much space surrounds it
+fn skip_word(line: &str) -> &str {
+    let line = line.trim_start();
+    line.find(char::is_whitespace).map(|end| &line[end..]).unwrap_or("")
+}
+
+fn optional_number(argument: Option<&&str>, default: u16) -> Result<u16, String> {
+    match argument {
+        Some(text) => parse_number(text),
+        None => Ok(default),
+    }
+}
@@ -0,0 +1,264 @@
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use synacorvm_core::devices::DeviceBus;
+use synacorvm_core::svm_constants::MEMORY_SIZE_MAX;
+use synacorvm_core::svm_engine_state::SVMEngineState;
+use synacorvm_core::svm_error::SVMError;
+use super::svm_random::XorShift64Star;
+
+//  Values a device hands the program are machine numbers, so 15 bits at a time
+const WORD_BITS: u32 = 15;
+const WORD_MASK: u64 = 0x7FFF;
+//  What the console device reads when there is no buffered input
+pub const NO_INPUT: u16 = 0x7FFF;
+
+//  Something the program talks to through rmem and wmem on a range of
+//  addresses. Offsets are relative to where the device is mapped.
+pub trait SVMDevice {
+    fn get_name(&self) -> &str;
+
+    //  How many addresses the device takes up
+    fn get_length(&self) -> u16;
+
+    fn read(&mut self, offset: u16, engine_state: &mut SVMEngineState) -> Result<u16, SVMError>;
+
+    fn write(&mut self, offset: u16, value: u16, engine_state: &mut SVMEngineState) -> Result<(), SVMError>;
+
+    //  A copy in the same state, for forked engines
+    fn box_clone(&self) -> Box<dyn SVMDevice>;
+}
+
+struct MappedDevice {
+    start: u16,
+    device: Box<dyn SVMDevice>,
+}
+
+impl Clone for MappedDevice {
+    fn clone(&self) -> MappedDevice {
+        MappedDevice {
+            start: self.start,
+            device: self.device.box_clone(),
+        }
+    }
+}
+
+//  The devices an engine has and where they are
+#[derive(Clone, Default)]
+pub struct SVMDeviceMap {
+    devices: Vec<MappedDevice>,
+}
+
+impl SVMDeviceMap {
+    pub fn new() -> SVMDeviceMap {
+        SVMDeviceMap::default()
+    }
+
+    pub fn map(&mut self, start: u16, device: Box<dyn SVMDevice>) -> Result<(), String> {
+        let end = start as usize + device.get_length() as usize;
+        if device.get_length() == 0 || end > MEMORY_SIZE_MAX {
+            return Err(format!("{} does not fit at {}", device.get_name(), start));
+        }
+        if let Some(other) = self.devices.iter().find(|other| (start as usize) < other.end() && (other.start as usize) < end) {
+            return Err(format!("{} at {} overlaps {} at {}", device.get_name(), start, other.device.get_name(), other.start));
+        }
+        self.devices.push(MappedDevice { start, device });
+        Ok(())
+    }
+
+    //  Where each device is mapped, with its name and length
+    pub fn get_mappings(&self) -> Vec<(u16, u16, String)> {
+        self.devices.iter()
+            .map(|mapped| (mapped.start, mapped.device.get_length(), mapped.device.get_name().to_string()))
+            .collect()
+    }
+
+    fn find(&mut self, address: u16) -> Option<(u16, &mut Box<dyn SVMDevice>)> {
+        self.devices.iter_mut()
+            .find(|mapped| mapped.start <= address && (address as usize) < mapped.end())
+            .map(|mapped| (address - mapped.start, &mut mapped.device))
+    }
+}
+
+impl MappedDevice {
+    fn end(&self) -> usize {
+        self.start as usize + self.device.get_length() as usize
+    }
+}
+
+impl DeviceBus for SVMDeviceMap {
+    fn read(&mut self, engine_state: &mut SVMEngineState, address: u16) -> Option<Result<u16, SVMError>> {
+        self.find(address).map(|(offset, device)| device.read(offset, engine_state))
+    }
+
+    fn write(&mut self, engine_state: &mut SVMEngineState, address: u16, value: u16) -> Option<Result<(), SVMError>> {
+        self.find(address).map(|(offset, device)| device.write(offset, value, engine_state))
+    }
+}
+
+//  The device for a name given on the command line
+pub fn create_device(name: &str) -> Option<Box<dyn SVMDevice>> {
+    match name {
+        "cycles" => Some(Box::new(CycleCounter::new())),
+        "random" => {
+            let seed = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_nanos() as u64).unwrap_or(0);
+            Some(Box::new(RandomSource::new(seed)))
+        },
+        "clock" => Some(Box::new(MillisecondClock::new())),
+        "console" => Some(Box::new(ConsoleDevice)),
+        _ => None,
+    }
+}
+
+//  Instructions executed, 45 bits over three words from least significant and read only.
+//  Reading the first word latches the count so the others match it.
+#[derive(Clone)]
+pub struct CycleCounter {
+    latched: u64,
+}
+
+impl CycleCounter {
+    pub fn new() -> CycleCounter {
+        CycleCounter { latched: 0 }
+    }
+}
+
+impl Default for CycleCounter {
+    fn default() -> CycleCounter {
+        CycleCounter::new()
+    }
+}
+
+impl SVMDevice for CycleCounter {
+    fn get_name(&self) -> &str {
+        "cycles"
+    }
+
+    fn get_length(&self) -> u16 {
+        3
+    }
+
+    fn read(&mut self, offset: u16, engine_state: &mut SVMEngineState) -> Result<u16, SVMError> {
+        if offset == 0 {
+            self.latched = engine_state.instruction_count;
+        }
+        Ok(((self.latched >> (offset as u32 * WORD_BITS)) & WORD_MASK) as u16)
+    }
+
+    fn write(&mut self, _offset: u16, _value: u16, _engine_state: &mut SVMEngineState) -> Result<(), SVMError> {
+        Err(SVMError::InvalidMemory)
+    }
+
+    fn box_clone(&self) -> Box<dyn SVMDevice> {
+        Box::new(self.clone())
+    }
+}
+
+//  15-bit random numbers from xorshift64*. Writing a word reseeds it, so a
+//  program can make its runs repeatable.
+#[derive(Clone)]
+pub struct RandomSource {
+    random: XorShift64Star,
+}
+
+impl RandomSource {
+    pub fn new(seed: u64) -> RandomSource {
+        RandomSource { random: XorShift64Star::new(seed) }
+    }
+}
+
+impl SVMDevice for RandomSource {
+    fn get_name(&self) -> &str {
+        "random"
+    }
+
+    fn get_length(&self) -> u16 {
+        1
+    }
+
+    fn read(&mut self, _offset: u16, _engine_state: &mut SVMEngineState) -> Result<u16, SVMError> {
+        Ok((self.random.next_u64() >> 49) as u16)
+    }
+
+    fn write(&mut self, _offset: u16, value: u16, _engine_state: &mut SVMEngineState) -> Result<(), SVMError> {
+        self.random = XorShift64Star::from_small_seed(value as u64);
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn SVMDevice> {
+        Box::new(self.clone())
+    }
+}
+
+//  Milliseconds since the clock was mapped or last reset, 30 bits over two
+//  words latched like the cycle counter. Writing resets it to zero.
+#[derive(Clone)]
+pub struct MillisecondClock {
+    started: Instant,
+    latched: u64,
+}
+
+impl MillisecondClock {
+    pub fn new() -> MillisecondClock {
+        MillisecondClock { started: Instant::now(), latched: 0 }
+    }
+}
+
+impl Default for MillisecondClock {
+    fn default() -> MillisecondClock {
+        MillisecondClock::new()
+    }
+}
+
+impl SVMDevice for MillisecondClock {
+    fn get_name(&self) -> &str {
+        "clock"
+    }
+
+    fn get_length(&self) -> u16 {
+        2
+    }
+
+    fn read(&mut self, offset: u16, _engine_state: &mut SVMEngineState) -> Result<u16, SVMError> {
+        if offset == 0 {
+            self.latched = self.started.elapsed().as_millis() as u64;
+        }
+        Ok(((self.latched >> (offset as u32 * WORD_BITS)) & WORD_MASK) as u16)
+    }
+
+    fn write(&mut self, _offset: u16, _value: u16, _engine_state: &mut SVMEngineState) -> Result<(), SVMError> {
+        self.started = Instant::now();
+        self.latched = 0;
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn SVMDevice> {
+        Box::new(self.clone())
+    }
+}
+
+//  Writing a character outputs it like out does. Reading takes the next
+//  byte of input already buffered, or NO_INPUT, and never waits.
+#[derive(Clone)]
+pub struct ConsoleDevice;
+
+impl SVMDevice for ConsoleDevice {
+    fn get_name(&self) -> &str {
+        "console"
+    }
+
+    fn get_length(&self) -> u16 {
+        1
+    }
+
+    fn read(&mut self, _offset: u16, engine_state: &mut SVMEngineState) -> Result<u16, SVMError> {
+        Ok(engine_state.input_buffer.pop_front().map(|byte| byte as u16).unwrap_or(NO_INPUT))
+    }
+
+    fn write(&mut self, _offset: u16, value: u16, engine_state: &mut SVMEngineState) -> Result<(), SVMError> {
+        engine_state.output_buffer.push(value as u8);
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn SVMDevice> {
+        Box::new(self.clone())
+    }
+}
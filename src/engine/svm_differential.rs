@@ -0,0 +1,200 @@
+use std::fmt;
+use super::svm_disassembler::disassemble_instruction;
+use super::svm_engine::SVMEngine;
+use super::svm_program::SVMProgram;
+use super::svm_random::XorShift64Star;
+use super::svm_reference::{ReferenceStep, SVMReference};
+use super::svm_symbols::SVMSymbols;
+use synacorvm_core::svm_constants::NUM_OF_REGISTERS;
+use synacorvm_core::opcode::OpcodeValue;
+use synacorvm_core::svm_error::SVMError;
+
+//  Generated programs write data into this range, away from their code
+const DATA_START: u16 = 1000;
+const DATA_SIZE: u16 = 64;
+
+//  The first place the engine and the reference interpreter disagree
+pub struct Divergence {
+    pub step: u64,
+    pub address: u16,
+    pub instruction: String,
+    pub difference: String,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "step {} at {} ({}): {}", self.step, self.address, self.instruction, self.difference)
+    }
+}
+
+//  Runs the program in the engine and the reference in lockstep, comparing
+//  them after every instruction. Returns the number of steps run when they agree
+//  until both stop or max_steps is reached.
+pub fn run_differential(program: &[u16], input: &[u8], max_steps: u64) -> Result<u64, Divergence> {
+    let mut engine = SVMEngine::new(SVMProgram::from_words(program.to_vec()));
+    engine.detach_console();
+    engine.push_input(input);
+    let mut reference = SVMReference::new(program, input);
+
+    let mut steps = 0;
+    while steps < max_steps {
+        let address = reference.ip;
+        let instruction = disassemble_instruction(&engine.get_state().memory, address, &SVMSymbols::new(), engine.get_extensions()).text;
+        let diverged = |difference: String| Divergence { step: steps, address, instruction: instruction.clone(), difference };
+
+        let engine_result = engine.step();
+        let reference_step = reference.step();
+        match (&engine_result, reference_step) {
+            (Ok(_), ReferenceStep::Ran) | (Ok(_), ReferenceStep::Halted) => {},
+            (Err(SVMError::AwaitingInput), ReferenceStep::AwaitingInput) | (Err(_), ReferenceStep::Failed(_)) => break,
+            (Ok(_), reference_step) => return Err(diverged(format!("the engine ran but the reference {:?}", reference_step))),
+            (Err(error), reference_step) => return Err(diverged(format!(
+                "the engine failed with {} but the reference {:?}", error.get_description(), reference_step))),
+        }
+        if let Some(difference) = compare(&engine, &reference) {
+            return Err(diverged(difference));
+        }
+        steps += 1;
+        if reference.halted {
+            break;
+        }
+    }
+
+    match compare_memory(&engine, &reference) {
+        Some(difference) => Err(Divergence { step: steps, address: reference.ip, instruction: "after the last step".to_string(), difference }),
+        None => Ok(steps),
+    }
+}
+
+//  Everything but memory, which is only compared where the last instruction wrote
+fn compare(engine: &SVMEngine, reference: &SVMReference) -> Option<String> {
+    let state = engine.get_state();
+    if state.instruction_pointer.get_ip() != reference.ip {
+        return Some(format!("ip is {} in the engine and {} in the reference", state.instruction_pointer.get_ip(), reference.ip));
+    }
+    if state.halted != reference.halted {
+        return Some(format!("halted is {} in the engine and {} in the reference", state.halted, reference.halted));
+    }
+    let registers: Vec<u16> = (0..NUM_OF_REGISTERS).map(|index| state.registers.get_register_by_index(index)).collect();
+    if registers[..] != reference.registers[..] {
+        return Some(format!("registers are {:?} in the engine and {:?} in the reference", registers, reference.registers));
+    }
+    if state.stack != reference.stack {
+        return Some(format!("the stack is {:?} in the engine and {:?} in the reference", state.stack, reference.stack));
+    }
+    if state.output_buffer != reference.output {
+        return Some(format!("output is {:?} in the engine and {:?} in the reference",
+            String::from_utf8_lossy(&state.output_buffer), String::from_utf8_lossy(&reference.output)));
+    }
+    let engine_write = state.last_memory_write.as_ref().map(|write| write.address);
+    if engine_write != reference.last_write {
+        return Some(format!("the engine wrote {:?} and the reference wrote {:?}", engine_write, reference.last_write));
+    }
+    match reference.last_write {
+        Some(address) => compare_word(engine, reference, address),
+        None => None,
+    }
+}
+
+fn compare_memory(engine: &SVMEngine, reference: &SVMReference) -> Option<String> {
+    (0..reference.memory.len() as u16).find_map(|address| compare_word(engine, reference, address))
+}
+
+fn compare_word(engine: &SVMEngine, reference: &SVMReference, address: u16) -> Option<String> {
+    let value = engine.get_state().memory.load_memory(address).ok();
+    let expected = reference.memory[address as usize];
+    if value == Some(expected) {
+        return None;
+    }
+    Some(format!("memory at {} is {:?} in the engine and {} in the reference", address, value, expected))
+}
+
+//  Generated cases are reproducible from their seed alone
+pub struct CaseGenerator {
+    random: XorShift64Star,
+}
+
+impl CaseGenerator {
+    pub fn new(seed: u64) -> CaseGenerator {
+        CaseGenerator { random: XorShift64Star::from_small_seed(seed) }
+    }
+
+    fn below(&mut self, bound: u64) -> u16 {
+        (self.random.next_u64() % bound) as u16
+    }
+
+    //  A well formed program of the given number of instructions. Jumps mostly
+    //  land on instruction starts, data goes to a small area after the code, and
+    //  operands favour registers and edge values.
+    pub fn generate_program(&mut self, instructions: usize) -> Vec<u16> {
+        let opcodes: Vec<u16> = (0..instructions)
+            .map(|_| if self.below(40) == 0 { 0 } else { 1 + self.below(21) })
+            .collect();
+        let mut starts = Vec::new();
+        let mut address = 0;
+        for opcode in opcodes.iter() {
+            starts.push(address);
+            address += 1 + operand_count(*opcode);
+        }
+
+        let mut program = Vec::new();
+        for opcode in opcodes {
+            program.push(opcode);
+            for index in 0..operand_count(opcode) {
+                let operand = match (opcode, index) {
+                    (1 | 3 | 4 | 5 | 9..=15 | 20, 0) => self.destination(),
+                    (6 | 17, 0) | (7 | 8, 1) => self.jump_target(&starts),
+                    (15, 1) | (16, 0) => self.address(program.len() as u16 + 4),
+                    _ => self.value(),
+                };
+                program.push(operand);
+            }
+        }
+        program
+    }
+
+    pub fn generate_input(&mut self, length: usize) -> Vec<u8> {
+        (0..length).map(|_| b"ab \n"[self.below(4) as usize]).collect()
+    }
+
+    fn register(&mut self) -> u16 {
+        32768 + self.below(8)
+    }
+
+    fn value(&mut self) -> u16 {
+        match self.below(8) {
+            0..=3 => self.register(),
+            4 => [0, 1, 2, 32767][self.below(4) as usize],
+            5 => self.below(32768),
+            _ => self.below(100),
+        }
+    }
+
+    fn destination(&mut self) -> u16 {
+        match self.below(5) {
+            0 => DATA_START + self.below(DATA_SIZE as u64),
+            _ => self.register(),
+        }
+    }
+
+    fn jump_target(&mut self, starts: &[u16]) -> u16 {
+        match self.below(10) {
+            0 => self.register(),
+            1 => self.below(starts.last().cloned().unwrap_or(0) as u64 + 4),
+            _ => starts[self.below(starts.len() as u64) as usize],
+        }
+    }
+
+    //  Reads and writes mostly hit the data area, sometimes the code itself
+    fn address(&mut self, code_size: u16) -> u16 {
+        match self.below(6) {
+            0 => self.register(),
+            1 => self.below(code_size as u64),
+            _ => DATA_START + self.below(DATA_SIZE as u64),
+        }
+    }
+}
+
+fn operand_count(opcode: u16) -> u16 {
+    opcode.get_opcode().map(|opcode| opcode.get_operand_count()).unwrap_or(0)
+}
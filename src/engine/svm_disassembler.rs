@@ -0,0 +1,132 @@
+use synacorvm_core::extensions::RegisterValue;
+use synacorvm_core::memory::Memory;
+use synacorvm_core::opcode::{ExtensionSet, OpcodeValue, SVMOpCode};
+use synacorvm_core::svm_constants::MEMORY_SIZE_MAX;
+use super::svm_symbols::{DataType, SVMSymbols};
+
+pub struct DisassembledInstruction {
+    pub address: u16,
+    pub length: u16,
+    pub text: String,
+}
+
+//  Disassembles the instruction at the address. Words that are not a valid
+//  opcode in the extension set, or whose operands run off the end of memory,
+//  come out as data
+pub fn disassemble_instruction(memory: &Memory, address: u16, symbols: &SVMSymbols, extensions: ExtensionSet) -> DisassembledInstruction {
+    let word = memory.load_memory(address).unwrap_or(0);
+    let opcode = match word.get_opcode_with(extensions) {
+        Ok(opcode) => opcode,
+        Err(_) => return data_word(address, word),
+    };
+
+    let mut text = opcode.get_mnemonic().to_string();
+    let operand_count = opcode.get_operand_count();
+    for index in 0..operand_count {
+        let operand = match memory.load_memory(address.wrapping_add(1 + index)) {
+            Ok(operand) => operand,
+            Err(_) => return data_word(address, word),
+        };
+        text.push(' ');
+        text.push_str(&format_operand(operand, opcode.get_address_operand() == Some(index as usize),
+            matches!(opcode, SVMOpCode::Out), symbols));
+    }
+
+    DisassembledInstruction {
+        address,
+        length: operand_count + 1,
+        text,
+    }
+}
+
+pub fn format_operand(operand: u16, is_address: bool, is_character: bool, symbols: &SVMSymbols) -> String {
+    if operand.is_valid_register() {
+        format!("r{}", operand.get_register_index())
+    } else if is_address {
+        symbols.format_address(operand)
+    } else if is_character {
+        format_character(operand)
+    } else {
+        operand.to_string()
+    }
+}
+
+//  Disassembles [start, end) into listing lines, honouring labels, comments and data annotations
+pub fn disassemble_range(memory: &Memory, start: u16, end: u16, symbols: &SVMSymbols, extensions: ExtensionSet) -> Vec<String> {
+    let end = end.min(MEMORY_SIZE_MAX as u16);
+    let mut lines = Vec::new();
+    let mut address = start;
+    while address < end {
+        let annotation = symbols.get(address);
+        if let Some(annotation) = annotation {
+            if let Some(ref function) = annotation.function {
+                lines.push(String::new());
+                lines.push(format!("function {}:", function));
+            }
+            if let Some(ref label) = annotation.label {
+                lines.push(format!("{}:", label));
+            }
+        }
+
+        let instruction = match annotation.and_then(|annotation| annotation.data_type) {
+            Some((DataType::String, length)) => data_string(memory, address, length),
+            Some((DataType::Table, length)) => data_table(memory, address, length),
+            _ => disassemble_instruction(memory, address, symbols, extensions),
+        };
+        let mut line = format!("{:6}: {}", instruction.address, instruction.text);
+        if let Some(comment) = annotation.and_then(|annotation| annotation.comment.as_ref()) {
+            line = format!("{:40} ; {}", line, comment);
+        }
+        lines.push(line);
+        address = match address.checked_add(instruction.length.max(1)) {
+            Some(next) => next,
+            None => break,
+        };
+    }
+    lines
+}
+
+fn data_word(address: u16, word: u16) -> DisassembledInstruction {
+    DisassembledInstruction {
+        address,
+        length: 1,
+        text: format!("data {}", word),
+    }
+}
+
+fn data_string(memory: &Memory, address: u16, length: u16) -> DisassembledInstruction {
+    let mut text = String::from("data \"");
+    for offset in 0..length {
+        let value = memory.load_memory(address.wrapping_add(offset)).unwrap_or(0);
+        match value {
+            0x0A => text.push_str("\\n"),
+            0x22 => text.push_str("\\\""),
+            0x5C => text.push_str("\\\\"),
+            0x20..=0x7E => text.push(value as u8 as char),
+            _ => text.push_str(&format!("\\x{:02x}", value & 0xFF)),
+        }
+    }
+    text.push('"');
+    DisassembledInstruction { address, length, text }
+}
+
+fn data_table(memory: &Memory, address: u16, length: u16) -> DisassembledInstruction {
+    let values: Vec<String> = (0..length)
+        .map(|offset| memory.load_memory(address.wrapping_add(offset)).unwrap_or(0).to_string())
+        .collect();
+    DisassembledInstruction {
+        address,
+        length,
+        text: format!("data {}", values.join(" ")),
+    }
+}
+
+fn format_character(value: u16) -> String {
+    match value {
+        0x0A => "'\\n'".to_string(),
+        0x27 => "'\\''".to_string(),
+        0x5C => "'\\\\'".to_string(),
+        0x20..=0x7E => format!("'{}'", value as u8 as char),
+        _ => value.to_string(),
+    }
+}
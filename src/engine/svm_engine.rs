@@ -1,50 +1,544 @@
-use super::internals::svm_engine_state::SVMEngineState;
-use super::internals::svm_error::SVMError;
-use super::internals::opcode::{OpcodeValue, OpCode};
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+use synacorvm_core::svm_engine_state::SVMEngineState;
+use synacorvm_core::svm_error::SVMError;
+use synacorvm_core::opcode::{ExtensionSet, OpcodeValue, OpCode, SVMOpCode};
+use synacorvm_core::svm_constants::NUM_OF_REGISTERS;
+use super::svm_checkpoints::SVMCheckpoints;
+use super::svm_code_tracker::SVMCodeTracker;
+use super::svm_coverage::SVMCoverage;
+use super::svm_devices::{SVMDevice, SVMDeviceMap};
+use super::svm_console::{SVMConsole, SVMStdConsole};
+use super::svm_disassembler::disassemble_instruction;
+#[cfg(feature = "host-calls")]
+use super::svm_host_calls::SVMHostCalls;
+use super::svm_fault_report::{format_fault_report, FAULT_HISTORY_LENGTH};
+use super::svm_limits::SVMLimits;
+use super::svm_meta_commands::execute_meta_command;
+use super::svm_profiler::SVMProfiler;
 use super::svm_program::SVMProgram;
+use super::svm_save_slots::SVMSaveSlots;
+use super::svm_snapshot::save_snapshot;
+use super::svm_symbols::SVMSymbols;
+
+//  Limits are only checked against the wall clock every this many instructions
+const TIME_CHECK_INTERVAL: u64 = 4096;
+
+//  Why run() or run_until() stopped
+pub enum SVMTermination {
+    Halted,
+    AwaitingInput,
+    Stopped,
+    Error(SVMError),
+    InstructionLimit,
+    TimeLimit,
+    StackLimit,
+    OutputLimit,
+    InputLimit,
+}
+
+impl SVMTermination {
+    pub fn get_description(&self) -> &'static str {
+        match *self {
+            SVMTermination::Halted => "halted",
+            SVMTermination::AwaitingInput => "waiting for input",
+            SVMTermination::Stopped => "stopped",
+            SVMTermination::Error(ref error) => error.get_description(),
+            SVMTermination::InstructionLimit => "instruction limit reached",
+            SVMTermination::TimeLimit => "time limit reached",
+            SVMTermination::StackLimit => "stack depth limit reached",
+            SVMTermination::OutputLimit => "output limit reached",
+            SVMTermination::InputLimit => "input limit reached",
+        }
+    }
+}
 
 pub struct SVMEngine {
-    engine_state: SVMEngineState
+    engine_state: SVMEngineState,
+    code_tracker: Option<SVMCodeTracker>,
+    profiler: Option<SVMProfiler>,
+    coverage: Option<SVMCoverage>,
+    symbols: Arc<SVMSymbols>,
+    trace: Option<Box<dyn Write>>,
+    limits: SVMLimits,
+    console: Option<Box<dyn SVMConsole>>,
+    devices: Option<SVMDeviceMap>,
+    //  The opcodes beyond the spec this machine decodes
+    extensions: ExtensionSet,
+    #[cfg(feature = "host-calls")]
+    host_calls: Option<SVMHostCalls>,
+    //  Addresses of the most recently executed instructions, oldest first
+    history: VecDeque<u16>,
+    fault_snapshot: Option<PathBuf>,
+    //  Console input lines starting with this are host commands rather than program input
+    meta_prefix: Option<String>,
+    //  Taken before each console input line, for undo
+    checkpoints: Option<SVMCheckpoints>,
+    save_slots: SVMSaveSlots,
+    //  The output line being written and the last non-empty one finished
+    output_line: Vec<u8>,
+    last_output_line: String,
+}
+
+//  Cloning forks the machine. The fork shares unmodified memory pages and the
+//  symbols with the original and gets copies of its devices and extensions,
+//  while the trace, analysis tools and console stay behind. Host calls go to
+//  the same sandbox, but files the original opened stay with it. Input for the
+//  fork is given with push_input and its output collected with take_output.
+impl Clone for SVMEngine {
+    fn clone(&self) -> SVMEngine {
+        SVMEngine {
+            engine_state: self.engine_state.clone(),
+            code_tracker: None,
+            profiler: None,
+            coverage: None,
+            symbols: self.symbols.clone(),
+            trace: None,
+            limits: self.limits.clone(),
+            console: None,
+            devices: self.devices.clone(),
+            extensions: self.extensions,
+            #[cfg(feature = "host-calls")]
+            host_calls: self.host_calls.as_ref().map(|host_calls| SVMHostCalls::new(host_calls.get_sandbox().to_path_buf())),
+            history: self.history.clone(),
+            fault_snapshot: None,
+            meta_prefix: self.meta_prefix.clone(),
+            checkpoints: None,
+            save_slots: SVMSaveSlots::new(self.save_slots.get_directory().to_path_buf()),
+            output_line: self.output_line.clone(),
+            last_output_line: self.last_output_line.clone(),
+        }
+    }
 }
 
 impl SVMEngine {
     pub fn new(program: SVMProgram) -> SVMEngine {
         SVMEngine {
-            engine_state: SVMEngineState::new(program.get_bytecode())
-        }
-    }
-
-    pub fn run(&mut self) {
-        loop {
-            let opcode_number = self.engine_state.instruction_pointer.get_next_memory_value(&self.engine_state.memory);
-            match opcode_number {
-                Ok(opcode_value) => { /*println!("IP:{}, Opcode Value:{}\n", self.engine_state.instruction_pointer.get_ip(), opcode_value); */match opcode_value.get_opcode() {
-                    Ok(opcode) => match opcode.dispatch(&mut self.engine_state) {
-                        Err(error) => self.print_error(error),
-                        _ => {}
-                    },
-                    Err(error) => self.print_error(error)
-                }},
-                Err(error) => self.print_error(error)
+            engine_state: program.create_state(),
+            code_tracker: None,
+            profiler: None,
+            coverage: None,
+            symbols: Arc::new(program.get_symbols().cloned().unwrap_or_default()),
+            trace: None,
+            limits: SVMLimits::default(),
+            console: Some(Box::new(SVMStdConsole)),
+            devices: None,
+            extensions: ExtensionSet::STRICT,
+            #[cfg(feature = "host-calls")]
+            host_calls: None,
+            history: VecDeque::with_capacity(FAULT_HISTORY_LENGTH),
+            fault_snapshot: None,
+            meta_prefix: None,
+            checkpoints: None,
+            save_slots: SVMSaveSlots::new(PathBuf::from(".")),
+            output_line: Vec::new(),
+            last_output_line: String::new(),
+        }
+    }
+
+    pub fn enable_code_tracking(&mut self, dump_directory: Option<PathBuf>) {
+        self.code_tracker = Some(SVMCodeTracker::new(dump_directory));
+    }
+
+    pub fn get_code_tracker(&self) -> Option<&SVMCodeTracker> {
+        self.code_tracker.as_ref()
+    }
+
+    //  Without a console, output collects until take_output and `in` waits for push_input
+    pub fn detach_console(&mut self) {
+        self.console = None;
+    }
+
+    pub fn set_console(&mut self, console: Box<dyn SVMConsole>) {
+        self.console = Some(console);
+    }
+
+    //  rmem and wmem on the device's addresses go to it instead of memory
+    pub fn map_device(&mut self, start: u16, device: Box<dyn SVMDevice>) -> Result<(), String> {
+        self.devices.get_or_insert_with(SVMDeviceMap::new).map(start, device)
+    }
+
+    pub fn get_devices(&self) -> Option<&SVMDeviceMap> {
+        self.devices.as_ref()
+    }
+
+    //  Turns on the hcall extension and gives the program the host services,
+    //  with its files kept in the sandbox directory
+    #[cfg(feature = "host-calls")]
+    pub fn enable_host_calls(&mut self, sandbox: PathBuf) {
+        self.extensions = self.extensions.union(ExtensionSet::HOST_CALLS);
+        self.host_calls = Some(SVMHostCalls::new(sandbox));
+    }
+
+    #[cfg(feature = "host-calls")]
+    pub fn get_host_calls(&self) -> Option<&SVMHostCalls> {
+        self.host_calls.as_ref()
+    }
+
+    //  Every engine starts strict, decoding only the spec's opcodes
+    pub fn get_extensions(&self) -> ExtensionSet {
+        self.extensions
+    }
+
+    pub fn push_input(&mut self, input: &[u8]) {
+        self.engine_state.input_buffer.extend(input.iter());
+    }
+
+    pub fn take_output(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.engine_state.output_buffer)
+    }
+
+    pub fn set_limits(&mut self, limits: SVMLimits) {
+        self.limits = limits;
+    }
+
+    //  print_error saves a snapshot of the faulting machine here
+    pub fn set_fault_snapshot(&mut self, path: PathBuf) {
+        self.fault_snapshot = Some(path);
+    }
+
+    pub fn enable_profiler(&mut self) {
+        self.profiler = Some(SVMProfiler::new());
+    }
+
+    pub fn get_profiler(&self) -> Option<&SVMProfiler> {
+        self.profiler.as_ref()
+    }
+
+    pub fn enable_coverage(&mut self) {
+        self.coverage = Some(SVMCoverage::new());
+    }
+
+    pub fn get_coverage(&self) -> Option<&SVMCoverage> {
+        self.coverage.as_ref()
+    }
+
+    pub fn get_symbols(&self) -> &SVMSymbols {
+        &self.symbols
+    }
+
+    pub fn get_symbols_mut(&mut self) -> &mut SVMSymbols {
+        Arc::make_mut(&mut self.symbols)
+    }
+
+    pub fn set_symbols(&mut self, symbols: SVMSymbols) {
+        self.symbols = Arc::new(symbols);
+    }
+
+    //  Every executed instruction is written to the trace with the registers before it runs
+    pub fn enable_trace(&mut self, trace: Box<dyn Write>) {
+        self.trace = Some(trace);
+    }
+
+    pub fn disable_trace(&mut self) {
+        if let Some(mut trace) = self.trace.take() {
+            trace.flush().unwrap_or_default();
+        }
+    }
+
+    //  Enables host commands such as "!regs" typed at the console, see svm_meta_commands
+    pub fn set_meta_prefix(&mut self, prefix: String) {
+        self.meta_prefix = Some(prefix);
+    }
+
+    //  Keeps the state from before each of the last depth console input lines
+    pub fn enable_undo(&mut self, depth: usize) {
+        self.checkpoints = Some(SVMCheckpoints::new(depth));
+    }
+
+    pub fn get_checkpoints(&self) -> Option<&SVMCheckpoints> {
+        self.checkpoints.as_ref()
+    }
+
+    //  Goes back to just before the count-th most recent input line was read
+    pub fn undo(&mut self, count: usize) -> Result<(), String> {
+        let checkpoints = self.checkpoints.as_mut().ok_or("Undo is not enabled")?;
+        let available = checkpoints.len();
+        match checkpoints.undo(count) {
+            Some(engine_state) => {
+                self.engine_state = engine_state;
+                Ok(())
+            },
+            None => Err(format!("Can only undo up to {} command(s)", available)),
+        }
+    }
+
+    pub fn set_save_directory(&mut self, directory: PathBuf) {
+        self.save_slots = SVMSaveSlots::new(directory);
+    }
+
+    pub fn get_save_slots(&self) -> &SVMSaveSlots {
+        &self.save_slots
+    }
+
+    pub fn get_last_output_line(&self) -> &str {
+        &self.last_output_line
+    }
+
+    pub fn get_state(&self) -> &SVMEngineState {
+        &self.engine_state
+    }
+
+    pub(super) fn get_state_mut(&mut self) -> &mut SVMEngineState {
+        &mut self.engine_state
+    }
+
+    pub fn set_state(&mut self, engine_state: SVMEngineState) {
+        self.engine_state = engine_state;
+    }
+
+    pub fn run(&mut self) -> SVMTermination {
+        self.run_until(|_| false)
+    }
+
+    //  Runs until the program stops or should_stop returns true before an
+    //  instruction. The current instruction always runs, so running again from
+    //  a breakpoint makes progress.
+    pub fn run_until<F: FnMut(&SVMEngineState) -> bool>(&mut self, mut should_stop: F) -> SVMTermination {
+        let started = Instant::now();
+        let mut executed: u64 = 0;
+        let mut first = true;
+        let termination = loop {
+            if self.engine_state.halted {
+                break SVMTermination::Halted;
+            }
+            if let Some(termination) = self.check_limits(&started, executed) {
+                break termination;
+            }
+            if !first && should_stop(&self.engine_state) {
+                break SVMTermination::Stopped;
+            }
+            first = false;
+            match self.step() {
+                Ok(_) => executed += 1,
+                Err(SVMError::AwaitingInput) => break SVMTermination::AwaitingInput,
+                Err(error) => break SVMTermination::Error(error),
+            }
+        };
+        if let Some(ref mut trace) = self.trace {
+            trace.flush().unwrap_or_default();
+        }
+        if let Some(ref mut console) = self.console {
+            console.flush().unwrap_or_default();
+        }
+        termination
+    }
+
+    //  Checked before the next instruction runs, so a limit stops the program before it is exceeded.
+    //  The clock is read every TIME_CHECK_INTERVAL instructions of this run.
+    fn check_limits(&self, started: &Instant, executed: u64) -> Option<SVMTermination> {
+        if self.limits.is_unlimited() {
+            return None;
+        }
+        let engine_state = &self.engine_state;
+        if limit_reached(self.limits.max_instructions, engine_state.instruction_count) {
+            return Some(SVMTermination::InstructionLimit);
+        }
+        if let Some(max_duration) = self.limits.max_duration {
+            if executed.is_multiple_of(TIME_CHECK_INTERVAL) && started.elapsed() >= max_duration {
+                return Some(SVMTermination::TimeLimit);
+            }
+        }
+
+        let ip = engine_state.instruction_pointer.get_ip();
+        let opcode = match engine_state.memory.load_memory(ip).map(|value| value.get_opcode_with(self.extensions)) {
+            Ok(Ok(opcode)) => opcode,
+            _ => return None,
+        };
+        match opcode {
+            SVMOpCode::Push | SVMOpCode::Call
+                if limit_reached(self.limits.max_stack_depth, engine_state.stack.len()) => Some(SVMTermination::StackLimit),
+            SVMOpCode::Out
+                if limit_reached(self.limits.max_output_bytes, engine_state.output_bytes) => Some(SVMTermination::OutputLimit),
+            SVMOpCode::In
+                if limit_reached(self.limits.max_input_reads, engine_state.input_reads) => Some(SVMTermination::InputLimit),
+            _ => None,
+        }
+    }
+
+    pub fn step(&mut self) -> Result<(), SVMError> {
+        let instruction_address = self.engine_state.instruction_pointer.get_ip();
+        let opcode_value = self.engine_state.instruction_pointer.get_next_memory_value(&self.engine_state.memory);
+        let opcode = match opcode_value.and_then(|value| value.get_opcode_with(self.extensions)) {
+            Ok(opcode) => opcode,
+            Err(error) => return Err(self.fault(instruction_address, error)),
+        };
+        let opcode_value = opcode.get_value();
+
+        //  Input is read with the machine between instructions, so host commands can
+        //  save or replace it, and the instruction then starts over
+        if let SVMOpCode::In = opcode {
+            if self.engine_state.input_buffer.is_empty() {
+                self.engine_state.instruction_pointer.set_ip(instruction_address)?;
+                return match self.read_console_input() {
+                    Ok(true) => self.step(),
+                    Ok(false) => Err(SVMError::AwaitingInput),
+                    Err(error) => Err(error),
+                };
+            }
+        }
+
+        if let Some(ref mut tracker) = self.code_tracker {
+            tracker.record_execution(instruction_address, opcode.get_operand_count() + 1, &self.engine_state.memory);
+        }
+
+        if self.trace.is_some() {
+            self.write_trace(instruction_address);
+        }
+
+        if self.history.len() == FAULT_HISTORY_LENGTH {
+            self.history.pop_front();
+        }
+        self.history.push_back(instruction_address);
+
+        self.engine_state.last_memory_write = None;
+        let output_length = self.engine_state.output_buffer.len();
+        if let Err(error) = self.dispatch(&opcode) {
+            return Err(self.fault(instruction_address, error));
+        }
+
+        self.engine_state.instruction_count += 1;
+        match opcode {
+            //  A console device writes through wmem
+            SVMOpCode::Out | SVMOpCode::Wmem if self.engine_state.output_buffer.len() > output_length => {
+                self.engine_state.output_bytes += 1;
+                self.record_output_line();
+                self.write_console_output()?;
+            },
+            SVMOpCode::In => self.engine_state.input_reads += 1,
+            _ => {},
+        }
+
+        if let (Some(tracker), Some(write)) = (&mut self.code_tracker, &self.engine_state.last_memory_write) {
+            tracker.record_write(instruction_address, write);
+        }
+        if let Some(ref mut coverage) = self.coverage {
+            coverage.record_instruction(instruction_address, &opcode, self.engine_state.instruction_pointer.get_ip());
+        }
+        if let Some(ref mut profiler) = self.profiler {
+            profiler.record_instruction(instruction_address, opcode_value);
+            match opcode {
+                SVMOpCode::Call => profiler.record_call(self.engine_state.instruction_pointer.get_ip()),
+                SVMOpCode::Ret => profiler.record_return(),
+                _ => {},
+            }
+        }
+        Ok(())
+    }
+
+    fn dispatch(&mut self, opcode: &SVMOpCode) -> Result<(), SVMError> {
+        #[cfg(feature = "host-calls")]
+        if let SVMOpCode::HostCall = opcode {
+            return match self.host_calls {
+                Some(ref mut host_calls) => host_calls.call(&mut self.engine_state),
+                None => Err(SVMError::InvalidOpCode),
             };
         }
+        match self.devices {
+            Some(ref mut devices) => opcode.dispatch_with_devices(&mut self.engine_state, devices),
+            None => opcode.dispatch(&mut self.engine_state),
+        }
+    }
+
+    //  Leaves the instruction pointer on the faulting instruction
+    fn fault(&mut self, instruction_address: u16, error: SVMError) -> SVMError {
+        self.engine_state.instruction_pointer.set_ip(instruction_address).unwrap_or_default();
+        error
     }
 
-    fn print_error(&self, error: SVMError) {
-        let ip = self.engine_state.instruction_pointer.get_ip();
-        let opcode = match self.engine_state.memory.load_memory(ip) {
-            Ok(x) => x,
-            _ => 0,
+    fn read_console_input(&mut self) -> Result<bool, SVMError> {
+        let mut console = match self.console.take() {
+            Some(console) => console,
+            None => return Ok(false),
+        };
+        let result = loop {
+            let input = match console.read_input() {
+                Ok(Some(input)) => input,
+                Ok(None) => break Ok(false),
+                Err(_) => break Err(SVMError::ReadError),
+            };
+            let command = self.meta_prefix.as_ref()
+                .and_then(|prefix| input.strip_prefix(prefix.as_bytes()))
+                .map(|command| String::from_utf8_lossy(command).trim().to_string());
+            match command {
+                Some(command) => {
+                    let response = execute_meta_command(self, &command);
+                    if console.write_output(response.as_bytes()).is_err() {
+                        break Err(SVMError::WriteError);
+                    }
+                },
+                None => {
+                    if let (Some(checkpoints), false) = (&mut self.checkpoints, input.is_empty()) {
+                        checkpoints.record(&self.engine_state);
+                    }
+                    self.engine_state.input_buffer.extend(input.iter());
+                    break Ok(!input.is_empty());
+                },
+            }
         };
-        println!("Error at instruction: {}, opcode: {} ", ip, opcode);
-        match error {
-            SVMError::InvalidMemory => println!("Memory Error"),
-            SVMError::InvalidOpCode => println!("Invalid Opcode"),
-            SVMError::InvalidRegister => println!("Invalid Register"),
-            SVMError::ReadError => println!("Read error"),
-            SVMError::StackEmpty => println!("Stack error"),
-            SVMError::WriteError => println!("Write error"),
-        }
-        std::process::exit(1);
-    }
-}
\ No newline at end of file
+        self.console = Some(console);
+        result
+    }
+
+    fn record_output_line(&mut self) {
+        match self.engine_state.output_buffer.last() {
+            Some(b'\n') if !self.output_line.is_empty() => {
+                self.last_output_line = String::from_utf8_lossy(&self.output_line).to_string();
+                self.output_line.clear();
+            },
+            Some(b'\n') | None => {},
+            Some(byte) => self.output_line.push(*byte),
+        }
+    }
+
+    fn write_console_output(&mut self) -> Result<(), SVMError> {
+        if let Some(ref mut console) = self.console {
+            console.write_output(&self.engine_state.output_buffer).map_err(|_| SVMError::WriteError)?;
+            self.engine_state.output_buffer.clear();
+        }
+        Ok(())
+    }
+
+    fn write_trace(&mut self, address: u16) {
+        let instruction = disassemble_instruction(&self.engine_state.memory, address, &self.symbols, self.extensions);
+        let registers: Vec<String> = (0..NUM_OF_REGISTERS)
+            .map(|index| self.engine_state.registers.get_register_by_index(index).to_string())
+            .collect();
+        let line = format!("{:>16}: {:32} [{}]", self.symbols.format_address(address), instruction.text, registers.join(" "));
+        if let Some(ref mut trace) = self.trace {
+            if writeln!(trace, "{}", line).is_err() {
+                self.trace = None;
+            }
+        }
+    }
+
+    //  The lines print_error shows for an error from step or run
+    pub fn get_fault_report(&self, error: &SVMError) -> Vec<String> {
+        format_fault_report(&self.engine_state, &self.symbols, self.extensions, error, &self.history)
+    }
+
+    //  Prints a fault report for an error from step or run, and saves a snapshot
+    //  of the machine when a fault snapshot is set
+    pub fn print_error(&self, error: &SVMError) {
+        for line in self.get_fault_report(error) {
+            println!("{}", line);
+        }
+        if let Some(ref path) = self.fault_snapshot {
+            match save_snapshot(&self.engine_state, path) {
+                Ok(_) => println!("Saved a snapshot to {}, restore it in the debugger with: load {}", path.display(), path.display()),
+                Err(error) => println!("Failed to save a snapshot to {}: {}", path.display(), error),
+            }
+        }
+    }
+
+}
+
+fn limit_reached<T: PartialOrd>(limit: Option<T>, value: T) -> bool {
+    match limit {
+        Some(max) => value >= max,
+        None => false,
+    }
+}
@@ -0,0 +1,293 @@
+use std::collections::{BTreeSet, HashSet, VecDeque};
+use std::io::{self, Write};
+use super::svm_engine::{SVMEngine, SVMTermination};
+use super::svm_limits::SVMLimits;
+
+pub struct ExplorerOptions {
+    pub max_states: usize,
+    pub max_depth: usize,
+    pub instruction_budget: u64,
+}
+
+impl Default for ExplorerOptions {
+    fn default() -> ExplorerOptions {
+        ExplorerOptions {
+            max_states: 1000,
+            max_depth: 64,
+            instruction_budget: 10_000_000,
+        }
+    }
+}
+
+pub struct Room {
+    pub name: String,
+    pub description: String,
+    pub exits: Vec<String>,
+    pub items: Vec<String>,
+}
+
+pub struct ExploredState {
+    pub commands: Vec<String>,
+    pub room: Option<usize>,
+    pub inventory: Vec<String>,
+}
+
+//  A command after which the program did not ask for input again
+pub struct Ending {
+    pub commands: Vec<String>,
+    pub reason: &'static str,
+    pub output: String,
+}
+
+//  Explores a text adventure breadth first. Every state waiting for input is
+//  forked once per command worth trying there: `go` for each exit, `take` for
+//  each item in the room and `use` for each item carried. States already seen
+//  are skipped, so the command list stored with each state is a shortest one.
+pub struct SVMExplorer {
+    options: ExplorerOptions,
+    rooms: Vec<Room>,
+    exits: BTreeSet<(usize, String, usize)>,
+    states: Vec<ExploredState>,
+    endings: Vec<Ending>,
+}
+
+impl SVMExplorer {
+    pub fn new(options: ExplorerOptions) -> SVMExplorer {
+        SVMExplorer {
+            options,
+            rooms: Vec::new(),
+            exits: BTreeSet::new(),
+            states: Vec::new(),
+            endings: Vec::new(),
+        }
+    }
+
+    pub fn explore(&mut self, mut engine: SVMEngine) {
+        engine.detach_console();
+        let (termination, output) = self.advance(&mut engine);
+        if !matches!(termination, SVMTermination::AwaitingInput) {
+            self.endings.push(Ending { commands: Vec::new(), reason: termination.get_description(), output });
+            return;
+        }
+
+        let mut seen = HashSet::new();
+        seen.insert(engine.get_state().get_machine_hash());
+        let mut queue = VecDeque::new();
+        queue.push_back((engine, Vec::new()));
+
+        while let Some((engine, commands)) = queue.pop_front() {
+            let room = self.look(&engine).map(|room| self.add_room(room));
+            let inventory = self.inventory(&engine);
+            let mut candidates = Vec::new();
+            if let Some(room) = room {
+                candidates.extend(self.rooms[room].exits.iter().map(|exit| format!("go {}", exit)));
+                candidates.extend(self.rooms[room].items.iter().map(|item| format!("take {}", item)));
+            }
+            candidates.extend(inventory.iter().map(|item| format!("use {}", item)));
+            self.states.push(ExploredState { commands: commands.clone(), room, inventory });
+            if commands.len() >= self.options.max_depth {
+                continue;
+            }
+
+            for command in candidates {
+                if self.states.len() + queue.len() >= self.options.max_states {
+                    break;
+                }
+                let mut fork = engine.clone();
+                fork.push_input(format!("{}\n", command).as_bytes());
+                let (termination, output) = self.advance(&mut fork);
+                let mut path = commands.clone();
+                path.push(command.clone());
+                if !matches!(termination, SVMTermination::AwaitingInput) {
+                    self.endings.push(Ending { commands: path, reason: termination.get_description(), output });
+                    continue;
+                }
+                if let (Some(room), Some(exit)) = (room, command.strip_prefix("go ")) {
+                    if let Some(destination) = parse_room(&output) {
+                        let destination = self.add_room(destination);
+                        self.exits.insert((room, exit.to_string(), destination));
+                    }
+                }
+                if seen.insert(fork.get_state().get_machine_hash()) {
+                    queue.push_back((fork, path));
+                }
+            }
+        }
+    }
+
+    pub fn get_rooms(&self) -> &[Room] {
+        &self.rooms
+    }
+
+    pub fn get_states(&self) -> &[ExploredState] {
+        &self.states
+    }
+
+    pub fn get_endings(&self) -> &[Ending] {
+        &self.endings
+    }
+
+    pub fn print_report(&self) {
+        println!("Explored {} unique states in {} rooms", self.states.len(), self.rooms.len());
+
+        println!("Rooms:");
+        for index in 0..self.rooms.len() {
+            let room = &self.rooms[index];
+            println!("  {}: exits {}; items {}", self.get_room_label(index),
+                join_or_none(&room.exits), join_or_none(&room.items));
+        }
+
+        println!("Room graph:");
+        for (from, exit, to) in self.exits.iter() {
+            println!("  {} --{}--> {}", self.get_room_label(*from), exit, self.get_room_label(*to));
+        }
+
+        println!("Shortest inputs:");
+        for state in self.states.iter() {
+            let room = state.room.map(|room| self.get_room_label(room)).unwrap_or_else(|| "?".to_string());
+            println!("  [{}] inventory {}: {}", room, join_or_none(&state.inventory), format_commands(&state.commands));
+        }
+
+        println!("Endings:");
+        for ending in self.endings.iter() {
+            println!("  {} ({}): {}", format_commands(&ending.commands), ending.reason, last_line(&ending.output));
+        }
+    }
+
+    //  The room graph in Graphviz format
+    pub fn write_dot<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "digraph rooms {{")?;
+        for index in 0..self.rooms.len() {
+            writeln!(writer, "    room{} [label={:?}];", index, self.get_room_label(index))?;
+        }
+        for (from, exit, to) in self.exits.iter() {
+            writeln!(writer, "    room{} -> room{} [label={:?}];", from, to, exit)?;
+        }
+        writeln!(writer, "}}")
+    }
+
+    //  Rooms sharing a name, like the twisty passages of a maze, are numbered
+    fn get_room_label(&self, index: usize) -> String {
+        let name = &self.rooms[index].name;
+        let same_name: Vec<usize> = (0..self.rooms.len()).filter(|other| self.rooms[*other].name == *name).collect();
+        if same_name.len() > 1 {
+            let position = same_name.iter().position(|other| *other == index).unwrap_or(0);
+            format!("{} #{}", name, position + 1)
+        } else {
+            name.clone()
+        }
+    }
+
+    //  A room is known by its name and description; its items change as they are taken
+    fn add_room(&mut self, room: Room) -> usize {
+        match self.rooms.iter().position(|known| known.name == room.name && known.description == room.description) {
+            Some(index) => index,
+            None => {
+                self.rooms.push(room);
+                self.rooms.len() - 1
+            },
+        }
+    }
+
+    fn look(&self, engine: &SVMEngine) -> Option<Room> {
+        let mut fork = engine.clone();
+        fork.push_input(b"look\n");
+        let (_, output) = self.advance(&mut fork);
+        parse_room(&output)
+    }
+
+    fn inventory(&self, engine: &SVMEngine) -> Vec<String> {
+        let mut fork = engine.clone();
+        fork.push_input(b"inv\n");
+        let (_, output) = self.advance(&mut fork);
+        let mut items = Vec::new();
+        let mut in_inventory = false;
+        for line in output.lines() {
+            if line.starts_with("Your inventory:") {
+                in_inventory = true;
+            } else if let (true, Some(item)) = (in_inventory, line.strip_prefix("- ")) {
+                items.push(item.trim().to_string());
+            } else {
+                in_inventory = false;
+            }
+        }
+        items
+    }
+
+    //  Runs until the program asks for input again, within the instruction budget
+    fn advance(&self, engine: &mut SVMEngine) -> (SVMTermination, String) {
+        engine.set_limits(SVMLimits {
+            max_instructions: Some(engine.get_state().instruction_count + self.options.instruction_budget),
+            ..SVMLimits::default()
+        });
+        let termination = engine.run();
+        let output = String::from_utf8_lossy(&engine.take_output()).into_owned();
+        (termination, output)
+    }
+}
+
+//  Parses the last room description in the output:
+//
+//      == Foothills ==
+//      You find yourself standing at the base of an enormous mountain.
+//
+//      Things of interest here:
+//      - tablet
+//
+//      There are 2 exits:
+//      - north
+//      - south
+pub fn parse_room(output: &str) -> Option<Room> {
+    let lines: Vec<&str> = output.lines().map(|line| line.trim_end()).collect();
+    let title = lines.iter().rposition(|line| line.starts_with("== ") && line.ends_with(" =="))?;
+    let mut room = Room {
+        name: lines[title].trim_matches(|c: char| c == '=' || c == ' ').to_string(),
+        description: String::new(),
+        exits: Vec::new(),
+        items: Vec::new(),
+    };
+
+    let mut remaining = lines[title + 1..].iter();
+    let mut description = Vec::new();
+    for line in remaining.by_ref() {
+        if line.is_empty() {
+            break;
+        }
+        description.push(*line);
+    }
+    room.description = description.join("\n");
+
+    let mut section: Option<&mut Vec<String>> = None;
+    for line in remaining {
+        if *line == "Things of interest here:" {
+            section = Some(&mut room.items);
+        } else if (line.starts_with("There are ") || line.starts_with("There is ")) && line.ends_with(':') {
+            section = Some(&mut room.exits);
+        } else if let (Some(ref mut entries), Some(entry)) = (section.as_mut(), line.strip_prefix("- ")) {
+            entries.push(entry.trim().to_string());
+        } else {
+            section = None;
+        }
+    }
+    Some(room)
+}
+
+fn join_or_none(values: &[String]) -> String {
+    if values.is_empty() {
+        "none".to_string()
+    } else {
+        values.join(", ")
+    }
+}
+
+fn format_commands(commands: &[String]) -> String {
+    if commands.is_empty() {
+        "<start>".to_string()
+    } else {
+        commands.join("; ")
+    }
+}
+
+fn last_line(output: &str) -> &str {
+    output.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or("").trim()
+}
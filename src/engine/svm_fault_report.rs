@@ -0,0 +1,84 @@
+use std::collections::VecDeque;
+use synacorvm_core::extensions::{MemoryValue, RegisterValue};
+use synacorvm_core::opcode::{ExtensionSet, OpcodeValue};
+use synacorvm_core::svm_constants::NUM_OF_REGISTERS;
+use synacorvm_core::svm_engine_state::SVMEngineState;
+use synacorvm_core::svm_error::SVMError;
+use super::svm_backtrace::{format_backtrace, get_return_address_slots};
+use super::svm_disassembler::disassemble_instruction;
+use super::svm_symbols::SVMSymbols;
+
+//  How many executed instructions the engine remembers for fault reports
+pub const FAULT_HISTORY_LENGTH: usize = 16;
+//  Stack slots shown, from the top
+const STACK_LINES: usize = 8;
+
+//  Describes a fault with the instruction pointer on the faulting instruction:
+//  the instruction and its operand values, registers, the top of the stack, the
+//  call stack and the instructions that ran before it, oldest first
+pub fn format_fault_report(engine_state: &SVMEngineState, symbols: &SVMSymbols, extensions: ExtensionSet, error: &SVMError, history: &VecDeque<u16>) -> Vec<String> {
+    let ip = engine_state.instruction_pointer.get_ip();
+    let memory = &engine_state.memory;
+    let opcode_value = memory.load_memory(ip).unwrap_or_default();
+    let location = match symbols.find_symbol(ip) {
+        Some(_) => format!("{} ({})", symbols.format_address(ip), ip),
+        None => ip.to_string(),
+    };
+    let mut lines = vec![
+        format!("Error at instruction: {}, opcode: {} ", location, opcode_value),
+        error.get_description().to_string(),
+    ];
+
+    lines.push(String::new());
+    lines.push(format!("Faulting instruction:  {}", disassemble_instruction(memory, ip, symbols, extensions).text));
+    if let Ok(opcode) = opcode_value.get_opcode_with(extensions) {
+        let operands: Vec<String> = (1..=opcode.get_operand_count())
+            .map(|offset| match memory.load_memory(ip.wrapping_add(offset)) {
+                Ok(word) => describe_operand(engine_state, word),
+                Err(_) => "outside memory".to_string(),
+            })
+            .collect();
+        if !operands.is_empty() {
+            lines.push(format!("Operands:  {}", operands.join(", ")));
+        }
+    }
+
+    let registers: Vec<String> = (0..NUM_OF_REGISTERS)
+        .map(|index| format!("r{}={}", index, engine_state.registers.get_register_by_index(index)))
+        .collect();
+    lines.push(format!("Registers:  {}", registers.join(" ")));
+
+    let stack = &engine_state.stack;
+    lines.push(format!("Stack depth {}{}", stack.len(), if stack.len() > STACK_LINES { ", top shown" } else { "" }));
+    let return_slots = get_return_address_slots(engine_state);
+    for (index, value) in stack.iter().enumerate().rev().take(STACK_LINES) {
+        if return_slots.contains(&index) {
+            lines.push(format!("  [{}]: {} <- return to {}", index, value, symbols.format_address(*value)));
+        } else {
+            lines.push(format!("  [{}]: {}", index, value));
+        }
+    }
+
+    lines.push("Call stack:".to_string());
+    lines.extend(format_backtrace(engine_state, symbols).into_iter().map(|line| format!("  {}", line)));
+
+    if !history.is_empty() {
+        lines.push(format!("Last {} instructions:", history.len()));
+        for address in history.iter() {
+            let instruction = disassemble_instruction(memory, *address, symbols, extensions);
+            lines.push(format!("  {:>16}: {}", symbols.format_address(*address), instruction.text));
+        }
+    }
+    lines
+}
+
+fn describe_operand(engine_state: &SVMEngineState, word: u16) -> String {
+    if word.is_valid_register() {
+        let index = word.get_register_index() as usize;
+        format!("r{} = {}", index, engine_state.registers.get_register_by_index(index))
+    } else if word.is_valid_memory_address() {
+        word.to_string()
+    } else {
+        format!("{} (invalid)", word)
+    }
+}
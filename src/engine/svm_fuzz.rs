@@ -0,0 +1,39 @@
+use std::io::{self, Cursor};
+use super::svm_engine::SVMEngine;
+use super::svm_limits::SVMLimits;
+use super::svm_program::SVMProgram;
+use super::svm_snapshot::read_snapshot;
+
+//  Enough for a fuzzed program to loop for a while without slowing the fuzzer down
+pub const FUZZ_INSTRUCTION_BUDGET: u64 = 20_000;
+
+//  Bodies of the fuzz targets in fuzz/, kept here so the regression tests run
+//  exactly what the fuzzer ran. Neither may panic, whatever the data.
+
+//  Loads the data as a raw image, as whatever image format it looks like and as a snapshot
+pub fn fuzz_load(data: &[u8]) {
+    SVMEngine::new(SVMProgram::from_bytes(data));
+    if let Ok(program) = SVMProgram::from_image(data) {
+        SVMEngine::new(program);
+    }
+    read_snapshot(&mut Cursor::new(data)).ok();
+}
+
+//  The first byte is the length of the input that follows it, and the rest is
+//  the program image. It runs with the analysis tools enabled, since they index
+//  by address too.
+pub fn fuzz_execute(data: &[u8]) {
+    let input_length = data.first().map(|length| *length as usize).unwrap_or(0);
+    let input = data.get(1..).unwrap_or(&[]);
+    let (input, image) = input.split_at(input_length.min(input.len()));
+
+    let mut engine = SVMEngine::new(SVMProgram::from_bytes(image));
+    engine.detach_console();
+    engine.enable_code_tracking(None);
+    engine.enable_profiler();
+    engine.enable_coverage();
+    engine.enable_trace(Box::new(io::sink()));
+    engine.set_limits(SVMLimits { max_instructions: Some(FUZZ_INSTRUCTION_BUDGET), ..SVMLimits::default() });
+    engine.push_input(input);
+    engine.run();
+}
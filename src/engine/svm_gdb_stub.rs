@@ -0,0 +1,506 @@
+use std::collections::BTreeSet;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::TcpStream;
+use synacorvm_core::extensions::RegisterValue;
+use synacorvm_core::opcode::{OpcodeValue, SVMOpCode};
+use synacorvm_core::svm_constants::NUM_OF_REGISTERS;
+use synacorvm_core::svm_engine_state::SVMEngineState;
+use synacorvm_core::svm_error::SVMError;
+use super::svm_engine::{SVMEngine, SVMTermination};
+
+const PACKET_SIZE: usize = 4096;
+//  What gdb sends to stop a running program
+const INTERRUPT: u8 = 0x03;
+//  Instructions run between checks for an interrupt while continuing
+const INTERRUPT_POLL_INTERVAL: u64 = 10_000;
+//  r0-r7 followed by the instruction pointer
+const NUM_OF_GDB_REGISTERS: usize = NUM_OF_REGISTERS + 1;
+
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.synacor.svm.core">
+    <reg name="r0" bitsize="16" type="uint16" regnum="0"/>
+    <reg name="r1" bitsize="16" type="uint16"/>
+    <reg name="r2" bitsize="16" type="uint16"/>
+    <reg name="r3" bitsize="16" type="uint16"/>
+    <reg name="r4" bitsize="16" type="uint16"/>
+    <reg name="r5" bitsize="16" type="uint16"/>
+    <reg name="r6" bitsize="16" type="uint16"/>
+    <reg name="r7" bitsize="16" type="uint16"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+  </feature>
+</target>
+"#;
+
+#[derive(Clone, Copy, PartialEq)]
+enum WatchKind {
+    Write,
+    Read,
+    Access,
+}
+
+impl WatchKind {
+    fn get_stop_name(&self) -> &'static str {
+        match *self {
+            WatchKind::Write => "watch",
+            WatchKind::Read => "rwatch",
+            WatchKind::Access => "awatch",
+        }
+    }
+}
+
+//  Covers the words [start, end)
+struct Watchpoint {
+    start: u16,
+    end: u16,
+    kind: WatchKind,
+}
+
+//  A connection the stub can check for an interrupt while the program runs
+pub trait GdbConnection: Read + Write {
+    //  Whether the client sent an interrupt or went away, without waiting for either.
+    //  Leftover acknowledgements before the interrupt are consumed with it.
+    fn poll_interrupt(&mut self) -> io::Result<bool>;
+}
+
+impl GdbConnection for TcpStream {
+    fn poll_interrupt(&mut self) -> io::Result<bool> {
+        let mut pending = [0; 16];
+        self.set_nonblocking(true)?;
+        let peeked = self.peek(&mut pending);
+        self.set_nonblocking(false)?;
+        let length = match peeked {
+            Ok(0) => return Ok(true),
+            Ok(length) => length,
+            Err(ref error) if error.kind() == ErrorKind::WouldBlock => return Ok(false),
+            Err(error) => return Err(error),
+        };
+        match pending[..length].iter().position(|byte| *byte != b'+') {
+            Some(position) if pending[position] == INTERRUPT => {
+                self.read_exact(&mut pending[..=position])?;
+                Ok(true)
+            },
+            _ => Ok(false),
+        }
+    }
+}
+
+//  A GDB remote serial protocol stub. GDB addresses everything in bytes, so
+//  word n of the machine is bytes 2n and 2n+1, little endian. Memory and
+//  watchpoint packets, the pc register, breakpoints and resume addresses all
+//  use byte addresses, and code addresses must be even.
+pub struct SVMGdbStub {
+    engine: SVMEngine,
+    breakpoints: BTreeSet<u16>,
+    watchpoints: Vec<Watchpoint>,
+    last_stop: String,
+}
+
+impl SVMGdbStub {
+    pub fn new(engine: SVMEngine) -> SVMGdbStub {
+        SVMGdbStub {
+            engine,
+            breakpoints: BTreeSet::new(),
+            watchpoints: Vec::new(),
+            last_stop: "S05".to_string(),
+        }
+    }
+
+    pub fn get_engine(&self) -> &SVMEngine {
+        &self.engine
+    }
+
+    //  Serves one client until it detaches, kills the program or disconnects
+    pub fn serve<S: GdbConnection>(&mut self, stream: &mut S) -> io::Result<()> {
+        while let Some(packet) = read_packet(stream)? {
+            stream.write_all(b"+")?;
+            let (reply, keep_serving) = self.handle_packet(&packet, stream);
+            write_packet(stream, &reply)?;
+            if !keep_serving {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    //  Returns the reply and whether to keep serving
+    fn handle_packet<S: GdbConnection>(&mut self, packet: &str, stream: &mut S) -> (String, bool) {
+        let (command, arguments) = packet.split_at(packet.chars().next().map(|c| c.len_utf8()).unwrap_or(0));
+        let reply = match command {
+            "?" => self.last_stop.clone(),
+            "g" => self.read_registers(),
+            "G" => self.write_registers(arguments),
+            "p" => self.read_register(arguments),
+            "P" => self.write_register(arguments),
+            "m" => self.read_memory(arguments),
+            "M" => self.write_memory(arguments),
+            "c" => self.resume(arguments, false, stream),
+            "s" => self.resume(arguments, true, stream),
+            "Z" => self.change_breakpoint(arguments, true),
+            "z" => self.change_breakpoint(arguments, false),
+            "H" | "T" => "OK".to_string(),
+            "q" => self.query(arguments),
+            "v" => match arguments {
+                "Cont?" => "vCont;c;C;s;S".to_string(),
+                "Kill" => return ("OK".to_string(), false),
+                _ if arguments.starts_with("Cont;c") || arguments.starts_with("Cont;C") => self.resume("", false, stream),
+                _ if arguments.starts_with("Cont;s") || arguments.starts_with("Cont;S") => self.resume("", true, stream),
+                _ => String::new(),
+            },
+            "D" | "k" => return ("OK".to_string(), false),
+            _ => String::new(),
+        };
+        (reply, true)
+    }
+
+    fn query(&self, query: &str) -> String {
+        if query.starts_with("Supported") {
+            format!("PacketSize={:x};qXfer:features:read+;swbreak+;hwbreak+", PACKET_SIZE)
+        } else if let Some(range) = query.strip_prefix("Xfer:features:read:target.xml:") {
+            match parse_pair(range, ',') {
+                Some((offset, length)) => {
+                    let offset = (offset as usize).min(TARGET_XML.len());
+                    let end = (offset + length as usize).min(TARGET_XML.len());
+                    let prefix = if end == TARGET_XML.len() { "l" } else { "m" };
+                    format!("{}{}", prefix, &TARGET_XML[offset..end])
+                },
+                None => "E01".to_string(),
+            }
+        } else {
+            match query {
+                "Attached" => "1".to_string(),
+                "C" => "QC1".to_string(),
+                "fThreadInfo" => "m1".to_string(),
+                "sThreadInfo" => "l".to_string(),
+                _ if query.starts_with("Symbol") => "OK".to_string(),
+                _ => String::new(),
+            }
+        }
+    }
+
+    fn get_register(&self, index: usize) -> u16 {
+        let engine_state = self.engine.get_state();
+        if index < NUM_OF_REGISTERS {
+            engine_state.registers.get_register_by_index(index)
+        } else {
+            engine_state.instruction_pointer.get_ip() * 2
+        }
+    }
+
+    fn set_register(&mut self, index: usize, value: u16) -> Result<(), ()> {
+        let engine_state = self.engine.get_state_mut();
+        if index < NUM_OF_REGISTERS {
+            engine_state.registers.set_register_by_index(index, value);
+            Ok(())
+        } else {
+            let ip = code_address(value as u32).ok_or(())?;
+            engine_state.instruction_pointer.set_ip(ip).map_err(|_| ())
+        }
+    }
+
+    fn read_registers(&self) -> String {
+        (0..NUM_OF_GDB_REGISTERS).map(|index| encode_word(self.get_register(index))).collect()
+    }
+
+    fn write_registers(&mut self, data: &str) -> String {
+        let bytes = match decode_hex(data) {
+            Some(bytes) if bytes.len() >= NUM_OF_GDB_REGISTERS * 2 => bytes,
+            _ => return "E01".to_string(),
+        };
+        for index in 0..NUM_OF_GDB_REGISTERS {
+            let value = u16::from_le_bytes([bytes[index * 2], bytes[index * 2 + 1]]);
+            if self.set_register(index, value).is_err() {
+                return "E02".to_string();
+            }
+        }
+        "OK".to_string()
+    }
+
+    fn read_register(&self, index: &str) -> String {
+        match u32::from_str_radix(index, 16) {
+            Ok(index) if (index as usize) < NUM_OF_GDB_REGISTERS => encode_word(self.get_register(index as usize)),
+            _ => "E01".to_string(),
+        }
+    }
+
+    fn write_register(&mut self, arguments: &str) -> String {
+        let (index, value) = match arguments.split_once('=') {
+            Some((index, value)) => (u32::from_str_radix(index, 16).ok(), decode_hex(value)),
+            None => return "E01".to_string(),
+        };
+        match (index, value) {
+            (Some(index), Some(bytes)) if (index as usize) < NUM_OF_GDB_REGISTERS && bytes.len() == 2 => {
+                match self.set_register(index as usize, u16::from_le_bytes([bytes[0], bytes[1]])) {
+                    Ok(_) => "OK".to_string(),
+                    Err(_) => "E02".to_string(),
+                }
+            },
+            _ => "E01".to_string(),
+        }
+    }
+
+    fn read_memory(&self, arguments: &str) -> String {
+        let (address, length) = match parse_pair(arguments, ',') {
+            Some(range) => range,
+            None => return "E01".to_string(),
+        };
+        let memory = &self.engine.get_state().memory;
+        let mut reply = String::new();
+        for byte_address in address..address.saturating_add(length) {
+            let word = match memory.load_memory((byte_address / 2) as u16) {
+                Ok(word) if byte_address / 2 <= u16::MAX as u32 => word,
+                _ => break,
+            };
+            reply.push_str(&format!("{:02x}", word.to_le_bytes()[(byte_address % 2) as usize]));
+        }
+        if reply.is_empty() && length > 0 {
+            "E02".to_string()
+        } else {
+            reply
+        }
+    }
+
+    fn write_memory(&mut self, arguments: &str) -> String {
+        let (range, data) = match arguments.split_once(':') {
+            Some(parts) => parts,
+            None => return "E01".to_string(),
+        };
+        let (address, bytes) = match (parse_pair(range, ','), decode_hex(data)) {
+            (Some((address, length)), Some(bytes)) if bytes.len() == length as usize => (address, bytes),
+            _ => return "E01".to_string(),
+        };
+        let memory = &mut self.engine.get_state_mut().memory;
+        for (offset, byte) in bytes.iter().enumerate() {
+            let byte_address = address + offset as u32;
+            let word_address = (byte_address / 2) as u16;
+            let mut word = match memory.load_memory(word_address) {
+                Ok(word) if byte_address / 2 <= u16::MAX as u32 => word.to_le_bytes(),
+                _ => return "E02".to_string(),
+            };
+            word[(byte_address % 2) as usize] = *byte;
+            if memory.store_memory(word_address, u16::from_le_bytes(word)).is_err() {
+                return "E02".to_string();
+            }
+        }
+        "OK".to_string()
+    }
+
+    //  Z0/Z1 are breakpoints on the instruction at an even byte address, Z2-Z4 watch byte ranges
+    fn change_breakpoint(&mut self, arguments: &str, insert: bool) -> String {
+        let mut parts = arguments.split(',');
+        let kind = parts.next();
+        let address = parts.next().and_then(|address| u32::from_str_radix(address, 16).ok());
+        let length = parts.next().and_then(|length| u32::from_str_radix(length, 16).ok()).unwrap_or(1);
+        let address = match address {
+            Some(address) => address,
+            None => return "E01".to_string(),
+        };
+
+        let watch_kind = match kind {
+            Some("0") | Some("1") => {
+                let address = match code_address(address) {
+                    Some(address) => address,
+                    None => return "E01".to_string(),
+                };
+                if insert {
+                    self.breakpoints.insert(address);
+                } else {
+                    self.breakpoints.remove(&address);
+                }
+                return "OK".to_string();
+            },
+            Some("2") => WatchKind::Write,
+            Some("3") => WatchKind::Read,
+            Some("4") => WatchKind::Access,
+            _ => return String::new(),
+        };
+        let start = (address / 2).min(u16::MAX as u32) as u16;
+        let end = (address.saturating_add(length.max(1)).div_ceil(2)).min(u16::MAX as u32) as u16;
+        if insert {
+            self.watchpoints.push(Watchpoint { start, end, kind: watch_kind });
+        } else {
+            self.watchpoints.retain(|watch| !(watch.start == start && watch.end == end && watch.kind == watch_kind));
+        }
+        "OK".to_string()
+    }
+
+    //  Continuing checks the connection every INTERRUPT_POLL_INTERVAL instructions,
+    //  so gdb's Ctrl-C stops a program that would otherwise never return
+    fn resume<S: GdbConnection>(&mut self, address: &str, single_step: bool, stream: &mut S) -> String {
+        if !address.is_empty() {
+            let moved = u32::from_str_radix(address, 16).ok().and_then(code_address)
+                .and_then(|address| self.engine.get_state_mut().instruction_pointer.set_ip(address).ok());
+            if moved.is_none() {
+                return "E01".to_string();
+            }
+        }
+        if self.engine.get_state().halted {
+            return "W00".to_string();
+        }
+
+        let stop = if single_step {
+            match self.engine.step() {
+                Ok(_) => written_watchpoint(&self.watchpoints, self.engine.get_state())
+                    .unwrap_or_else(|| "S05".to_string()),
+                //  The in instruction has not run yet and can be stepped again once there is input
+                Err(SVMError::AwaitingInput) => "S05".to_string(),
+                //  SIGILL, the program cannot go on
+                Err(error) => {
+                    self.engine.print_error(&error);
+                    "S04".to_string()
+                },
+            }
+        } else {
+            let breakpoints = &self.breakpoints;
+            let watchpoints = &self.watchpoints;
+            let mut watch_stop = None;
+            let mut interrupted = false;
+            let mut executed: u64 = 0;
+            let termination = self.engine.run_until(|engine_state| {
+                executed += 1;
+                if executed.is_multiple_of(INTERRUPT_POLL_INTERVAL) {
+                    //  A broken connection stops the program too, serve then finds it closed
+                    interrupted = stream.poll_interrupt().unwrap_or(true);
+                }
+                watch_stop = written_watchpoint(watchpoints, engine_state).or_else(|| read_watchpoint(watchpoints, engine_state));
+                interrupted || watch_stop.is_some() || breakpoints.contains(&engine_state.instruction_pointer.get_ip())
+            });
+            match termination {
+                //  SIGINT
+                SVMTermination::Stopped if interrupted => "S02".to_string(),
+                SVMTermination::Stopped => watch_stop.unwrap_or_else(|| "S05".to_string()),
+                //  Stopped on the in instruction, which runs once there is input
+                SVMTermination::AwaitingInput => "S05".to_string(),
+                SVMTermination::Halted => "W00".to_string(),
+                SVMTermination::Error(ref error) => {
+                    self.engine.print_error(error);
+                    "S04".to_string()
+                },
+                //  SIGXCPU for the resource limits
+                _ => "S18".to_string(),
+            }
+        };
+        let stop = if self.engine.get_state().halted { "W00".to_string() } else { stop };
+        self.last_stop = stop.clone();
+        stop
+    }
+}
+
+//  A write by the instruction that just ran
+fn written_watchpoint(watchpoints: &[Watchpoint], engine_state: &SVMEngineState) -> Option<String> {
+    let write = engine_state.last_memory_write.as_ref()?;
+    watchpoints.iter()
+        .find(|watch| watch.kind != WatchKind::Read && watch.start <= write.address && write.address < watch.end)
+        .map(|watch| format!("T05{}:{:x};", watch.kind.get_stop_name(), write.address as u32 * 2))
+}
+
+//  The machine only reads memory as data in rmem, so a read is caught before it happens
+fn read_watchpoint(watchpoints: &[Watchpoint], engine_state: &SVMEngineState) -> Option<String> {
+    let ip = engine_state.instruction_pointer.get_ip();
+    let memory = &engine_state.memory;
+    match memory.load_memory(ip).ok()?.get_opcode() {
+        Ok(SVMOpCode::Rmem) => {},
+        _ => return None,
+    }
+    let address = memory.load_memory(ip.wrapping_add(2)).ok()?
+        .unwrap_potential_register(&engine_state.registers).ok()?;
+    watchpoints.iter()
+        .find(|watch| watch.kind != WatchKind::Write && watch.start <= address && address < watch.end)
+        .map(|watch| format!("T05{}:{:x};", watch.kind.get_stop_name(), address as u32 * 2))
+}
+
+//  Reads the next packet, acknowledging bad checksums with '-'. Returns None when the client disconnects
+fn read_packet<S: Read + Write>(stream: &mut S) -> io::Result<Option<String>> {
+    loop {
+        match read_byte(stream)? {
+            None => return Ok(None),
+            Some(b'$') => {},
+            //  Acknowledgements, and interrupts sent while the program was already stopped
+            Some(_) => continue,
+        }
+
+        let mut data = Vec::new();
+        let mut checksum: u8 = 0;
+        loop {
+            let byte = match read_byte(stream)? {
+                Some(byte) => byte,
+                None => return Ok(None),
+            };
+            if byte == b'#' {
+                break;
+            }
+            checksum = checksum.wrapping_add(byte);
+            if byte == b'}' {
+                let escaped = match read_byte(stream)? {
+                    Some(escaped) => escaped,
+                    None => return Ok(None),
+                };
+                checksum = checksum.wrapping_add(escaped);
+                data.push(escaped ^ 0x20);
+            } else {
+                data.push(byte);
+            }
+        }
+
+        let mut expected = [0; 2];
+        stream.read_exact(&mut expected)?;
+        let expected = std::str::from_utf8(&expected).ok().and_then(|text| u8::from_str_radix(text, 16).ok());
+        if expected == Some(checksum) {
+            return Ok(Some(String::from_utf8_lossy(&data).into_owned()));
+        }
+        stream.write_all(b"-")?;
+    }
+}
+
+fn read_byte<S: Read>(stream: &mut S) -> io::Result<Option<u8>> {
+    let mut byte = [0; 1];
+    match stream.read(&mut byte)? {
+        0 => Ok(None),
+        _ => Ok(Some(byte[0])),
+    }
+}
+
+fn write_packet<S: Write>(stream: &mut S, data: &str) -> io::Result<()> {
+    let mut packet = Vec::with_capacity(data.len() + 4);
+    packet.push(b'$');
+    for byte in data.bytes() {
+        if matches!(byte, b'$' | b'#' | b'}' | b'*') {
+            packet.push(b'}');
+            packet.push(byte ^ 0x20);
+        } else {
+            packet.push(byte);
+        }
+    }
+    let checksum = packet[1..].iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+    packet.extend_from_slice(format!("#{:02x}", checksum).as_bytes());
+    stream.write_all(&packet)?;
+    stream.flush()
+}
+
+//  The word address of the instruction at an even byte address
+fn code_address(byte_address: u32) -> Option<u16> {
+    if byte_address.is_multiple_of(2) && byte_address / 2 <= u16::MAX as u32 {
+        Some((byte_address / 2) as u16)
+    } else {
+        None
+    }
+}
+
+fn encode_word(value: u16) -> String {
+    value.to_le_bytes().iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len()).step_by(2)
+        .map(|index| text.get(index..index + 2).and_then(|pair| u8::from_str_radix(pair, 16).ok()))
+        .collect()
+}
+
+fn parse_pair(text: &str, separator: char) -> Option<(u32, u32)> {
+    let (first, second) = text.split_once(separator)?;
+    Some((u32::from_str_radix(first, 16).ok()?, u32::from_str_radix(second, 16).ok()?))
+}
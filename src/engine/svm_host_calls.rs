@@ -0,0 +1,188 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use synacorvm_core::svm_constants::MEMORY_SIZE_MAX;
+use synacorvm_core::svm_engine_state::SVMEngineState;
+use synacorvm_core::svm_error::SVMError;
+
+//  What a service leaves in r0 when it fails, out of the range of anything it returns on success
+pub const HOST_CALL_FAILED: u16 = 0x7FFF;
+//  File names are zero-terminated, one character per word
+const MAX_NAME_LENGTH: u16 = 255;
+
+pub const SERVICE_EXIT: u16 = 0;
+pub const SERVICE_TIME: u16 = 1;
+pub const SERVICE_OPEN: u16 = 2;
+pub const SERVICE_CLOSE: u16 = 3;
+pub const SERVICE_READ: u16 = 4;
+pub const SERVICE_WRITE: u16 = 5;
+
+pub const OPEN_READ: u16 = 0;
+pub const OPEN_WRITE: u16 = 1;
+pub const OPEN_APPEND: u16 = 2;
+
+//  The services behind hcall. r0 selects the service, r1 to r3 are its
+//  arguments and r0 its result:
+//
+//      0 exit    r1 status                       halts with an exit status
+//      1 time    -> r0 r1 r2                     seconds since 1970, 15 bits per register from least significant
+//      2 open    r1 name address, r2 mode        -> r0 handle, modes 0 read, 1 write, 2 append
+//      3 close   r1 handle                       -> r0 0
+//      4 read    r1 handle, r2 address, r3 count -> r0 bytes read, one per word, 0 at the end of the file
+//      5 write   r1 handle, r2 address, r3 count -> r0 bytes written, the low byte of each word
+//
+//  Files can only be named relative to the sandbox directory and never leave
+//  it. A service that fails returns HOST_CALL_FAILED instead of faulting, so
+//  programs can handle missing files; an unknown service is an invalid operand.
+pub struct SVMHostCalls {
+    sandbox: PathBuf,
+    files: Vec<Option<File>>,
+    exit_status: Option<u16>,
+}
+
+impl SVMHostCalls {
+    pub fn new(sandbox: PathBuf) -> SVMHostCalls {
+        SVMHostCalls {
+            sandbox,
+            files: Vec::new(),
+            exit_status: None,
+        }
+    }
+
+    pub fn get_sandbox(&self) -> &Path {
+        &self.sandbox
+    }
+
+    //  The status the program gave the exit service, if it used it
+    pub fn get_exit_status(&self) -> Option<u16> {
+        self.exit_status
+    }
+
+    pub fn call(&mut self, engine_state: &mut SVMEngineState) -> Result<(), SVMError> {
+        let argument = |index| engine_state.registers.get_register_by_index(index);
+        let (service, first, second, third) = (argument(0), argument(1), argument(2), argument(3));
+        let result = match service {
+            SERVICE_EXIT => {
+                self.exit_status = Some(first);
+                engine_state.halted = true;
+                return Ok(());
+            },
+            SERVICE_TIME => {
+                let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(0);
+                for index in 0..3 {
+                    engine_state.registers.set_register_by_index(index, ((seconds >> (index * 15)) & 0x7FFF) as u16);
+                }
+                return Ok(());
+            },
+            SERVICE_OPEN => {
+                let name = read_name(engine_state, first)?;
+                self.open(&name, second)
+            },
+            SERVICE_CLOSE => self.files.get_mut(first as usize).and_then(Option::take).map(|_| 0),
+            SERVICE_READ => self.read(engine_state, first, second, third)?,
+            SERVICE_WRITE => self.write(engine_state, first, second, third)?,
+            _ => return Err(SVMError::InvalidOperand),
+        };
+        engine_state.registers.set_register_by_index(0, result.unwrap_or(HOST_CALL_FAILED));
+        Ok(())
+    }
+
+    fn open(&mut self, name: &str, mode: u16) -> Option<u16> {
+        let path = self.resolve(name)?;
+        let mut options = OpenOptions::new();
+        match mode {
+            OPEN_READ => options.read(true),
+            OPEN_WRITE => options.write(true).create(true).truncate(true),
+            OPEN_APPEND => options.append(true).create(true),
+            _ => return None,
+        };
+        let file = options.open(path).ok()?;
+        let handle = match self.files.iter().position(Option::is_none) {
+            Some(handle) => handle,
+            None => {
+                self.files.push(None);
+                self.files.len() - 1
+            },
+        };
+        if handle >= HOST_CALL_FAILED as usize {
+            return None;
+        }
+        self.files[handle] = Some(file);
+        Some(handle as u16)
+    }
+
+    //  Memory errors fault like they would for rmem and wmem, file errors fail the call
+    fn read(&mut self, engine_state: &mut SVMEngineState, handle: u16, address: u16, count: u16) -> Result<Option<u16>, SVMError> {
+        check_buffer(address, count)?;
+        let file = match self.file(handle) {
+            Some(file) => file,
+            None => return Ok(None),
+        };
+        let mut buffer = vec![0; count as usize];
+        let length = match file.read(&mut buffer) {
+            Ok(length) => length,
+            Err(_) => return Ok(None),
+        };
+        for (offset, byte) in buffer[..length].iter().enumerate() {
+            engine_state.memory.store_memory(address + offset as u16, *byte as u16)?;
+        }
+        Ok(Some(length as u16))
+    }
+
+    fn write(&mut self, engine_state: &mut SVMEngineState, handle: u16, address: u16, count: u16) -> Result<Option<u16>, SVMError> {
+        check_buffer(address, count)?;
+        let bytes = (0..count)
+            .map(|offset| engine_state.memory.load_memory(address + offset).map(|word| word as u8))
+            .collect::<Result<Vec<u8>, SVMError>>()?;
+        let file = match self.file(handle) {
+            Some(file) => file,
+            None => return Ok(None),
+        };
+        Ok(file.write_all(&bytes).ok().map(|_| count))
+    }
+
+    fn file(&mut self, handle: u16) -> Option<&mut File> {
+        self.files.get_mut(handle as usize).and_then(Option::as_mut)
+    }
+
+    //  Only plain relative names are allowed, and a symbolic link inside the
+    //  sandbox must not lead out of it either
+    fn resolve(&self, name: &str) -> Option<PathBuf> {
+        let relative = Path::new(name);
+        if name.is_empty() || !relative.components().all(|component| matches!(component, Component::Normal(_))) {
+            return None;
+        }
+        let sandbox = fs::canonicalize(&self.sandbox).ok()?;
+        let path = sandbox.join(relative);
+        let parent = fs::canonicalize(path.parent()?).ok()?;
+        let resolved = match fs::symlink_metadata(&path) {
+            Ok(_) => fs::canonicalize(&path).ok()?,
+            Err(_) => parent.join(path.file_name()?),
+        };
+        if parent.starts_with(&sandbox) && resolved.starts_with(&sandbox) { Some(resolved) } else { None }
+    }
+}
+
+//  A buffer has to lie in memory, like the operand of rmem or wmem, and be
+//  short enough that its length cannot be mistaken for a failure
+fn check_buffer(address: u16, count: u16) -> Result<(), SVMError> {
+    if count >= HOST_CALL_FAILED {
+        return Err(SVMError::InvalidOperand);
+    }
+    if address as usize + count as usize > MEMORY_SIZE_MAX {
+        return Err(SVMError::InvalidMemory);
+    }
+    Ok(())
+}
+
+fn read_name(engine_state: &SVMEngineState, address: u16) -> Result<String, SVMError> {
+    let mut name = String::new();
+    for offset in 0..MAX_NAME_LENGTH {
+        match engine_state.memory.load_memory(address.checked_add(offset).ok_or(SVMError::InvalidMemory)?)? {
+            0 => return Ok(name),
+            character => name.push(character as u8 as char),
+        }
+    }
+    Err(SVMError::InvalidOperand)
+}
@@ -0,0 +1,303 @@
+use std::io::{self, Write};
+use byteorder::{LittleEndian, WriteBytesExt};
+use synacorvm_core::svm_constants::{MEMORY_SIZE_MAX, NUM_OF_REGISTERS};
+use super::svm_program::SVMProgram;
+use super::svm_symbols::SVMSymbols;
+
+//  Container images are little-endian like raw ones:
+//  magic, version, entry point, the initial registers, a segment count and per
+//  segment its load address, length and words, then a symbol count and per
+//  symbol its address, name length and UTF-8 name.
+pub const CONTAINER_MAGIC: &[u8; 4] = b"SVMI";
+const CONTAINER_VERSION: u16 = 1;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ImageFormat {
+    Raw,
+    Container,
+    HexText,
+    IntelHex,
+}
+
+//  Text images are told apart from raw ones by holding nothing but printable
+//  ASCII, which a raw image cannot do since its first word is an opcode and so
+//  starts with a control character and a zero. Intel HEX records start with a colon.
+pub fn detect_format(data: &[u8]) -> ImageFormat {
+    if data.starts_with(CONTAINER_MAGIC) {
+        return ImageFormat::Container;
+    }
+    let is_text = !data.is_empty() && data.iter().all(|byte| byte.is_ascii_graphic() || byte.is_ascii_whitespace());
+    match data.iter().find(|byte| !byte.is_ascii_whitespace()) {
+        Some(b':') if is_text => ImageFormat::IntelHex,
+        Some(_) if is_text => ImageFormat::HexText,
+        _ => ImageFormat::Raw,
+    }
+}
+
+//  Words to be placed at a load address
+#[derive(Clone, PartialEq, Debug)]
+pub struct Segment {
+    pub load_address: u16,
+    pub words: Vec<u16>,
+}
+
+//  The contents of a container image, as written by a toolchain
+#[derive(Clone, Default)]
+pub struct ContainerImage {
+    pub entry_point: u16,
+    pub initial_registers: [u16; NUM_OF_REGISTERS],
+    pub segments: Vec<Segment>,
+    pub symbols: Vec<(u16, String)>,
+}
+
+impl ContainerImage {
+    pub fn read(data: &[u8]) -> Result<ContainerImage, String> {
+        let mut reader = Reader { data, position: 0 };
+        if reader.take(CONTAINER_MAGIC.len())? != CONTAINER_MAGIC {
+            return Err("not a container image".to_string());
+        }
+        let version = reader.word()?;
+        if version == 0 || version > CONTAINER_VERSION {
+            return Err(format!("unsupported container version {}", version));
+        }
+        let mut image = ContainerImage { entry_point: reader.word()?, ..ContainerImage::default() };
+        for register in image.initial_registers.iter_mut() {
+            *register = reader.word()?;
+        }
+        for _ in 0..reader.word()? {
+            let load_address = reader.word()?;
+            let length = reader.word()? as usize;
+            let words = reader.take(length * 2)?.chunks(2).map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]])).collect();
+            image.segments.push(Segment { load_address, words });
+        }
+        for _ in 0..reader.word()? {
+            let address = reader.word()?;
+            let length = reader.word()? as usize;
+            let name = String::from_utf8(reader.take(length)?.to_vec()).map_err(|_| "a symbol name is not UTF-8".to_string())?;
+            image.symbols.push((address, name));
+        }
+        if reader.position != data.len() {
+            return Err("unexpected data after the symbol table".to_string());
+        }
+        Ok(image)
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(CONTAINER_MAGIC)?;
+        writer.write_u16::<LittleEndian>(CONTAINER_VERSION)?;
+        writer.write_u16::<LittleEndian>(self.entry_point)?;
+        for register in self.initial_registers.iter() {
+            writer.write_u16::<LittleEndian>(*register)?;
+        }
+        writer.write_u16::<LittleEndian>(self.segments.len() as u16)?;
+        for segment in self.segments.iter() {
+            writer.write_u16::<LittleEndian>(segment.load_address)?;
+            writer.write_u16::<LittleEndian>(segment.words.len() as u16)?;
+            for word in segment.words.iter() {
+                writer.write_u16::<LittleEndian>(*word)?;
+            }
+        }
+        writer.write_u16::<LittleEndian>(self.symbols.len() as u16)?;
+        for (address, name) in self.symbols.iter() {
+            writer.write_u16::<LittleEndian>(*address)?;
+            writer.write_u16::<LittleEndian>(name.len() as u16)?;
+            writer.write_all(name.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn into_program(self) -> Result<SVMProgram, String> {
+        let mut image = ImageWriter::new();
+        for segment in self.segments.iter() {
+            for (offset, word) in segment.words.iter().enumerate() {
+                image.place(segment.load_address as usize + offset, *word)?;
+            }
+        }
+        let mut program = image.into_program(Some(self.entry_point))?;
+        if let Some(register) = self.initial_registers.iter().find(|register| **register as usize >= MEMORY_SIZE_MAX) {
+            return Err(format!("initial register value {} is not a 15-bit number", register));
+        }
+        program.set_initial_registers(self.initial_registers);
+        if !self.symbols.is_empty() {
+            let mut symbols = SVMSymbols::new();
+            for (address, name) in self.symbols.iter() {
+                symbols.set_label(*address, name);
+            }
+            program.set_symbols(symbols);
+        }
+        Ok(program)
+    }
+}
+
+//  Hex words separated by whitespace, in the style of Verilog's $readmemh:
+//
+//      // the entry code
+//      0013 0041 0000
+//      @0100
+//      0015 0000
+//
+//  "@address" moves the load address, in words and hex. Comments start with
+//  // or # and run to the end of the line.
+pub fn parse_hex_text(data: &[u8]) -> Result<SVMProgram, String> {
+    let text = String::from_utf8_lossy(data);
+    let mut image = ImageWriter::new();
+    let mut address = 0;
+    for (index, line) in text.lines().enumerate() {
+        let line = line.split("//").next().unwrap_or("").split('#').next().unwrap_or("");
+        for token in line.split_whitespace() {
+            let (origin, digits) = match token.strip_prefix('@') {
+                Some(digits) => (true, digits),
+                None => (false, token),
+            };
+            let value = u16::from_str_radix(digits, 16).map_err(|_| format!("line {}: {} is not a hex word", index + 1, token))?;
+            if origin {
+                address = value as usize;
+            } else {
+                image.place(address, value).map_err(|error| format!("line {}: {}", index + 1, error))?;
+                address += 1;
+            }
+        }
+    }
+    image.into_program(None)
+}
+
+//  Intel HEX with data, end of file, segment and linear address records. The
+//  addresses are in bytes and pairs of bytes make little-endian words. A start
+//  address record sets the entry point.
+pub fn parse_intel_hex(data: &[u8]) -> Result<SVMProgram, String> {
+    let text = String::from_utf8_lossy(data);
+    let mut bytes: Vec<Option<u8>> = vec![None; MEMORY_SIZE_MAX * 2];
+    let mut base = 0;
+    let mut entry_point = None;
+    let mut ended = false;
+    for (index, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let line_error = |message: &str| format!("line {}: {}", index + 1, message);
+        if ended {
+            return Err(line_error("record after the end of file record"));
+        }
+        let record = decode_record(line.trim()).map_err(|error| line_error(&error))?;
+        let (length, offset, kind, payload) = (record[0] as usize, u16::from_be_bytes([record[1], record[2]]), record[3], &record[4..record.len() - 1]);
+        if payload.len() != length {
+            return Err(line_error("the byte count does not match the record"));
+        }
+        match kind {
+            0x00 => {
+                for (position, byte) in payload.iter().enumerate() {
+                    let address = base + offset as usize + position;
+                    let slot = bytes.get_mut(address).ok_or_else(|| line_error(&format!("byte address {} is outside memory", address)))?;
+                    if slot.is_some() {
+                        return Err(line_error(&format!("byte address {} is written twice", address)));
+                    }
+                    *slot = Some(*byte);
+                }
+            },
+            0x01 => ended = true,
+            0x02 if length == 2 => base = (u16::from_be_bytes([payload[0], payload[1]]) as usize) << 4,
+            0x04 if length == 2 => base = (u16::from_be_bytes([payload[0], payload[1]]) as usize) << 16,
+            0x03 if length == 4 => {
+                let segment = u16::from_be_bytes([payload[0], payload[1]]) as usize;
+                entry_point = Some(((segment << 4) + u16::from_be_bytes([payload[2], payload[3]]) as usize) / 2);
+            },
+            0x05 if length == 4 => entry_point = Some(u32::from_be_bytes([payload[0], payload[1], payload[2], payload[3]]) as usize / 2),
+            _ => return Err(line_error(&format!("unsupported record type {:02X} of length {}", kind, length))),
+        }
+    }
+    if !ended {
+        return Err("missing the end of file record".to_string());
+    }
+
+    let mut image = ImageWriter::new();
+    for (address, pair) in bytes.chunks(2).enumerate() {
+        if pair[0].is_some() || pair[1].is_some() {
+            image.place(address, u16::from_le_bytes([pair[0].unwrap_or(0), pair[1].unwrap_or(0)]))?;
+        }
+    }
+    let entry_point = match entry_point {
+        Some(entry_point) if entry_point < MEMORY_SIZE_MAX => Some(entry_point as u16),
+        Some(entry_point) => return Err(format!("the start address {} is outside memory", entry_point * 2)),
+        None => None,
+    };
+    image.into_program(entry_point)
+}
+
+//  A record's bytes after the colon, with its checksum verified
+fn decode_record(line: &str) -> Result<Vec<u8>, String> {
+    let digits = line.strip_prefix(':').ok_or("a record does not start with a colon")?;
+    if digits.len() % 2 != 0 || digits.len() < 10 {
+        return Err("a record is too short".to_string());
+    }
+    //  Checked first so that the byte offsets below are character boundaries
+    if !digits.is_ascii() {
+        return Err("a record has a character that is not hex".to_string());
+    }
+    let record = (0..digits.len()).step_by(2)
+        .map(|position| u8::from_str_radix(&digits[position..position + 2], 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .map_err(|_| "a record has a character that is not hex".to_string())?;
+    if record.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) != 0 {
+        return Err("bad checksum".to_string());
+    }
+    Ok(record)
+}
+
+//  Places words at addresses, refusing to write the same address twice or
+//  past the end of memory
+struct ImageWriter {
+    words: Vec<u16>,
+    written: Vec<bool>,
+}
+
+impl ImageWriter {
+    fn new() -> ImageWriter {
+        ImageWriter {
+            words: Vec::new(),
+            written: Vec::new(),
+        }
+    }
+
+    fn place(&mut self, address: usize, word: u16) -> Result<(), String> {
+        if address >= MEMORY_SIZE_MAX {
+            return Err(format!("address {} is outside memory", address));
+        }
+        if self.words.len() <= address {
+            self.words.resize(address + 1, 0);
+            self.written.resize(address + 1, false);
+        }
+        if self.written[address] {
+            return Err(format!("address {} is loaded twice", address));
+        }
+        self.words[address] = word;
+        self.written[address] = true;
+        Ok(())
+    }
+
+    fn into_program(self, entry_point: Option<u16>) -> Result<SVMProgram, String> {
+        let mut program = SVMProgram::from_words(self.words);
+        if let Some(entry_point) = entry_point {
+            if entry_point as usize >= MEMORY_SIZE_MAX {
+                return Err(format!("the entry point {} is outside memory", entry_point));
+            }
+            program.set_entry_point(entry_point);
+        }
+        Ok(program)
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], String> {
+        let end = self.position.checked_add(length).filter(|end| *end <= self.data.len())
+            .ok_or_else(|| "the container image is truncated".to_string())?;
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn word(&mut self) -> Result<u16, String> {
+        self.take(2).map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+}
@@ -0,0 +1,18 @@
+use std::time::Duration;
+
+//  Resource limits for running untrusted programs. Anything left as None is unlimited.
+#[derive(Clone, Default)]
+pub struct SVMLimits {
+    pub max_instructions: Option<u64>,
+    pub max_duration: Option<Duration>,
+    pub max_stack_depth: Option<usize>,
+    pub max_output_bytes: Option<u64>,
+    pub max_input_reads: Option<u64>,
+}
+
+impl SVMLimits {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_instructions.is_none() && self.max_duration.is_none() && self.max_stack_depth.is_none()
+            && self.max_output_bytes.is_none() && self.max_input_reads.is_none()
+    }
+}
@@ -0,0 +1,83 @@
+use synacorvm_core::memory::Memory;
+use synacorvm_core::svm_constants::MEMORY_SIZE_MAX;
+use super::svm_symbols::parse_number;
+
+pub enum SearchFilter {
+    Changed,
+    Unchanged,
+    Increased,
+    Decreased,
+    Equals(u16),
+}
+
+impl SearchFilter {
+    pub fn parse(arguments: &[&str]) -> Result<SearchFilter, String> {
+        match arguments {
+            ["changed"] => Ok(SearchFilter::Changed),
+            ["unchanged"] => Ok(SearchFilter::Unchanged),
+            ["increased"] => Ok(SearchFilter::Increased),
+            ["decreased"] => Ok(SearchFilter::Decreased),
+            ["equals", value] | ["eq", value] => parse_value(value).map(SearchFilter::Equals),
+            _ => Err("Filter must be changed, unchanged, increased, decreased or equals <n>".to_string()),
+        }
+    }
+
+    fn matches(&self, previous: u16, current: u16) -> bool {
+        match *self {
+            SearchFilter::Changed => current != previous,
+            SearchFilter::Unchanged => current == previous,
+            SearchFilter::Increased => current > previous,
+            SearchFilter::Decreased => current < previous,
+            SearchFilter::Equals(value) => current == value,
+        }
+    }
+}
+
+//  A decimal or 0x hex value; memory only holds values up to 32767, so a larger one could never match
+pub fn parse_value(text: &str) -> Result<u16, String> {
+    match parse_number(text)? {
+        value if value as usize >= MEMORY_SIZE_MAX => Err(format!("Invalid value {}, values go up to {}", text, MEMORY_SIZE_MAX - 1)),
+        value => Ok(value),
+    }
+}
+
+pub struct Candidate {
+    pub address: u16,
+    pub value: u16,
+}
+
+//  Narrows down the addresses holding a variable by repeatedly filtering them
+//  against how their value changed since the previous scan
+pub struct SVMMemorySearch {
+    candidates: Vec<Candidate>,
+}
+
+impl SVMMemorySearch {
+    //  Starts with every address, for when the initial value of the variable is unknown
+    pub fn scan_all(memory: &Memory) -> SVMMemorySearch {
+        SVMMemorySearch {
+            candidates: (0..MEMORY_SIZE_MAX as u16)
+                .map(|address| Candidate { address, value: memory.load_memory(address).unwrap_or(0) })
+                .collect(),
+        }
+    }
+
+    pub fn scan_exact(memory: &Memory, value: u16) -> SVMMemorySearch {
+        let mut search = SVMMemorySearch::scan_all(memory);
+        search.candidates.retain(|candidate| candidate.value == value);
+        search
+    }
+
+    pub fn refine(&mut self, memory: &Memory, filter: &SearchFilter) {
+        self.candidates.retain_mut(|candidate| {
+            let current = memory.load_memory(candidate.address).unwrap_or(0);
+            let keep = filter.matches(candidate.value, current);
+            candidate.value = current;
+            keep
+        });
+    }
+
+    pub fn get_candidates(&self) -> &[Candidate] {
+        &self.candidates
+    }
+}
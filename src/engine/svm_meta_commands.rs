@@ -0,0 +1,161 @@
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::time::{SystemTime, UNIX_EPOCH};
+use synacorvm_core::svm_constants::NUM_OF_REGISTERS;
+use super::svm_engine::SVMEngine;
+use super::svm_symbols::parse_number;
+
+const HELP_TEXT: &str = "Host commands:
+  save <slot>           Save the machine to a named slot
+  load <slot>           Restore a slot
+  slots                 List the saved slots
+  undo [n]              Take back the last n commands (default 1)
+  regs                  Show the instruction pointer and registers
+  set <rN|addr> <value> Set a register or a memory word
+  mem <addr> [n]        Show n words of memory (default 8)
+  trace on [file]       Trace instructions to the file, or stderr
+  trace off             Stop tracing
+  help                  Show this help
+";
+
+//  Runs a console line the engine intercepted because it started with the meta
+//  prefix, with the prefix removed. The machine is between instructions, waiting
+//  for input. Returns the text to show the player, errors included.
+pub fn execute_meta_command(engine: &mut SVMEngine, line: &str) -> String {
+    let arguments: Vec<&str> = line.split_whitespace().collect();
+    let result = match arguments.first().cloned().unwrap_or("") {
+        "save" => match arguments.get(1) {
+            Some(name) => engine.get_save_slots().save(name, engine.get_state(), engine.get_last_output_line())
+                .map(|_| format!("Saved {}\n", name))
+                .map_err(|error| error.to_string()),
+            None => Err("save requires a slot name".to_string()),
+        },
+        "load" => match arguments.get(1) {
+            Some(name) => engine.get_save_slots().load(name)
+                .map(|engine_state| {
+                    engine.set_state(engine_state);
+                    format!("Loaded {}\n", name)
+                })
+                .map_err(|error| error.to_string()),
+            None => Err("load requires a slot name".to_string()),
+        },
+        "slots" => list_slots(engine),
+        "undo" => match arguments.get(1) {
+            Some(text) => parse_number(text),
+            None => Ok(1),
+        }.and_then(|count| {
+            engine.undo(count as usize)?;
+            Ok(format!("Undid {} command(s)\n", count))
+        }),
+        "regs" => Ok(format_registers(engine)),
+        "set" => set(engine, &arguments[1..]),
+        "mem" => memory(engine, &arguments[1..]),
+        "trace" => trace(engine, &arguments[1..]),
+        "help" | "" => Ok(HELP_TEXT.to_string()),
+        command => Err(format!("Unknown command {}, try help", command)),
+    };
+    match result {
+        Ok(response) => response,
+        Err(error) => format!("{}\n", error),
+    }
+}
+
+fn format_registers(engine: &SVMEngine) -> String {
+    let engine_state = engine.get_state();
+    let mut text = format!("ip: {}\n", engine_state.instruction_pointer.get_ip());
+    for index in 0..NUM_OF_REGISTERS {
+        text.push_str(&format!("r{}: {}\n", index, engine_state.registers.get_register_by_index(index)));
+    }
+    text
+}
+
+fn list_slots(engine: &SVMEngine) -> Result<String, String> {
+    let slots = engine.get_save_slots().list().map_err(|error| error.to_string())?;
+    if slots.is_empty() {
+        return Ok("No saved slots\n".to_string());
+    }
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(0);
+    let mut text = String::new();
+    for slot in slots {
+        text.push_str(&format!("  {:16} {:>12} instructions  {:>8}  {}\n",
+            slot.name, slot.instruction_count, format_age(now.saturating_sub(slot.saved_at)), slot.last_output_line));
+    }
+    Ok(text)
+}
+
+fn format_age(seconds: u64) -> String {
+    match seconds {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{}m ago", seconds / 60),
+        3600..=86399 => format!("{}h ago", seconds / 3600),
+        _ => format!("{}d ago", seconds / 86400),
+    }
+}
+
+fn set(engine: &mut SVMEngine, arguments: &[&str]) -> Result<String, String> {
+    let (target, value) = match arguments {
+        [target, value] => (*target, parse_number(value)?),
+        _ => return Err("set requires a register or address and a value".to_string()),
+    };
+    let register = target.strip_prefix('r').and_then(|index| index.parse::<usize>().ok());
+    match register {
+        Some(index) if index < NUM_OF_REGISTERS => {
+            engine.get_state_mut().registers.set_register_by_index(index, value);
+        },
+        Some(index) => return Err(format!("Invalid register r{}", index)),
+        None => {
+            let address = engine.get_symbols().parse_address(target)?;
+            engine.get_state_mut().memory.store_memory(address, value)
+                .map_err(|error| error.get_description().to_string())?;
+        },
+    }
+    Ok(format!("{} = {}\n", target, value))
+}
+
+fn memory(engine: &SVMEngine, arguments: &[&str]) -> Result<String, String> {
+    let address = match arguments.first() {
+        Some(text) => engine.get_symbols().parse_address(text)?,
+        None => return Err("mem requires an address".to_string()),
+    };
+    let count = match arguments.get(1) {
+        Some(text) => parse_number(text)?,
+        None => 8,
+    };
+    let memory = &engine.get_state().memory;
+    let mut text = String::new();
+    for offset in 0..count {
+        let current = address.saturating_add(offset);
+        let value = match memory.load_memory(current) {
+            Ok(value) => value,
+            Err(_) => break,
+        };
+        if offset % 8 == 0 {
+            if offset > 0 {
+                text.push('\n');
+            }
+            text.push_str(&format!("{:5}:", current));
+        }
+        text.push_str(&format!(" {:5}", value));
+    }
+    text.push('\n');
+    Ok(text)
+}
+
+fn trace(engine: &mut SVMEngine, arguments: &[&str]) -> Result<String, String> {
+    match arguments {
+        ["on"] => {
+            engine.enable_trace(Box::new(io::stderr()));
+            Ok("Tracing to stderr\n".to_string())
+        },
+        ["on", path] => {
+            let file = File::create(path).map_err(|error| format!("Cannot create {}: {}", path, error))?;
+            engine.enable_trace(Box::new(BufWriter::new(file)));
+            Ok(format!("Tracing to {}\n", path))
+        },
+        ["off"] => {
+            engine.disable_trace();
+            Ok("Tracing off\n".to_string())
+        },
+        _ => Err("trace requires on [file] or off".to_string()),
+    }
+}
@@ -0,0 +1,133 @@
+use std::fs;
+use std::path::Path;
+use synacorvm_core::opcode::ExtensionSet;
+use super::svm_assembler::SVMAssembly;
+use super::svm_program::SVMProgram;
+use super::svm_symbols::parse_number;
+
+//  One replacement at an address, checked against the words it expects to replace
+pub struct SVMPatch {
+    pub address: u16,
+    pub expected: Vec<u16>,
+    pub replacement: Vec<u16>,
+    //  The line of the patch's "at", for error messages
+    pub line: usize,
+}
+
+//  Patch files describe changes to a program image:
+//
+//      ; skip the self-test
+//      at 5489
+//      expect 8 32768 5605
+//      words 21 21 21
+//
+//      at 0x1792
+//      expect 7 32775 6047
+//      asm jmp 6047
+//      asm noop
+//
+//  Each patch starts with "at <address>" and needs an "expect" line with the
+//  original words, so a patch made for another image fails instead of
+//  corrupting it. The replacement is given as numbers with "words", as
+//  assembly with "asm", or both, in order. Comments start with a semicolon.
+pub struct SVMPatchFile {
+    patches: Vec<SVMPatch>,
+}
+
+impl SVMPatchFile {
+    pub fn load(path: &Path, extensions: ExtensionSet) -> Result<SVMPatchFile, String> {
+        let source = fs::read_to_string(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+        SVMPatchFile::parse(&source, extensions).map_err(|error| format!("{}: {}", path.display(), error))
+    }
+
+    //  Assembly in the patches may use the mnemonics of the extensions in the set
+    pub fn parse(source: &str, extensions: ExtensionSet) -> Result<SVMPatchFile, String> {
+        let mut patches: Vec<PatchSource> = Vec::new();
+        for (index, text) in source.lines().enumerate() {
+            let line = index + 1;
+            let text = text.trim();
+            if text.is_empty() || text.starts_with(';') {
+                continue;
+            }
+            //  Assembly keeps its comment, the assembler drops it without tripping on ';' literals
+            let (directive, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+            let rest = if directive == "asm" { rest } else { rest.split(';').next().unwrap_or("") }.trim();
+            if directive == "at" {
+                let address = parse_number(rest).map_err(|error| format!("line {}: {}", line, error))?;
+                patches.push(PatchSource { address, expected: Vec::new(), statements: Vec::new(), line });
+                continue;
+            }
+            let patch = patches.last_mut().ok_or_else(|| format!("line {}: {} before the first at", line, directive))?;
+            match directive {
+                "expect" => patch.expected.extend(parse_words(rest).map_err(|error| format!("line {}: {}", line, error))?),
+                "words" => {
+                    let words = parse_words(rest).map_err(|error| format!("line {}: {}", line, error))?;
+                    let words: Vec<String> = words.iter().map(|word| word.to_string()).collect();
+                    patch.statements.push((line, format!("data {}", words.join(" "))));
+                },
+                "asm" => patch.statements.push((line, rest.to_string())),
+                _ => return Err(format!("line {}: unknown directive {}", line, directive)),
+            }
+        }
+
+        let patches = patches.into_iter().map(|patch| patch.assemble(extensions)).collect::<Result<Vec<SVMPatch>, String>>()?;
+        Ok(SVMPatchFile { patches })
+    }
+
+    pub fn get_patches(&self) -> &[SVMPatch] {
+        &self.patches
+    }
+
+    //  Applies the patches in order, stopping at the first that does not match the program
+    pub fn apply(&self, program: &mut SVMProgram) -> Result<(), String> {
+        for patch in self.patches.iter() {
+            program.patch(patch.address, &patch.expected, &patch.replacement)
+                .map_err(|error| format!("line {}: {}", patch.line, error))?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_words(text: &str) -> Result<Vec<u16>, String> {
+    text.split_whitespace().map(parse_number).collect()
+}
+
+//  A patch as written, with its replacement still to be assembled
+struct PatchSource {
+    address: u16,
+    expected: Vec<u16>,
+    //  Assembly statements and the lines they came from, "words" becoming data
+    statements: Vec<(usize, String)>,
+    line: usize,
+}
+
+impl PatchSource {
+    //  Assembles the replacement where it will be placed, so its labels can be jumped to
+    fn assemble(self, extensions: ExtensionSet) -> Result<SVMPatch, String> {
+        if self.expected.is_empty() {
+            return Err(format!("line {}: the patch at {} has no expect line", self.line, self.address));
+        }
+        let mut source = format!(".org {}\n", self.address);
+        for (_, statement) in self.statements.iter() {
+            source.push_str(statement);
+            source.push('\n');
+        }
+        let assembly = SVMAssembly::assemble(&source, extensions).map_err(|error| self.map_assembler_error(error))?;
+        let replacement = assembly.get_words().get(self.address as usize..).unwrap_or(&[]).to_vec();
+        if replacement.is_empty() {
+            return Err(format!("line {}: the patch at {} has no words or asm", self.line, self.address));
+        }
+        Ok(SVMPatch { address: self.address, expected: self.expected, replacement, line: self.line })
+    }
+
+    //  The assembler numbers lines within the generated source, after the .org
+    fn map_assembler_error(&self, error: String) -> String {
+        let message = error.strip_prefix("line ").and_then(|rest| rest.split_once(": "));
+        match message.and_then(|(number, message)| Some((number.parse::<usize>().ok()?, message))) {
+            Some((number, message)) if number >= 2 && number - 2 < self.statements.len() => {
+                format!("line {}: {}", self.statements[number - 2].0, message)
+            },
+            _ => format!("line {}: {}", self.line, error),
+        }
+    }
+}
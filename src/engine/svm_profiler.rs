@@ -0,0 +1,209 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+use synacorvm_core::memory::Memory;
+use synacorvm_core::opcode::{ExtensionSet, OpcodeValue, NUM_OF_OPCODES};
+use synacorvm_core::svm_constants::MEMORY_SIZE_MAX;
+use super::svm_disassembler::disassemble_instruction;
+use super::svm_symbols::SVMSymbols;
+
+//  A node in the call tree. The same function appears once per distinct call path
+struct CallNode {
+    function: Option<u16>,
+    parent: usize,
+    children: HashMap<u16, usize>,
+    calls: u64,
+    self_cycles: u64,
+}
+
+#[derive(Default)]
+pub struct FunctionStatistics {
+    pub calls: u64,
+    pub inclusive_cycles: u64,
+    pub exclusive_cycles: u64,
+}
+
+//  Counts executed instructions per address and opcode and attributes them
+//  to functions by following call and ret. The root of the call tree is the
+//  code that runs before the first call.
+pub struct SVMProfiler {
+    address_counts: Vec<u64>,
+    opcode_counts: [u64; NUM_OF_OPCODES as usize],
+    nodes: Vec<CallNode>,
+    current_node: usize,
+    total_cycles: u64,
+}
+
+impl Default for SVMProfiler {
+    fn default() -> SVMProfiler {
+        SVMProfiler::new()
+    }
+}
+
+impl SVMProfiler {
+    pub fn new() -> SVMProfiler {
+        SVMProfiler {
+            address_counts: vec![0; MEMORY_SIZE_MAX],
+            opcode_counts: [0; NUM_OF_OPCODES as usize],
+            nodes: vec![CallNode {
+                function: None,
+                parent: 0,
+                children: HashMap::new(),
+                calls: 1,
+                self_cycles: 0,
+            }],
+            current_node: 0,
+            total_cycles: 0,
+        }
+    }
+
+    pub fn record_instruction(&mut self, address: u16, opcode_value: u16) {
+        self.total_cycles += 1;
+        if let Some(count) = self.address_counts.get_mut(address as usize) {
+            *count += 1;
+        }
+        if let Some(count) = self.opcode_counts.get_mut(opcode_value as usize) {
+            *count += 1;
+        }
+        self.nodes[self.current_node].self_cycles += 1;
+    }
+
+    pub fn record_call(&mut self, target: u16) {
+        let parent = self.current_node;
+        let next_index = self.nodes.len();
+        let child = *self.nodes[parent].children.entry(target).or_insert(next_index);
+        if child == next_index {
+            self.nodes.push(CallNode {
+                function: Some(target),
+                parent,
+                children: HashMap::new(),
+                calls: 0,
+                self_cycles: 0,
+            });
+        }
+        self.nodes[child].calls += 1;
+        self.current_node = child;
+    }
+
+    //  A ret without a matching call leaves us at the root rather than failing
+    pub fn record_return(&mut self) {
+        self.current_node = self.nodes[self.current_node].parent;
+    }
+
+    pub fn get_total_cycles(&self) -> u64 {
+        self.total_cycles
+    }
+
+    //  How many times instructions with the opcode ran
+    pub fn get_opcode_count(&self, opcode_value: u16) -> u64 {
+        self.opcode_counts.get(opcode_value as usize).cloned().unwrap_or(0)
+    }
+
+    pub fn get_function_statistics(&self) -> BTreeMap<Option<u16>, FunctionStatistics> {
+        let inclusive = self.get_inclusive_cycles();
+        let mut statistics: BTreeMap<Option<u16>, FunctionStatistics> = BTreeMap::new();
+        for (index, node) in self.nodes.iter().enumerate() {
+            let entry = statistics.entry(node.function).or_default();
+            entry.calls += node.calls;
+            entry.exclusive_cycles += node.self_cycles;
+            //  Recursive calls are already counted in the outermost frame of the same function
+            if !self.has_ancestor_function(index, node.function) {
+                entry.inclusive_cycles += inclusive[index];
+            }
+        }
+        statistics
+    }
+
+    pub fn print_report(&self, memory: &Memory, symbols: &SVMSymbols, extensions: ExtensionSet, count: usize) {
+        println!("Profile: {} instructions executed", self.total_cycles);
+
+        println!("Hot spots:");
+        let mut addresses: Vec<(usize, u64)> = self.address_counts.iter().cloned().enumerate()
+            .filter(|(_, hits)| *hits > 0)
+            .collect();
+        addresses.sort_by(|left, right| right.1.cmp(&left.1).then(left.0.cmp(&right.0)));
+        for (address, hits) in addresses.iter().take(count) {
+            let instruction = disassemble_instruction(memory, *address as u16, symbols, extensions);
+            println!("  {:>12} {:6.2}%  {:>16}: {}", hits, self.percent(*hits),
+                symbols.format_address(*address as u16), instruction.text);
+        }
+
+        println!("Opcodes:");
+        let mut opcodes: Vec<(usize, u64)> = self.opcode_counts.iter().cloned().enumerate()
+            .filter(|(_, hits)| *hits > 0)
+            .collect();
+        opcodes.sort_by_key(|(_, hits)| Reverse(*hits));
+        for (opcode_value, hits) in opcodes.iter() {
+            let mnemonic = (*opcode_value as u16).get_opcode_with(extensions).map(|opcode| opcode.get_mnemonic()).unwrap_or("?");
+            println!("  {:>12} {:6.2}%  {}", hits, self.percent(*hits), mnemonic);
+        }
+
+        println!("Functions (by inclusive instructions):");
+        println!("  {:>10} {:>12} {:>8} {:>12} {:>8}  function", "calls", "inclusive", "", "exclusive", "");
+        let mut functions: Vec<(Option<u16>, FunctionStatistics)> = self.get_function_statistics().into_iter().collect();
+        functions.sort_by_key(|(_, statistics)| Reverse(statistics.inclusive_cycles));
+        for (function, statistics) in functions.iter().take(count) {
+            println!("  {:>10} {:>12} {:7.2}% {:>12} {:7.2}%  {}", statistics.calls,
+                statistics.inclusive_cycles, self.percent(statistics.inclusive_cycles),
+                statistics.exclusive_cycles, self.percent(statistics.exclusive_cycles),
+                format_function(*function, symbols));
+        }
+    }
+
+    //  One line per call path with its exclusive instruction count, the input format of flamegraph.pl
+    pub fn write_folded_stacks<W: Write>(&self, writer: &mut W, symbols: &SVMSymbols) -> io::Result<()> {
+        for (index, node) in self.nodes.iter().enumerate() {
+            if node.self_cycles == 0 {
+                continue;
+            }
+            let mut frames = Vec::new();
+            let mut current = index;
+            loop {
+                frames.push(format_function(self.nodes[current].function, symbols));
+                if current == 0 {
+                    break;
+                }
+                current = self.nodes[current].parent;
+            }
+            frames.reverse();
+            writeln!(writer, "{} {}", frames.join(";"), node.self_cycles)?;
+        }
+        Ok(())
+    }
+
+    fn get_inclusive_cycles(&self) -> Vec<u64> {
+        //  Children are always created after their parents, so one reverse pass accumulates the totals
+        let mut inclusive: Vec<u64> = self.nodes.iter().map(|node| node.self_cycles).collect();
+        for index in (1..self.nodes.len()).rev() {
+            let parent = self.nodes[index].parent;
+            inclusive[parent] += inclusive[index];
+        }
+        inclusive
+    }
+
+    fn has_ancestor_function(&self, index: usize, function: Option<u16>) -> bool {
+        let mut current = index;
+        while current != 0 {
+            current = self.nodes[current].parent;
+            if self.nodes[current].function == function {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn percent(&self, cycles: u64) -> f64 {
+        if self.total_cycles == 0 {
+            0.0
+        } else {
+            cycles as f64 * 100.0 / self.total_cycles as f64
+        }
+    }
+}
+
+fn format_function(function: Option<u16>, symbols: &SVMSymbols) -> String {
+    match function {
+        Some(address) => symbols.format_address(address),
+        None => "<root>".to_string(),
+    }
+}
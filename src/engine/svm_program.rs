@@ -1,39 +1,114 @@
-use std::fs::File;
-use std::io::{Read, Cursor};
-use byteorder::{LittleEndian, ReadBytesExt};
-use super::internals::svm_constants::MEMORY_SIZE_MAX as PROGRAM_SIZE_MAX;
-
-pub type ByteCodeArray = [u16; PROGRAM_SIZE_MAX];
+use std::fs;
+use std::path::Path;
+use synacorvm_core::svm_constants::{MEMORY_SIZE_MAX, NUM_OF_REGISTERS};
+use synacorvm_core::svm_engine_state::SVMEngineState;
+use super::svm_image_formats::{detect_format, ImageFormat, ContainerImage, parse_hex_text, parse_intel_hex};
+use super::svm_symbols::SVMSymbols;
 
+//  A memory image with where and how execution starts. Raw images start at
+//  address 0 with every register clear, the other image formats can say otherwise.
+#[derive(Clone)]
 pub struct SVMProgram {
-    bytecode_size: usize,
-    bytecode: ByteCodeArray,
+    bytecode: Vec<u16>,
+    entry_point: u16,
+    initial_registers: [u16; NUM_OF_REGISTERS],
+    symbols: Option<SVMSymbols>,
 }
 
 impl SVMProgram {
-    pub fn new(mut file: &File) -> SVMProgram {
-        let mut file_data = Vec::new(); 
-        let file_size = file.read_to_end(&mut file_data).unwrap() as u64;
-        let mut file_data_cursor = Cursor::new(file_data);
-        let mut bytecode_size = 0;
-        let mut bytecode: ByteCodeArray = [0; PROGRAM_SIZE_MAX];
-        while file_data_cursor.position() < file_size {
-            bytecode[bytecode_size] = file_data_cursor.read_u16::<LittleEndian>().unwrap();
-            bytecode_size += 1;
-        }
+    //  Words are little-endian. A trailing odd byte becomes the low byte of a last word.
+    pub fn from_bytes(data: &[u8]) -> SVMProgram {
+        let bytecode = data.chunks(2)
+            .map(|chunk| u16::from_le_bytes([chunk[0], chunk.get(1).cloned().unwrap_or(0)]))
+            .collect();
+        SVMProgram::from_words(bytecode)
+    }
+
+    pub fn from_words(bytecode: Vec<u16>) -> SVMProgram {
         SVMProgram {
-            bytecode_size: bytecode_size,
-            bytecode: bytecode
+            bytecode,
+            entry_point: 0,
+            initial_registers: [0; NUM_OF_REGISTERS],
+            symbols: None,
         }
     }
 
-    pub fn print_program(&self) {
-        for i in 0..self.bytecode_size {
-            println!("{}: {}", i, self.bytecode[i]);
+    //  Reads a raw, container, hex text or Intel HEX image, telling them apart by their contents
+    pub fn from_image(data: &[u8]) -> Result<SVMProgram, String> {
+        match detect_format(data) {
+            ImageFormat::Raw if data.len().div_ceil(2) > MEMORY_SIZE_MAX =>
+                Err(format!("the image is {} words, memory holds {}", data.len().div_ceil(2), MEMORY_SIZE_MAX)),
+            ImageFormat::Raw => Ok(SVMProgram::from_bytes(data)),
+            ImageFormat::Container => ContainerImage::read(data)?.into_program(),
+            ImageFormat::HexText => parse_hex_text(data),
+            ImageFormat::IntelHex => parse_intel_hex(data),
         }
     }
 
-    pub(super) fn get_bytecode(&self) -> ByteCodeArray {
-        self.bytecode
+    pub fn load(path: &Path) -> Result<SVMProgram, String> {
+        let data = fs::read(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+        SVMProgram::from_image(&data).map_err(|error| format!("{}: {}", path.display(), error))
+    }
+
+    pub fn get_entry_point(&self) -> u16 {
+        self.entry_point
+    }
+
+    pub fn set_entry_point(&mut self, entry_point: u16) {
+        self.entry_point = entry_point;
+    }
+
+    pub fn get_initial_registers(&self) -> &[u16; NUM_OF_REGISTERS] {
+        &self.initial_registers
+    }
+
+    pub fn set_initial_registers(&mut self, registers: [u16; NUM_OF_REGISTERS]) {
+        self.initial_registers = registers;
+    }
+
+    //  Symbols carried by the image itself
+    pub fn get_symbols(&self) -> Option<&SVMSymbols> {
+        self.symbols.as_ref()
+    }
+
+    pub fn set_symbols(&mut self, symbols: SVMSymbols) {
+        self.symbols = Some(symbols);
+    }
+
+    //  The machine as it is before the first instruction
+    pub fn create_state(&self) -> SVMEngineState {
+        let mut engine_state = SVMEngineState::new(&self.bytecode);
+        engine_state.instruction_pointer.set_ip(self.entry_point).unwrap_or_default();
+        for (index, value) in self.initial_registers.iter().enumerate() {
+            engine_state.registers.set_register_by_index(index, *value);
+        }
+        engine_state
+    }
+
+    //  Replaces the words at the address after checking that the expected words are
+    //  there, with words past the end of the image reading as zero. The image grows
+    //  when the replacement runs past its end.
+    pub fn patch(&mut self, address: u16, expected: &[u16], replacement: &[u16]) -> Result<(), String> {
+        let start = address as usize;
+        if start + expected.len().max(replacement.len()) > MEMORY_SIZE_MAX {
+            return Err(format!("the patch at {} runs past the end of memory", address));
+        }
+        let found: Vec<u16> = (start..start + expected.len())
+            .map(|index| self.bytecode.get(index).cloned().unwrap_or(0))
+            .collect();
+        if found != expected {
+            return Err(format!("expected {:?} at {} but found {:?}", expected, address, found));
+        }
+        if self.bytecode.len() < start + replacement.len() {
+            self.bytecode.resize(start + replacement.len(), 0);
+        }
+        self.bytecode[start..start + replacement.len()].copy_from_slice(replacement);
+        Ok(())
+    }
+
+    pub fn print_program(&self) {
+        for (i, value) in self.bytecode.iter().enumerate() {
+            println!("{}: {}", i, value);
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,25 @@
+//  xorshift64*, for numbers that are reproducible from their seed alone
+#[derive(Clone)]
+pub struct XorShift64Star {
+    state: u64,
+}
+
+impl XorShift64Star {
+    //  The state must never be zero, so the lowest bit is always set
+    pub fn new(seed: u64) -> XorShift64Star {
+        XorShift64Star { state: seed | 1 }
+    }
+
+    //  Nearby seeds such as 1, 2 and 3 are spread over the state first, or
+    //  their first numbers would be nearly the same
+    pub fn from_small_seed(seed: u64) -> XorShift64Star {
+        XorShift64Star::new(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::VecDeque;
+
+const MEMORY_WORDS: usize = 32768;
+const REGISTER_BASE: u16 = 32768;
+const MODULUS: u32 = 32768;
+
+//  What one reference step did
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ReferenceStep {
+    Ran,
+    Halted,
+    AwaitingInput,
+    Failed(&'static str),
+}
+
+//  A deliberately plain interpreter written straight from the architecture
+//  spec, as something to check SVMEngine against. It shares no code with the
+//  engine and is not meant to be fast.
+pub struct SVMReference {
+    pub memory: Vec<u16>,
+    pub registers: [u16; 8],
+    pub stack: Vec<u16>,
+    pub ip: u16,
+    pub halted: bool,
+    pub input: VecDeque<u8>,
+    pub output: Vec<u8>,
+    //  The memory address written by the last step, if any
+    pub last_write: Option<u16>,
+}
+
+impl SVMReference {
+    pub fn new(program: &[u16], input: &[u8]) -> SVMReference {
+        let mut memory = vec![0; MEMORY_WORDS];
+        let length = program.len().min(MEMORY_WORDS);
+        memory[..length].copy_from_slice(&program[..length]);
+        SVMReference {
+            memory,
+            registers: [0; 8],
+            stack: Vec::new(),
+            ip: 0,
+            halted: false,
+            input: input.iter().cloned().collect(),
+            output: Vec::new(),
+            last_write: None,
+        }
+    }
+
+    pub fn step(&mut self) -> ReferenceStep {
+        if self.halted {
+            return ReferenceStep::Halted;
+        }
+        self.last_write = None;
+        let start = self.ip;
+        //  Like the engine, a failed or waiting instruction leaves ip on its opcode
+        let step = self.execute().unwrap_or_else(ReferenceStep::Failed);
+        if let ReferenceStep::Failed(_) | ReferenceStep::AwaitingInput = step {
+            self.ip = start;
+        }
+        step
+    }
+
+    fn execute(&mut self) -> Result<ReferenceStep, &'static str> {
+        let opcode = self.fetch()?;
+        match opcode {
+            //  halt
+            0 => {
+                self.halted = true;
+                return Ok(ReferenceStep::Halted);
+            },
+            //  set a b
+            1 => {
+                let (a, b) = (self.fetch()?, self.fetch_value()?);
+                self.write(a, b)?;
+            },
+            //  push a
+            2 => {
+                let a = self.fetch_value()?;
+                self.stack.push(a);
+            },
+            //  pop a
+            3 => {
+                let a = self.fetch()?;
+                let value = self.stack.pop().ok_or("pop from an empty stack")?;
+                self.write(a, value)?;
+            },
+            //  eq a b c, gt a b c
+            4 | 5 => {
+                let (a, b, c) = (self.fetch()?, self.fetch_value()?, self.fetch_value()?);
+                let result = if opcode == 4 { b == c } else { b > c };
+                self.write(a, result as u16)?;
+            },
+            //  jmp a
+            6 => {
+                let a = self.fetch_value()?;
+                self.jump(a)?;
+            },
+            //  jt a b, jf a b
+            7 | 8 => {
+                let (a, b) = (self.fetch_value()?, self.fetch_value()?);
+                if (a != 0) == (opcode == 7) {
+                    self.jump(b)?;
+                }
+            },
+            //  add, mult, mod, and, or
+            9..=13 => {
+                let (a, b, c) = (self.fetch()?, self.fetch_value()? as u32, self.fetch_value()? as u32);
+                let result = match opcode {
+                    9 => (b + c) % MODULUS,
+                    10 => (b * c) % MODULUS,
+                    11 => b.checked_rem(c).ok_or("mod by zero")?,
+                    12 => b & c,
+                    _ => b | c,
+                };
+                self.write(a, result as u16)?;
+            },
+            //  not a b
+            14 => {
+                let (a, b) = (self.fetch()?, self.fetch_value()?);
+                self.write(a, !b & 0x7FFF)?;
+            },
+            //  rmem a b
+            15 => {
+                let (a, b) = (self.fetch()?, self.fetch_value()?);
+                let value = *self.memory.get(b as usize).ok_or("read outside memory")?;
+                self.write(a, value)?;
+            },
+            //  wmem a b
+            16 => {
+                let (a, b) = (self.fetch_value()?, self.fetch_value()?);
+                self.write_memory(a, b)?;
+            },
+            //  call a
+            17 => {
+                let a = self.fetch_value()?;
+                self.stack.push(self.ip);
+                self.jump(a)?;
+            },
+            //  ret, which halts on an empty stack
+            18 => match self.stack.pop() {
+                Some(address) => self.jump(address)?,
+                None => {
+                    self.halted = true;
+                    return Ok(ReferenceStep::Halted);
+                },
+            },
+            //  out a
+            19 => {
+                let a = self.fetch_value()?;
+                self.output.push(a as u8);
+            },
+            //  in a
+            20 => {
+                let a = self.fetch()?;
+                let value = match self.input.pop_front() {
+                    Some(value) => value,
+                    None => return Ok(ReferenceStep::AwaitingInput),
+                };
+                self.write(a, value as u16)?;
+            },
+            //  noop
+            21 => {},
+            _ => return Err("invalid opcode"),
+        }
+        Ok(ReferenceStep::Ran)
+    }
+
+    fn fetch(&mut self) -> Result<u16, &'static str> {
+        let value = *self.memory.get(self.ip as usize).ok_or("instruction outside memory")?;
+        self.ip += 1;
+        Ok(value)
+    }
+
+    //  A literal or the contents of a register
+    fn fetch_value(&mut self) -> Result<u16, &'static str> {
+        match self.fetch()? {
+            value if value < REGISTER_BASE => Ok(value),
+            value if value < REGISTER_BASE + 8 => Ok(self.registers[(value - REGISTER_BASE) as usize]),
+            _ => Err("invalid operand"),
+        }
+    }
+
+    fn jump(&mut self, address: u16) -> Result<(), &'static str> {
+        if address as usize >= MEMORY_WORDS {
+            return Err("jump outside memory");
+        }
+        self.ip = address;
+        Ok(())
+    }
+
+    //  Destinations are registers, or memory addresses when they are not registers
+    fn write(&mut self, destination: u16, value: u16) -> Result<(), &'static str> {
+        match destination {
+            destination if destination < REGISTER_BASE => self.write_memory(destination, value),
+            destination if destination < REGISTER_BASE + 8 => {
+                self.registers[(destination - REGISTER_BASE) as usize] = value;
+                Ok(())
+            },
+            _ => Err("invalid destination"),
+        }
+    }
+
+    fn write_memory(&mut self, address: u16, value: u16) -> Result<(), &'static str> {
+        let word = self.memory.get_mut(address as usize).ok_or("write outside memory")?;
+        *word = value;
+        self.last_write = Some(address);
+        Ok(())
+    }
+}
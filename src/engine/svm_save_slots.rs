@@ -0,0 +1,110 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{self, Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use synacorvm_core::svm_engine_state::SVMEngineState;
+use super::svm_snapshot::{save_snapshot, load_snapshot};
+
+const SNAPSHOT_EXTENSION: &str = "snapshot";
+const METADATA_EXTENSION: &str = "meta";
+
+//  What a slot's metadata file records about the save
+pub struct SlotInfo {
+    pub name: String,
+    pub instruction_count: u64,
+    //  Seconds since the Unix epoch
+    pub saved_at: u64,
+    pub last_output_line: String,
+}
+
+//  Named saves in a directory: <name>.snapshot holds the machine and
+//  <name>.meta has one "key: value" line per SlotInfo field
+pub struct SVMSaveSlots {
+    directory: PathBuf,
+}
+
+impl SVMSaveSlots {
+    pub fn new(directory: PathBuf) -> SVMSaveSlots {
+        SVMSaveSlots {
+            directory,
+        }
+    }
+
+    pub fn get_directory(&self) -> &Path {
+        &self.directory
+    }
+
+    pub fn save(&self, name: &str, engine_state: &SVMEngineState, last_output_line: &str) -> io::Result<()> {
+        save_snapshot(engine_state, &self.slot_path(name, SNAPSHOT_EXTENSION)?)?;
+        let saved_at = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(0);
+        let mut writer = BufWriter::new(File::create(self.slot_path(name, METADATA_EXTENSION)?)?);
+        writeln!(writer, "instructions: {}", engine_state.instruction_count)?;
+        writeln!(writer, "saved_at: {}", saved_at)?;
+        writeln!(writer, "last_output: {}", last_output_line.trim_end())?;
+        writer.flush()
+    }
+
+    //  Restores the instruction count from the metadata too, since snapshots do not keep it
+    pub fn load(&self, name: &str) -> io::Result<SVMEngineState> {
+        let mut engine_state = load_snapshot(&self.slot_path(name, SNAPSHOT_EXTENSION)?)?;
+        if let Ok(info) = self.read_info(name) {
+            engine_state.instruction_count = info.instruction_count;
+        }
+        Ok(engine_state)
+    }
+
+    //  Every slot with a snapshot, sorted by name. Missing or damaged metadata reads as zeroes.
+    pub fn list(&self) -> io::Result<Vec<SlotInfo>> {
+        let mut slots = Vec::new();
+        for entry in fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some(SNAPSHOT_EXTENSION) {
+                continue;
+            }
+            if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+                slots.push(self.read_info(name).unwrap_or_else(|_| SlotInfo {
+                    name: name.to_string(),
+                    instruction_count: 0,
+                    saved_at: 0,
+                    last_output_line: String::new(),
+                }));
+            }
+        }
+        slots.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(slots)
+    }
+
+    pub fn read_info(&self, name: &str) -> io::Result<SlotInfo> {
+        let reader = BufReader::new(File::open(self.slot_path(name, METADATA_EXTENSION)?)?);
+        let mut info = SlotInfo {
+            name: name.to_string(),
+            instruction_count: 0,
+            saved_at: 0,
+            last_output_line: String::new(),
+        };
+        for line in reader.lines() {
+            let line = line?;
+            let (key, value) = match line.split_once(": ") {
+                Some(pair) => pair,
+                None => continue,
+            };
+            match key {
+                "instructions" => info.instruction_count = value.parse().unwrap_or(0),
+                "saved_at" => info.saved_at = value.parse().unwrap_or(0),
+                "last_output" => info.last_output_line = value.to_string(),
+                _ => {},
+            }
+        }
+        Ok(info)
+    }
+
+    //  A slot name is a single file name in the directory, checked like the names host calls resolve
+    fn slot_path(&self, name: &str, extension: &str) -> io::Result<PathBuf> {
+        let mut components = Path::new(name).components();
+        let is_file_name = matches!((components.next(), components.next()), (Some(Component::Normal(_)), None));
+        if !is_file_name || name.contains(path::is_separator) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("invalid slot name {:?}", name)));
+        }
+        Ok(self.directory.join(format!("{}.{}", name, extension)))
+    }
+}
@@ -0,0 +1,215 @@
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use super::svm_engine::{SVMEngine, SVMTermination};
+use super::svm_limits::SVMLimits;
+use super::svm_program::SVMProgram;
+use super::svm_snapshot::save_snapshot;
+
+//  Output is sent to the client when a line ends or this much has collected
+const OUTPUT_CHUNK_SIZE: usize = 1024;
+//  A session sending a longer line of input is closed
+const MAX_LINE_LENGTH: usize = 4096;
+
+#[derive(Clone)]
+pub struct ServerOptions {
+    //  Applied to each session separately, the time limit counts only the time spent running
+    pub limits: SVMLimits,
+    //  A session waiting this long for a whole line of input is closed
+    pub idle_timeout: Option<Duration>,
+    pub max_sessions: usize,
+    //  Each session is saved here as session-<n>.snapshot when it ends
+    pub snapshot_directory: Option<PathBuf>,
+}
+
+impl Default for ServerOptions {
+    fn default() -> ServerOptions {
+        ServerOptions {
+            limits: SVMLimits::default(),
+            idle_timeout: Some(Duration::from_secs(600)),
+            max_sessions: 16,
+            snapshot_directory: None,
+        }
+    }
+}
+
+//  Serves the program over TCP. Every client gets its own machine on its own
+//  thread, with `out` written to the socket and `in` reading lines from it.
+pub struct SVMServer {
+    program: SVMProgram,
+    options: Arc<ServerOptions>,
+    active_sessions: Arc<AtomicUsize>,
+    session_count: u64,
+}
+
+impl SVMServer {
+    pub fn new(program: SVMProgram, options: ServerOptions) -> SVMServer {
+        SVMServer {
+            program,
+            options: Arc::new(options),
+            active_sessions: Arc::new(AtomicUsize::new(0)),
+            session_count: 0,
+        }
+    }
+
+    //  Accepts clients until the listener fails
+    pub fn serve(&mut self, listener: TcpListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            let mut stream = match stream {
+                Ok(stream) => stream,
+                Err(error) => {
+                    println!("Failed to accept a connection: {}", error);
+                    continue;
+                },
+            };
+            let address = match stream.peer_addr() {
+                Ok(address) => address,
+                Err(error) => {
+                    println!("Failed to read the address of a connection: {}", error);
+                    continue;
+                },
+            };
+            if self.active_sessions.load(Ordering::SeqCst) >= self.options.max_sessions {
+                println!("Refused {}: {} sessions are running", address, self.options.max_sessions);
+                stream.write_all(b"The server is full, try again later.\n").unwrap_or_default();
+                continue;
+            }
+
+            self.session_count += 1;
+            let session = Session {
+                id: self.session_count,
+                address,
+                program: self.program.clone(),
+                options: self.options.clone(),
+            };
+            let active_sessions = self.active_sessions.clone();
+            active_sessions.fetch_add(1, Ordering::SeqCst);
+            println!("Session {} started for {}", session.id, address);
+            thread::spawn(move || {
+                session.run(stream);
+                active_sessions.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+        Ok(())
+    }
+}
+
+struct Session {
+    id: u64,
+    address: SocketAddr,
+    program: SVMProgram,
+    options: Arc<ServerOptions>,
+}
+
+impl Session {
+    //  The engine is made on the session's thread, its trace and console are not Send
+    fn run(self, stream: TcpStream) {
+        let mut engine = SVMEngine::new(self.program.clone());
+        engine.detach_console();
+        let reason = match self.converse(&mut engine, stream) {
+            Ok(reason) => reason,
+            Err(error) => format!("connection failed: {}", error),
+        };
+        println!("Session {} for {} ended: {}", self.id, self.address, reason);
+
+        if let Some(ref directory) = self.options.snapshot_directory {
+            let path = directory.join(format!("session-{}.snapshot", self.id));
+            match save_snapshot(engine.get_state(), &path) {
+                Ok(_) => println!("Session {} saved to {}", self.id, path.display()),
+                Err(error) => println!("Failed to save session {} to {}: {}", self.id, path.display(), error),
+            }
+        }
+    }
+
+    //  Runs the program against the client and returns why the session ended
+    fn converse(&self, engine: &mut SVMEngine, stream: TcpStream) -> io::Result<String> {
+        stream.set_nodelay(true)?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = stream;
+        let mut running_time = Duration::ZERO;
+
+        loop {
+            let mut limits = self.options.limits.clone();
+            limits.max_duration = limits.max_duration.map(|duration| duration.saturating_sub(running_time));
+            engine.set_limits(limits);
+
+            let started = Instant::now();
+            let termination = engine.run_until(|state| {
+                state.output_buffer.last() == Some(&b'\n') || state.output_buffer.len() >= OUTPUT_CHUNK_SIZE
+            });
+            running_time += started.elapsed();
+            writer.write_all(&engine.take_output())?;
+
+            match termination {
+                SVMTermination::Stopped => {},
+                SVMTermination::AwaitingInput => match read_line(&mut reader, self.options.idle_timeout)? {
+                    InputLine::Line(mut line) => {
+                        line.retain(|byte| *byte != b'\r');
+                        engine.push_input(&line);
+                    },
+                    InputLine::Disconnected => return Ok("client disconnected".to_string()),
+                    InputLine::TooLong => {
+                        writer.write_all(b"\nLine too long, closing the session.\n")?;
+                        return Ok("line too long".to_string());
+                    },
+                    InputLine::TimedOut => {
+                        writer.write_all(b"\nIdle timeout, closing the session.\n")?;
+                        return Ok("idle timeout".to_string());
+                    },
+                },
+                SVMTermination::Halted => return Ok("halted".to_string()),
+                termination => {
+                    writer.write_all(format!("\nSession stopped: {}\n", termination.get_description()).as_bytes())?;
+                    return Ok(termination.get_description().to_string());
+                },
+            }
+        }
+    }
+}
+
+enum InputLine {
+    Line(Vec<u8>),
+    Disconnected,
+    TooLong,
+    TimedOut,
+}
+
+//  Reads a line of at most MAX_LINE_LENGTH bytes. The timeout is for the whole
+//  line, so a client sending a byte at a time cannot keep a session open
+fn read_line(reader: &mut BufReader<TcpStream>, timeout: Option<Duration>) -> io::Result<InputLine> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let mut line = Vec::new();
+    loop {
+        if let Some(deadline) = deadline {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(InputLine::TimedOut);
+            }
+            reader.get_ref().set_read_timeout(Some(remaining))?;
+        }
+        let buffer = match reader.fill_buf() {
+            Ok(buffer) => buffer,
+            Err(ref error) if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted) => continue,
+            Err(error) => return Err(error),
+        };
+        if buffer.is_empty() {
+            return Ok(if line.is_empty() { InputLine::Disconnected } else { InputLine::Line(line) });
+        }
+        let (length, ended) = match buffer.iter().position(|byte| *byte == b'\n') {
+            Some(index) => (index + 1, true),
+            None => (buffer.len(), false),
+        };
+        if line.len() + length > MAX_LINE_LENGTH {
+            return Ok(InputLine::TooLong);
+        }
+        line.extend_from_slice(&buffer[..length]);
+        reader.consume(length);
+        if ended {
+            return Ok(InputLine::Line(line));
+        }
+    }
+}
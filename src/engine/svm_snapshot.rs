@@ -0,0 +1,115 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use synacorvm_core::svm_constants::{MEMORY_SIZE_MAX, NUM_OF_REGISTERS};
+use synacorvm_core::svm_engine_state::{SVMEngineState, CallFrame};
+use super::svm_program::SVMProgram;
+
+//  Snapshot files are little-endian like program images:
+//  magic, version, ip, halted flag, registers, stack length and words, the full memory image,
+//  then the shadow call stack. Only the current version is read.
+pub const SNAPSHOT_MAGIC: &[u8; 4] = b"SVMS";
+const SNAPSHOT_VERSION: u16 = 3;
+
+pub fn is_snapshot(data: &[u8]) -> bool {
+    data.len() >= SNAPSHOT_MAGIC.len() && &data[..SNAPSHOT_MAGIC.len()] == SNAPSHOT_MAGIC
+}
+
+pub fn write_snapshot<W: Write>(engine_state: &SVMEngineState, writer: &mut W) -> io::Result<()> {
+    writer.write_all(SNAPSHOT_MAGIC)?;
+    writer.write_u16::<LittleEndian>(SNAPSHOT_VERSION)?;
+    writer.write_u16::<LittleEndian>(engine_state.instruction_pointer.get_ip())?;
+    writer.write_u16::<LittleEndian>(engine_state.halted as u16)?;
+    for index in 0..NUM_OF_REGISTERS {
+        writer.write_u16::<LittleEndian>(engine_state.registers.get_register_by_index(index))?;
+    }
+    writer.write_u32::<LittleEndian>(engine_state.stack.len() as u32)?;
+    for value in engine_state.stack.iter() {
+        writer.write_u16::<LittleEndian>(*value)?;
+    }
+    for address in 0..MEMORY_SIZE_MAX {
+        writer.write_u16::<LittleEndian>(engine_state.memory.load_memory(address as u16).unwrap_or(0))?;
+    }
+    writer.write_u32::<LittleEndian>(engine_state.call_stack.len() as u32)?;
+    for frame in engine_state.call_stack.iter() {
+        writer.write_u16::<LittleEndian>(frame.call_site)?;
+        writer.write_u16::<LittleEndian>(frame.target)?;
+        writer.write_u16::<LittleEndian>(frame.return_address)?;
+        writer.write_u32::<LittleEndian>(frame.stack_depth as u32)?;
+    }
+    Ok(())
+}
+
+pub fn read_snapshot<R: Read>(reader: &mut R) -> io::Result<SVMEngineState> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != SNAPSHOT_MAGIC {
+        return Err(invalid_data("not a snapshot file"));
+    }
+    let version = reader.read_u16::<LittleEndian>()?;
+    if version != SNAPSHOT_VERSION {
+        return Err(invalid_data("unsupported snapshot version"));
+    }
+
+    let ip = reader.read_u16::<LittleEndian>()?;
+    let halted = reader.read_u16::<LittleEndian>()? != 0;
+    let mut registers = [0u16; NUM_OF_REGISTERS];
+    for register in registers.iter_mut() {
+        *register = reader.read_u16::<LittleEndian>()?;
+    }
+    let stack_size = reader.read_u32::<LittleEndian>()? as usize;
+    let mut stack = Vec::new();
+    for _ in 0..stack_size {
+        stack.push(reader.read_u16::<LittleEndian>()?);
+    }
+    let mut memory = vec![0u16; MEMORY_SIZE_MAX];
+    reader.read_u16_into::<LittleEndian>(&mut memory)?;
+    let frame_count = reader.read_u32::<LittleEndian>()?;
+    let mut call_stack = Vec::new();
+    for _ in 0..frame_count {
+        call_stack.push(CallFrame {
+            call_site: reader.read_u16::<LittleEndian>()?,
+            target: reader.read_u16::<LittleEndian>()?,
+            return_address: reader.read_u16::<LittleEndian>()?,
+            stack_depth: reader.read_u32::<LittleEndian>()? as usize,
+        });
+    }
+
+    let mut engine_state = SVMEngineState::new(&memory);
+    engine_state.instruction_pointer.set_ip(ip).map_err(|_| invalid_data("invalid instruction pointer"))?;
+    engine_state.halted = halted;
+    for (index, value) in registers.iter().enumerate() {
+        engine_state.registers.set_register_by_index(index, *value);
+    }
+    engine_state.stack = stack;
+    engine_state.call_stack = call_stack;
+    Ok(engine_state)
+}
+
+pub fn save_snapshot(engine_state: &SVMEngineState, path: &Path) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_snapshot(engine_state, &mut writer)?;
+    writer.flush()
+}
+
+pub fn load_snapshot(path: &Path) -> io::Result<SVMEngineState> {
+    let mut reader = BufReader::new(File::open(path)?);
+    read_snapshot(&mut reader)
+}
+
+//  Loads either a snapshot or a program image in any of its formats, which is treated as a freshly loaded machine
+pub fn load_state_or_program(path: &Path) -> io::Result<SVMEngineState> {
+    let mut magic = Vec::new();
+    File::open(path)?.take(SNAPSHOT_MAGIC.len() as u64).read_to_end(&mut magic)?;
+    if is_snapshot(&magic) {
+        load_snapshot(path)
+    } else {
+        let program = SVMProgram::load(path).map_err(|error| invalid_data(&error))?;
+        Ok(program.create_state())
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
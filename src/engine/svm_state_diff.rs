@@ -0,0 +1,145 @@
+use std::io::{self, Write};
+use synacorvm_core::svm_constants::{MEMORY_SIZE_MAX, NUM_OF_REGISTERS};
+use synacorvm_core::svm_engine_state::SVMEngineState;
+use super::svm_program::SVMProgram;
+
+//  A contiguous run of changed memory words
+pub struct ChangedRange {
+    pub start: u16,
+    pub old_values: Vec<u16>,
+    pub new_values: Vec<u16>,
+}
+
+impl ChangedRange {
+    pub fn get_end(&self) -> u16 {
+        self.start + self.old_values.len() as u16 - 1
+    }
+}
+
+pub struct ChangedValue {
+    pub index: usize,
+    pub old_value: Option<u16>,
+    pub new_value: Option<u16>,
+}
+
+pub struct SVMStateDiff {
+    pub ip: Option<(u16, u16)>,
+    pub halted: Option<(bool, bool)>,
+    pub registers: Vec<ChangedValue>,
+    pub stack_depth: (usize, usize),
+    pub stack: Vec<ChangedValue>,
+    pub memory: Vec<ChangedRange>,
+}
+
+impl SVMStateDiff {
+    pub fn between(old: &SVMEngineState, new: &SVMEngineState) -> SVMStateDiff {
+        let old_ip = old.instruction_pointer.get_ip();
+        let new_ip = new.instruction_pointer.get_ip();
+
+        let registers = (0..NUM_OF_REGISTERS)
+            .map(|index| (index, old.registers.get_register_by_index(index), new.registers.get_register_by_index(index)))
+            .filter(|(_, old_value, new_value)| old_value != new_value)
+            .map(|(index, old_value, new_value)| ChangedValue {
+                index,
+                old_value: Some(old_value),
+                new_value: Some(new_value),
+            })
+            .collect();
+
+        //  Stack entries are compared from the bottom so that a push shows up as a single new entry
+        let stack = (0..old.stack.len().max(new.stack.len()))
+            .map(|index| ChangedValue {
+                index,
+                old_value: old.stack.get(index).cloned(),
+                new_value: new.stack.get(index).cloned(),
+            })
+            .filter(|change| change.old_value != change.new_value)
+            .collect();
+
+        let mut memory: Vec<ChangedRange> = Vec::new();
+        for address in 0..MEMORY_SIZE_MAX as u16 {
+            let old_value = old.memory.load_memory(address).unwrap_or(0);
+            let new_value = new.memory.load_memory(address).unwrap_or(0);
+            if old_value == new_value {
+                continue;
+            }
+            match memory.last_mut() {
+                Some(range) if range.get_end() + 1 == address => {
+                    range.old_values.push(old_value);
+                    range.new_values.push(new_value);
+                },
+                _ => memory.push(ChangedRange {
+                    start: address,
+                    old_values: vec![old_value],
+                    new_values: vec![new_value],
+                }),
+            }
+        }
+
+        SVMStateDiff {
+            ip: if old_ip != new_ip { Some((old_ip, new_ip)) } else { None },
+            halted: if old.halted != new.halted { Some((old.halted, new.halted)) } else { None },
+            registers,
+            stack_depth: (old.stack.len(), new.stack.len()),
+            stack,
+            memory,
+        }
+    }
+
+    //  Compares a state against the machine as it was when the program was first loaded
+    pub fn from_program(program: &SVMProgram, state: &SVMEngineState) -> SVMStateDiff {
+        SVMStateDiff::between(&program.create_state(), state)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ip.is_none() && self.halted.is_none() && self.registers.is_empty()
+            && self.stack.is_empty() && self.memory.is_empty()
+    }
+
+    pub fn print(&self) {
+        let stdout = io::stdout();
+        self.write(&mut stdout.lock()).unwrap_or_default();
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        if self.is_empty() {
+            return writeln!(writer, "No differences.");
+        }
+        if let Some((old_ip, new_ip)) = self.ip {
+            writeln!(writer, "IP: {} -> {}", old_ip, new_ip)?;
+        }
+        if let Some((old_halted, new_halted)) = self.halted {
+            writeln!(writer, "Halted: {} -> {}", old_halted, new_halted)?;
+        }
+        for change in self.registers.iter() {
+            writeln!(writer, "r{}: {} -> {}", change.index, format_value(change.old_value), format_value(change.new_value))?;
+        }
+        if !self.stack.is_empty() {
+            writeln!(writer, "Stack (depth {} -> {}):", self.stack_depth.0, self.stack_depth.1)?;
+            for change in self.stack.iter() {
+                writeln!(writer, "  [{}]: {} -> {}", change.index, format_value(change.old_value), format_value(change.new_value))?;
+            }
+        }
+        if !self.memory.is_empty() {
+            let words: usize = self.memory.iter().map(|range| range.old_values.len()).sum();
+            writeln!(writer, "Memory: {} changed range(s), {} word(s)", self.memory.len(), words)?;
+            for range in self.memory.iter() {
+                writeln!(writer, "  {}-{}: {} -> {}", range.start, range.get_end(),
+                    format_values(&range.old_values), format_values(&range.new_values))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn format_value(value: Option<u16>) -> String {
+    match value {
+        Some(x) => x.to_string(),
+        None => "-".to_string(),
+    }
+}
+
+fn format_values(values: &[u16]) -> String {
+    let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+    values.join(" ")
+}
@@ -0,0 +1,214 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum DataType {
+    Code,
+    String,
+    Table,
+}
+
+impl DataType {
+    pub fn parse(text: &str) -> Option<DataType> {
+        match text {
+            "code" => Some(DataType::Code),
+            "string" => Some(DataType::String),
+            "table" => Some(DataType::Table),
+            _ => None,
+        }
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        match *self {
+            DataType::Code => "code",
+            DataType::String => "string",
+            DataType::Table => "table",
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Annotation {
+    pub label: Option<String>,
+    pub function: Option<String>,
+    pub comment: Option<String>,
+    pub data_type: Option<(DataType, u16)>,
+}
+
+impl Annotation {
+    pub fn get_name(&self) -> Option<&str> {
+        self.label.as_deref().or(self.function.as_deref())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.label.is_none() && self.function.is_none() && self.comment.is_none() && self.data_type.is_none()
+    }
+}
+
+//  Sidecar annotations for a program image. The file has one annotation per line:
+//      <address> label <name>
+//      <address> function <name>
+//      <address> comment <text>
+//      <address> code|string|table [length]
+//  Addresses are decimal or 0x-prefixed hex and lines starting with # are ignored.
+#[derive(Clone, Default)]
+pub struct SVMSymbols {
+    annotations: BTreeMap<u16, Annotation>,
+    path: Option<PathBuf>,
+}
+
+impl SVMSymbols {
+    pub fn new() -> SVMSymbols {
+        SVMSymbols::default()
+    }
+
+    pub fn load(path: &Path) -> io::Result<SVMSymbols> {
+        let mut symbols = SVMSymbols::new();
+        let reader = BufReader::new(File::open(path)?);
+        for (line_number, line) in reader.lines().enumerate() {
+            let line = line?;
+            symbols.parse_line(line.trim()).map_err(|error| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line_number + 1, error))
+            })?;
+        }
+        symbols.path = Some(path.to_path_buf());
+        Ok(symbols)
+    }
+
+    //  Saves to the given path, or back to the file the symbols were loaded from
+    pub fn save(&mut self, path: Option<&Path>) -> io::Result<()> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => self.path.clone().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, "no symbol file name given")
+            })?,
+        };
+        let mut writer = BufWriter::new(File::create(&path)?);
+        for (address, annotation) in self.annotations.iter() {
+            if let Some(ref label) = annotation.label {
+                writeln!(writer, "{} label {}", address, label)?;
+            }
+            if let Some(ref function) = annotation.function {
+                writeln!(writer, "{} function {}", address, function)?;
+            }
+            if let Some(ref comment) = annotation.comment {
+                writeln!(writer, "{} comment {}", address, comment)?;
+            }
+            if let Some((data_type, length)) = annotation.data_type {
+                writeln!(writer, "{} {} {}", address, data_type.get_name(), length)?;
+            }
+        }
+        writer.flush()?;
+        self.path = Some(path);
+        Ok(())
+    }
+
+    pub fn parse_line(&mut self, line: &str) -> Result<(), String> {
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+        let mut parts = line.splitn(3, char::is_whitespace);
+        let address = parse_number(parts.next().unwrap_or(""))?;
+        let kind = parts.next().ok_or("missing annotation kind")?;
+        let value = parts.next().unwrap_or("").trim();
+        match kind {
+            "label" | "function" if value.is_empty() || value.contains(char::is_whitespace) => {
+                Err(format!("invalid name '{}'", value))
+            },
+            "label" => {
+                self.set_label(address, value);
+                Ok(())
+            },
+            "function" => {
+                self.set_function(address, value);
+                Ok(())
+            },
+            "comment" => {
+                self.set_comment(address, value);
+                Ok(())
+            },
+            _ => match DataType::parse(kind) {
+                Some(data_type) => {
+                    let length = if value.is_empty() { 1 } else { parse_number(value)? };
+                    self.set_data_type(address, data_type, length);
+                    Ok(())
+                },
+                None => Err(format!("unknown annotation kind '{}'", kind)),
+            },
+        }
+    }
+
+    pub fn set_label(&mut self, address: u16, label: &str) {
+        self.annotations.entry(address).or_default().label = Some(label.to_string());
+    }
+
+    pub fn set_function(&mut self, address: u16, function: &str) {
+        self.annotations.entry(address).or_default().function = Some(function.to_string());
+    }
+
+    pub fn set_comment(&mut self, address: u16, comment: &str) {
+        self.annotations.entry(address).or_default().comment = Some(comment.to_string());
+    }
+
+    pub fn set_data_type(&mut self, address: u16, data_type: DataType, length: u16) {
+        self.annotations.entry(address).or_default().data_type = Some((data_type, length));
+    }
+
+    pub fn remove(&mut self, address: u16) {
+        self.annotations.remove(&address);
+    }
+
+    pub fn get(&self, address: u16) -> Option<&Annotation> {
+        self.annotations.get(&address).filter(|annotation| !annotation.is_empty())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&u16, &Annotation)> {
+        self.annotations.iter()
+    }
+
+    pub fn find_address(&self, name: &str) -> Option<u16> {
+        self.annotations.iter()
+            .find(|(_, annotation)| annotation.label.as_deref() == Some(name) || annotation.function.as_deref() == Some(name))
+            .map(|(address, _)| *address)
+    }
+
+    //  The closest label or function at or before the address
+    pub fn find_symbol(&self, address: u16) -> Option<(&str, u16)> {
+        self.annotations.range(..=address).rev()
+            .find_map(|(base, annotation)| annotation.get_name().map(|name| (name, address - base)))
+    }
+
+    pub fn format_address(&self, address: u16) -> String {
+        match self.find_symbol(address) {
+            Some((name, 0)) => name.to_string(),
+            Some((name, offset)) => format!("{}+{}", name, offset),
+            None => address.to_string(),
+        }
+    }
+
+    //  Accepts a number, a symbol name or name+offset
+    pub fn parse_address(&self, text: &str) -> Result<u16, String> {
+        if let Ok(address) = parse_number(text) {
+            return Ok(address);
+        }
+        let (name, offset) = match text.find('+') {
+            Some(index) => (&text[..index], parse_number(&text[index + 1..])?),
+            None => (text, 0),
+        };
+        match self.find_address(name) {
+            Some(address) => Ok(address.wrapping_add(offset)),
+            None => Err(format!("Unknown symbol {}", name)),
+        }
+    }
+}
+
+pub fn parse_number(text: &str) -> Result<u16, String> {
+    let parsed = if text.starts_with("0x") || text.starts_with("0X") {
+        u16::from_str_radix(&text[2..], 16)
+    } else {
+        text.parse::<u16>()
+    };
+    parsed.map_err(|_| format!("Invalid number {}", text))
+}
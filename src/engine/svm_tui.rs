@@ -0,0 +1,348 @@
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, Write};
+use std::process::{Command, Stdio};
+use synacorvm_core::svm_constants::NUM_OF_REGISTERS;
+use super::svm_backtrace::get_return_address_slots;
+use super::svm_disassembler::disassemble_instruction;
+use super::svm_engine::{SVMEngine, SVMTermination};
+use super::svm_program::SVMProgram;
+use super::svm_symbols::parse_number;
+
+const DEFAULT_ROWS: usize = 30;
+const DEFAULT_COLUMNS: usize = 100;
+const MEMORY_ROWS: usize = 4;
+const MEMORY_WORDS_PER_ROW: u16 = 8;
+const CONSOLE_ROWS: usize = 6;
+//  Header, two pane titles, status and prompt
+const FIXED_ROWS: usize = 5;
+//  How far back to look for an instruction boundary that leads to the IP
+const DISASSEMBLY_LOOKBEHIND: u16 = 24;
+
+const RESET: &str = "\x1b[0m";
+const REVERSE: &str = "\x1b[7m";
+const RED: &str = "\x1b[31m";
+const YELLOW: &str = "\x1b[33m";
+const BOLD: &str = "\x1b[1m";
+
+const HELP_LINE: &str = "s [n] step, c continue, b <addr> toggle breakpoint, m <addr> memory view, > <text> send input, r resize, q quit";
+
+//  A full screen debugger drawn with ANSI escapes. Commands are typed on the
+//  bottom line and the screen is redrawn after each one. The program's
+//  console is captured into its own pane; input is sent with `>`.
+pub struct SVMTui {
+    engine: SVMEngine,
+    breakpoints: BTreeSet<u16>,
+    memory_address: u16,
+    console: Vec<u8>,
+    status: String,
+    //  Asked for on the first draw and again by the resize command
+    terminal_size: Option<(usize, usize)>,
+}
+
+impl SVMTui {
+    pub fn new(program: SVMProgram) -> SVMTui {
+        let mut engine = SVMEngine::new(program);
+        engine.detach_console();
+        SVMTui {
+            engine,
+            breakpoints: BTreeSet::new(),
+            memory_address: 0,
+            console: Vec::new(),
+            status: HELP_LINE.to_string(),
+            terminal_size: None,
+        }
+    }
+
+    pub fn get_engine_mut(&mut self) -> &mut SVMEngine {
+        &mut self.engine
+    }
+
+    pub fn run(&mut self) {
+        let stdin = io::stdin();
+        //  Switch to the alternate screen so the terminal is restored afterwards
+        print!("\x1b[?1049h");
+        loop {
+            self.draw();
+            let mut line = String::new();
+            match stdin.lock().read_line(&mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {},
+            }
+            if !self.execute_command(line.trim_end_matches(['\r', '\n'])) {
+                break;
+            }
+        }
+        print!("\x1b[?1049l");
+        io::stdout().flush().unwrap_or_default();
+    }
+
+    //  Returns false when the user asked to leave
+    pub fn execute_command(&mut self, line: &str) -> bool {
+        if let Some(input) = line.strip_prefix('>') {
+            let input = format!("{}\n", input.strip_prefix(' ').unwrap_or(input));
+            self.console.extend_from_slice(input.as_bytes());
+            self.engine.push_input(input.as_bytes());
+            self.continue_execution();
+            return true;
+        }
+
+        let arguments: Vec<&str> = line.split_whitespace().collect();
+        let result = match arguments.first().cloned() {
+            //  An empty line single steps
+            None => {
+                self.step(1);
+                Ok(())
+            },
+            Some("s") | Some("step") => match arguments.get(1) {
+                Some(count) => parse_number(count).map(|count| self.step(count)),
+                None => {
+                    self.step(1);
+                    Ok(())
+                },
+            },
+            Some("c") | Some("continue") => {
+                self.continue_execution();
+                Ok(())
+            },
+            Some("b") | Some("break") => self.required_address(arguments.get(1)).map(|address| {
+                if !self.breakpoints.remove(&address) {
+                    self.breakpoints.insert(address);
+                }
+            }),
+            Some("m") | Some("mem") => self.required_address(arguments.get(1)).map(|address| {
+                self.memory_address = address;
+            }),
+            Some("r") | Some("resize") => {
+                self.terminal_size = None;
+                Ok(())
+            },
+            Some("h") | Some("help") => {
+                self.status = HELP_LINE.to_string();
+                Ok(())
+            },
+            Some("q") | Some("quit") => return false,
+            Some(command) => Err(format!("Unknown command {}", command)),
+        };
+        if let Err(error) = result {
+            self.status = error;
+        }
+        true
+    }
+
+    fn step(&mut self, count: u16) {
+        self.status = String::new();
+        for _ in 0..count {
+            if self.engine.get_state().halted {
+                self.status = "The program has halted".to_string();
+                break;
+            }
+            let result = self.engine.step();
+            self.collect_output();
+            if let Err(error) = result {
+                self.status = error.get_description().to_string();
+                break;
+            }
+        }
+    }
+
+    fn continue_execution(&mut self) {
+        let breakpoints = &self.breakpoints;
+        let termination = self.engine.run_until(|engine_state| breakpoints.contains(&engine_state.instruction_pointer.get_ip()));
+        self.collect_output();
+        self.status = match termination {
+            SVMTermination::Stopped => "Breakpoint".to_string(),
+            SVMTermination::AwaitingInput => "Waiting for input, type > followed by a line".to_string(),
+            other => format!("Stopped: {}", other.get_description()),
+        };
+    }
+
+    fn collect_output(&mut self) {
+        let output = self.engine.take_output();
+        self.console.extend_from_slice(&output);
+    }
+
+    fn required_address(&self, argument: Option<&&str>) -> Result<u16, String> {
+        match argument {
+            Some(text) => self.engine.get_symbols().parse_address(text),
+            None => Err("Missing address".to_string()),
+        }
+    }
+
+    //  The status line, which shows errors and why execution stopped
+    pub fn get_status(&self) -> &str {
+        &self.status
+    }
+
+    fn draw(&mut self) {
+        let (rows, columns) = *self.terminal_size.get_or_insert_with(get_terminal_size);
+        print!("{}", self.render(rows, columns));
+        io::stdout().flush().unwrap_or_default();
+    }
+
+    //  The whole screen for a terminal of the given size, ending at the prompt
+    pub fn render(&self, rows: usize, columns: usize) -> String {
+        let pane_rows = rows.saturating_sub(FIXED_ROWS + MEMORY_ROWS + CONSOLE_ROWS).max(NUM_OF_REGISTERS + 2);
+        let right_width = 32.min(columns / 3);
+        let left_width = columns.saturating_sub(right_width + 1);
+
+        let mut screen = String::from("\x1b[2J\x1b[H");
+        let engine_state = self.engine.get_state();
+        let header = format!(" synacorvm   ip {}   instructions {}   stack {}   call depth {}",
+            engine_state.instruction_pointer.get_ip(), engine_state.instruction_count,
+            engine_state.stack.len(), engine_state.call_stack.len());
+        screen.push_str(&format!("{}{}{}\r\n", REVERSE, fit(&header, columns), RESET));
+
+        let left = self.disassembly_pane(pane_rows, left_width);
+        let right = self.register_and_stack_pane(pane_rows, right_width);
+        for (left_line, right_line) in left.iter().zip(right.iter()) {
+            screen.push_str(&format!("{}|{}\r\n", left_line, right_line));
+        }
+
+        screen.push_str(&format!("{}{}{}\r\n", BOLD, fit(&format!("-- Memory at {} ", self.memory_address), columns), RESET));
+        for line in self.memory_pane() {
+            screen.push_str(&fit(&line, columns));
+            screen.push_str("\r\n");
+        }
+
+        screen.push_str(&format!("{}{}{}\r\n", BOLD, fit("-- Console ", columns), RESET));
+        for line in self.console_pane() {
+            screen.push_str(&fit(&line, columns));
+            screen.push_str("\r\n");
+        }
+
+        screen.push_str(&fit(&self.status, columns));
+        screen.push_str("\r\n> ");
+        screen
+    }
+
+    //  Instructions around the IP, with the IP highlighted and breakpoints marked
+    fn disassembly_pane(&self, rows: usize, width: usize) -> Vec<String> {
+        let engine_state = self.engine.get_state();
+        let symbols = self.engine.get_symbols();
+        let extensions = self.engine.get_extensions();
+        let ip = engine_state.instruction_pointer.get_ip();
+
+        //  Instructions can't be decoded backwards, so find an earlier start that lands on the IP
+        let mut addresses = vec![ip];
+        for start in ip.saturating_sub(DISASSEMBLY_LOOKBEHIND)..ip {
+            let mut candidate = Vec::new();
+            let mut address = start;
+            while address < ip {
+                candidate.push(address);
+                address = address.saturating_add(disassemble_instruction(&engine_state.memory, address, symbols, extensions).length);
+            }
+            if address == ip {
+                let context = rows / 3;
+                addresses = candidate.split_off(candidate.len().saturating_sub(context));
+                addresses.push(ip);
+                break;
+            }
+        }
+        while addresses.len() < rows {
+            let last = addresses[addresses.len() - 1];
+            let next = last.saturating_add(disassemble_instruction(&engine_state.memory, last, symbols, extensions).length);
+            if next == last {
+                break;
+            }
+            addresses.push(next);
+        }
+
+        let mut lines: Vec<String> = addresses.iter().take(rows).map(|address| {
+            let instruction = disassemble_instruction(&engine_state.memory, *address, symbols, extensions);
+            let marker = if self.breakpoints.contains(address) { "*" } else { " " };
+            let line = fit(&format!(" {}{:>16}: {}", marker, symbols.format_address(*address), instruction.text), width);
+            if *address == ip {
+                format!("{}{}{}", REVERSE, line, RESET)
+            } else if self.breakpoints.contains(address) {
+                format!("{}{}{}", RED, line, RESET)
+            } else {
+                line
+            }
+        }).collect();
+        lines.resize(rows, fit("", width));
+        lines
+    }
+
+    //  The registers, then the stack top first with return addresses highlighted
+    fn register_and_stack_pane(&self, rows: usize, width: usize) -> Vec<String> {
+        let engine_state = self.engine.get_state();
+        let mut lines = Vec::new();
+        for index in 0..NUM_OF_REGISTERS {
+            let value = engine_state.registers.get_register_by_index(index);
+            lines.push(fit(&format!(" r{}: {:5}  0x{:04x}", index, value, value), width));
+        }
+        lines.push(fit(&format!(" stack ({})", engine_state.stack.len()), width));
+
+        let return_slots = get_return_address_slots(engine_state);
+        for (index, value) in engine_state.stack.iter().enumerate().rev() {
+            if lines.len() == rows {
+                break;
+            }
+            if return_slots.contains(&index) {
+                let line = fit(&format!(" {:5}: {} ret", index, self.engine.get_symbols().format_address(*value)), width);
+                lines.push(format!("{}{}{}", YELLOW, line, RESET));
+            } else {
+                lines.push(fit(&format!(" {:5}: {}", index, value), width));
+            }
+        }
+        lines.resize(rows, fit("", width));
+        lines
+    }
+
+    fn memory_pane(&self) -> Vec<String> {
+        let memory = &self.engine.get_state().memory;
+        let mut lines = Vec::new();
+        for row in 0..MEMORY_ROWS as u16 {
+            let start = self.memory_address.saturating_add(row * MEMORY_WORDS_PER_ROW);
+            let mut hex = String::new();
+            let mut text = String::new();
+            for offset in 0..MEMORY_WORDS_PER_ROW {
+                match memory.load_memory(start.saturating_add(offset)) {
+                    Ok(value) => {
+                        hex.push_str(&format!(" {:04x}", value));
+                        text.push(if (0x20..0x7F).contains(&value) { value as u8 as char } else { '.' });
+                    },
+                    Err(_) => hex.push_str("     "),
+                }
+            }
+            lines.push(format!(" {:5}:{}  {}", start, hex, text));
+        }
+        lines
+    }
+
+    //  The last lines of program output, including an unfinished line such as a prompt
+    fn console_pane(&self) -> Vec<String> {
+        let text = String::from_utf8_lossy(&self.console);
+        let all: Vec<&str> = text.split('\n').collect();
+        let mut lines: Vec<String> = all[all.len().saturating_sub(CONSOLE_ROWS)..].iter()
+            .map(|line| line.to_string())
+            .collect();
+        while lines.len() < CONSOLE_ROWS {
+            lines.insert(0, String::new());
+        }
+        lines
+    }
+}
+
+//  Asks stty for the size of the terminal on stdin, falling back to a fixed size
+fn get_terminal_size() -> (usize, usize) {
+    let output = Command::new("stty").arg("size").stdin(Stdio::inherit()).output();
+    if let Ok(output) = output {
+        let text = String::from_utf8_lossy(&output.stdout);
+        let values: Vec<usize> = text.split_whitespace().filter_map(|value| value.parse().ok()).collect();
+        if let [rows, columns] = values[..] {
+            if rows > 0 && columns > 0 {
+                return (rows, columns.max(60));
+            }
+        }
+    }
+    (DEFAULT_ROWS, DEFAULT_COLUMNS)
+}
+
+//  Truncates or pads to exactly the width, counting characters
+fn fit(text: &str, width: usize) -> String {
+    let mut fitted: String = text.chars().filter(|c| !c.is_control()).take(width).collect();
+    let length = fitted.chars().count();
+    fitted.extend(std::iter::repeat_n(' ', width - length));
+    fitted
+}
@@ -1,15 +1,570 @@
-mod engine;
-
-use engine::svm_program::SVMProgram;
-use engine::svm_engine::SVMEngine;
-use std::fs::File;
+use synacorvm::engine::svm_program::SVMProgram;
+use synacorvm::engine::svm_engine::{SVMEngine, SVMTermination};
+use synacorvm::engine::svm_limits::SVMLimits;
+use synacorvm::engine::svm_checkpoints::DEFAULT_UNDO_DEPTH;
+use synacorvm::engine::svm_debugger::SVMDebugger;
+use synacorvm::engine::svm_tui::SVMTui;
+use synacorvm::engine::svm_gdb_stub::SVMGdbStub;
+use synacorvm::engine::svm_dap_server::SVMDapServer;
+use synacorvm::engine::svm_snapshot::{save_snapshot, load_state_or_program};
+use synacorvm::engine::svm_state_diff::SVMStateDiff;
+use synacorvm::engine::svm_symbols::SVMSymbols;
+use synacorvm::engine::svm_disassembler::disassemble_range;
+use synacorvm::engine::svm_explorer::{ExplorerOptions, SVMExplorer};
+use synacorvm::engine::svm_server::{ServerOptions, SVMServer};
+use synacorvm::engine::svm_coverage::SVMCoverage;
+use synacorvm::engine::svm_patch::SVMPatchFile;
+use synacorvm::engine::svm_devices::{create_device, SVMDeviceMap};
+use synacorvm::engine::svm_symbols::parse_number;
+use synacorvm_core::opcode::ExtensionSet;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::net::TcpListener;
 use std::env;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+struct Options {
+    program_path: String,
+    print_program: bool,
+    patches: Vec<PathBuf>,
+    devices: Vec<(String, u16)>,
+    host_call_sandbox: Option<PathBuf>,
+    strict: bool,
+    track_self_modification: bool,
+    dump_directory: Option<PathBuf>,
+    debug: bool,
+    tui: bool,
+    gdb_port: Option<u16>,
+    save_snapshot: Option<PathBuf>,
+    fault_snapshot: Option<PathBuf>,
+    meta_prefix: Option<String>,
+    save_directory: Option<PathBuf>,
+    undo_depth: usize,
+    symbols: Option<PathBuf>,
+    trace: Option<PathBuf>,
+    profile: bool,
+    folded_stacks: Option<PathBuf>,
+    coverage: Option<PathBuf>,
+    coverage_summary: Option<PathBuf>,
+    limits: SVMLimits,
+}
+
+fn print_usage() {
+    println!("Usage: synacorvm <program> [options]");
+    println!("       Programs are raw little-endian words, container images, hex text or Intel HEX");
+    println!("       synacorvm diff <old> <new>   Compare two snapshots or program images");
+    println!("       synacorvm disasm <image> [--symbols <file>] [start] [end]");
+    println!("       synacorvm dap                Serve the Debug Adapter Protocol on stdin and stdout");
+    println!("       synacorvm explore <program> [--max-states <n>] [--max-depth <n>] [--dot <file>]");
+    println!("       synacorvm serve <program> [--port <n>] [--idle-timeout <seconds>] [--max-sessions <n>]");
+    println!("                       [--snapshot-dir <dir>] [--max-* limits per session]");
+    println!("  --symbols <file>         Load labels, comments and data types for the program");
+    println!("  --trace <file>           Write every executed instruction to a file");
+    println!("  --profile                Print hot spots, opcode and function statistics when the program stops");
+    println!("  --profile-folded <file>  Write folded call stacks for flame graph tools (implies --profile)");
+    println!("  --coverage <file>        Write a disassembly annotated with executed code and branches taken");
+    println!("  --coverage-summary <file> Write code coverage statistics as JSON");
+    println!("  --debug                  Start the program in the line debugger");
+    println!("  --tui                    Start the program in the full screen debugger");
+    println!("  --gdb <port>             Wait for a GDB remote protocol client on the local port");
+    println!("  --save-snapshot <file>   Save a snapshot of the machine when the program stops");
+    println!("  --fault-snapshot <file>  Save a snapshot of the machine if the program faults");
+    println!("  --meta-prefix <prefix>   Treat input lines starting with the prefix as host commands, e.g. !help");
+    println!("  --save-dir <dir>         Keep the save slots of host commands in a directory (default .)");
+    println!("  --undo-depth <n>         How many commands host undo can take back (default {})", DEFAULT_UNDO_DEPTH);
+    println!("  --patch <file>           Apply a patch file to the program before running, can be repeated");
+    println!("  --device <name>@<addr>   Map a device over memory for rmem and wmem, can be repeated:");
+    println!("                           cycles (3 words), random, clock (2 words) or console");
+    println!("  --host-calls <dir>       Enable hcall (22) with file services sandboxed to the directory");
+    println!("  --strict                 Run the spec alone, without extension opcodes or devices");
+    println!("  --print-program          Print the loaded bytecode before running");
+    println!("  --max-instructions <n>   Stop after n instructions");
+    println!("  --max-time <seconds>     Stop after the given wall-clock time");
+    println!("  --max-stack <n>          Stop before the stack grows beyond n values");
+    println!("  --max-output <n>         Stop before writing more than n bytes");
+    println!("  --max-input <n>          Stop before reading more than n bytes");
+    println!("  --track-smc              Report self-modifying code when the program halts");
+    println!("  --smc-dump-dir <dir>     Dump the memory image the first time modified code runs (implies --track-smc)");
+}
+
+fn parse_arguments(args: &[String]) -> Result<Options, String> {
+    let mut program_path = None;
+    let mut options = Options {
+        program_path: String::new(),
+        print_program: false,
+        patches: Vec::new(),
+        devices: Vec::new(),
+        host_call_sandbox: None,
+        strict: false,
+        track_self_modification: false,
+        dump_directory: None,
+        debug: false,
+        tui: false,
+        gdb_port: None,
+        save_snapshot: None,
+        fault_snapshot: None,
+        meta_prefix: None,
+        save_directory: None,
+        undo_depth: DEFAULT_UNDO_DEPTH,
+        symbols: None,
+        trace: None,
+        profile: false,
+        folded_stacks: None,
+        coverage: None,
+        coverage_summary: None,
+        limits: SVMLimits::default(),
+    };
+
+    let mut arguments = args.iter().skip(1);
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--print-program" => options.print_program = true,
+            "--patch" => {
+                let path = arguments.next().ok_or("--patch requires a file name")?;
+                options.patches.push(PathBuf::from(path));
+            },
+            "--device" => {
+                let device = arguments.next().ok_or("--device requires a name and an address")?;
+                options.devices.push(device_argument(device)?);
+            },
+            "--host-calls" => {
+                let path = arguments.next().ok_or("--host-calls requires a sandbox directory")?;
+                options.host_call_sandbox = Some(PathBuf::from(path));
+            },
+            "--strict" => options.strict = true,
+            "--debug" => options.debug = true,
+            "--tui" => options.tui = true,
+            "--gdb" => {
+                let port = arguments.next().and_then(|port| port.parse::<u16>().ok());
+                options.gdb_port = Some(port.ok_or("--gdb requires a port number")?);
+            },
+            "--save-snapshot" => {
+                let path = arguments.next().ok_or("--save-snapshot requires a file name")?;
+                options.save_snapshot = Some(PathBuf::from(path));
+            },
+            "--fault-snapshot" => {
+                let path = arguments.next().ok_or("--fault-snapshot requires a file name")?;
+                options.fault_snapshot = Some(PathBuf::from(path));
+            },
+            "--meta-prefix" => {
+                let prefix = arguments.next().filter(|prefix| !prefix.is_empty());
+                options.meta_prefix = Some(prefix.ok_or("--meta-prefix requires a prefix")?.clone());
+            },
+            "--save-dir" => {
+                let path = arguments.next().ok_or("--save-dir requires a directory")?;
+                options.save_directory = Some(PathBuf::from(path));
+            },
+            "--undo-depth" => options.undo_depth = limit_argument(argument, arguments.next())? as usize,
+            "--symbols" => {
+                let path = arguments.next().ok_or("--symbols requires a file name")?;
+                options.symbols = Some(PathBuf::from(path));
+            },
+            "--trace" => {
+                let path = arguments.next().ok_or("--trace requires a file name")?;
+                options.trace = Some(PathBuf::from(path));
+            },
+            "--profile" => options.profile = true,
+            "--profile-folded" => {
+                let path = arguments.next().ok_or("--profile-folded requires a file name")?;
+                options.folded_stacks = Some(PathBuf::from(path));
+                options.profile = true;
+            },
+            "--coverage" => {
+                let path = arguments.next().ok_or("--coverage requires a file name")?;
+                options.coverage = Some(PathBuf::from(path));
+            },
+            "--coverage-summary" => {
+                let path = arguments.next().ok_or("--coverage-summary requires a file name")?;
+                options.coverage_summary = Some(PathBuf::from(path));
+            },
+            "--max-instructions" | "--max-time" | "--max-stack" | "--max-output" | "--max-input" => {
+                parse_limit(argument, arguments.next(), &mut options.limits)?;
+            },
+            "--track-smc" => options.track_self_modification = true,
+            "--smc-dump-dir" => {
+                let directory = arguments.next().ok_or("--smc-dump-dir requires a directory")?;
+                options.dump_directory = Some(PathBuf::from(directory));
+                options.track_self_modification = true;
+            },
+            _ if argument.starts_with("--") => return Err(format!("Unknown option {}", argument)),
+            _ if program_path.is_none() => program_path = Some(argument.clone()),
+            _ => return Err(format!("Unexpected argument {}", argument)),
+        }
+    }
+
+    options.program_path = program_path.ok_or("No program given")?;
+    if options.strict && (options.host_call_sandbox.is_some() || !options.devices.is_empty()) {
+        return Err("--strict cannot be combined with --host-calls or --device".to_string());
+    }
+    if cfg!(not(feature = "host-calls")) && options.host_call_sandbox.is_some() {
+        return Err("--host-calls is not available in this build".to_string());
+    }
+    //  Mapping them here reports unknown names and overlaps as usage errors
+    let mut devices = SVMDeviceMap::new();
+    for (name, address) in options.devices.iter() {
+        let device = create_device(name).ok_or_else(|| format!("Unknown device {}", name))?;
+        devices.map(*address, device)?;
+    }
+    Ok(options)
+}
+
+fn parse_limit(option: &str, value: Option<&String>, limits: &mut SVMLimits) -> Result<(), String> {
+    match option {
+        "--max-instructions" => limits.max_instructions = Some(limit_argument(option, value)?),
+        "--max-time" => limits.max_duration = Some(seconds_argument(option, value)?),
+        "--max-stack" => limits.max_stack_depth = Some(limit_argument(option, value)? as usize),
+        "--max-output" => limits.max_output_bytes = Some(limit_argument(option, value)?),
+        _ => limits.max_input_reads = Some(limit_argument(option, value)?),
+    }
+    Ok(())
+}
+
+fn seconds_argument(option: &str, value: Option<&String>) -> Result<Duration, String> {
+    value.and_then(|value| value.parse::<f64>().ok())
+        .filter(|seconds| *seconds >= 0.0)
+        .map(Duration::from_secs_f64)
+        .ok_or_else(|| format!("{} requires a number of seconds", option))
+}
+
+fn limit_argument(option: &str, value: Option<&String>) -> Result<u64, String> {
+    value.and_then(|value| value.parse::<u64>().ok())
+        .ok_or_else(|| format!("{} requires a number", option))
+}
+
+fn device_argument(value: &str) -> Result<(String, u16), String> {
+    let (name, address) = value.split_once('@').ok_or("--device requires <name>@<address>")?;
+    Ok((name.to_string(), parse_number(address)?))
+}
+
+fn diff_files(old_path: &str, new_path: &str) -> Result<(), String> {
+    let old = load_state_or_program(Path::new(old_path)).map_err(|error| format!("{}: {}", old_path, error))?;
+    let new = load_state_or_program(Path::new(new_path)).map_err(|error| format!("{}: {}", new_path, error))?;
+    SVMStateDiff::between(&old, &new).print();
+    Ok(())
+}
+
+fn disassemble_file(args: &[String]) -> Result<(), String> {
+    let mut image_path = None;
+    let mut symbols = SVMSymbols::new();
+    let mut bounds = Vec::new();
+    let mut arguments = args.iter();
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--symbols" => {
+                let path = arguments.next().ok_or("--symbols requires a file name")?;
+                symbols = load_symbols(Path::new(path))?;
+            },
+            _ if image_path.is_none() => image_path = Some(argument.clone()),
+            _ => bounds.push(symbols.parse_address(argument)?),
+        }
+    }
+
+    let image_path = image_path.ok_or("No image given")?;
+    let state = load_state_or_program(Path::new(&image_path)).map_err(|error| format!("{}: {}", image_path, error))?;
+    let start = bounds.first().cloned().unwrap_or(0);
+    let end = bounds.get(1).cloned().unwrap_or(u16::MAX);
+    for line in disassemble_range(&state.memory, start, end, &symbols, ExtensionSet::STRICT) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+fn explore_program(args: &[String]) -> Result<(), String> {
+    let mut program_path = None;
+    let mut options = ExplorerOptions::default();
+    let mut dot_path = None;
+    let mut arguments = args.iter();
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--max-states" => options.max_states = limit_argument(argument, arguments.next())? as usize,
+            "--max-depth" => options.max_depth = limit_argument(argument, arguments.next())? as usize,
+            "--dot" => dot_path = Some(arguments.next().ok_or("--dot requires a file name")?.clone()),
+            _ if program_path.is_none() => program_path = Some(argument.clone()),
+            _ => return Err(format!("Unknown argument: {}", argument)),
+        }
+    }
+
+    let program_path = program_path.ok_or("No program given")?;
+    let program = SVMProgram::load(Path::new(&program_path))?;
+    let mut explorer = SVMExplorer::new(options);
+    explorer.explore(SVMEngine::new(program));
+    explorer.print_report();
+    if let Some(dot_path) = dot_path {
+        File::create(&dot_path)
+            .and_then(|mut file| explorer.write_dot(&mut file))
+            .map_err(|error| format!("{}: {}", dot_path, error))?;
+    }
+    Ok(())
+}
+
+fn serve_program(args: &[String]) -> Result<(), String> {
+    let mut program_path = None;
+    let mut port = 4000;
+    let mut options = ServerOptions::default();
+    let mut arguments = args.iter();
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--port" => port = arguments.next().and_then(|port| port.parse::<u16>().ok()).ok_or("--port requires a port number")?,
+            "--idle-timeout" => {
+                let timeout = seconds_argument(argument, arguments.next())?;
+                options.idle_timeout = if timeout.is_zero() { None } else { Some(timeout) };
+            },
+            "--max-sessions" => options.max_sessions = limit_argument(argument, arguments.next())? as usize,
+            "--snapshot-dir" => {
+                let directory = arguments.next().ok_or("--snapshot-dir requires a directory")?;
+                options.snapshot_directory = Some(PathBuf::from(directory));
+            },
+            "--max-instructions" | "--max-time" | "--max-stack" | "--max-output" | "--max-input" => {
+                parse_limit(argument, arguments.next(), &mut options.limits)?;
+            },
+            _ if program_path.is_none() => program_path = Some(argument.clone()),
+            _ => return Err(format!("Unknown argument: {}", argument)),
+        }
+    }
+
+    let program_path = program_path.ok_or("No program given")?;
+    let program = SVMProgram::load(Path::new(&program_path))?;
+    let listener = TcpListener::bind(("127.0.0.1", port)).map_err(|error| format!("Port {}: {}", port, error))?;
+    println!("Serving {} on {}", program_path, listener.local_addr().map_err(|error| error.to_string())?);
+    SVMServer::new(program, options).serve(listener).map_err(|error| error.to_string())
+}
+
+fn serve_gdb(stub: &mut SVMGdbStub, port: u16) -> io::Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    println!("Waiting for GDB on {}", listener.local_addr()?);
+    let (mut stream, address) = listener.accept()?;
+    println!("GDB connected from {}", address);
+    stream.set_nodelay(true)?;
+    stub.serve(&mut stream)
+}
+
+fn load_symbols(path: &Path) -> Result<SVMSymbols, String> {
+    SVMSymbols::load(path).map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+fn configure_engine(engine: &mut SVMEngine, options: &Options, symbols: SVMSymbols) {
+    engine.set_symbols(symbols);
+    engine.set_limits(options.limits.clone());
+    if let Some(ref path) = options.fault_snapshot {
+        engine.set_fault_snapshot(path.clone());
+    }
+    if let Some(ref prefix) = options.meta_prefix {
+        engine.set_meta_prefix(prefix.clone());
+        engine.enable_undo(options.undo_depth);
+    }
+    if let Some(ref directory) = options.save_directory {
+        engine.set_save_directory(directory.clone());
+    }
+    for (name, address) in options.devices.iter() {
+        if let Some(device) = create_device(name) {
+            if let Err(error) = engine.map_device(*address, device) {
+                println!("Failed to map {}: {}", name, error);
+            }
+        }
+    }
+    #[cfg(feature = "host-calls")]
+    if let Some(ref sandbox) = options.host_call_sandbox {
+        engine.enable_host_calls(sandbox.clone());
+    }
+    if options.track_self_modification {
+        engine.enable_code_tracking(options.dump_directory.clone());
+    }
+    if options.profile {
+        engine.enable_profiler();
+    }
+    if options.coverage.is_some() || options.coverage_summary.is_some() {
+        engine.enable_coverage();
+    }
+    if let Some(ref path) = options.trace {
+        match File::create(path) {
+            Ok(file) => engine.enable_trace(Box::new(BufWriter::new(file))),
+            Err(error) => println!("Failed to create trace file {}: {}", path.display(), error),
+        }
+    }
+}
+
+//  What the program asked to exit with through the exit host call
+#[cfg(feature = "host-calls")]
+fn exit_status(engine: &SVMEngine) -> Option<u16> {
+    engine.get_host_calls().and_then(|host_calls| host_calls.get_exit_status())
+}
+
+#[cfg(not(feature = "host-calls"))]
+fn exit_status(_engine: &SVMEngine) -> Option<u16> {
+    None
+}
+
+fn print_reports(engine: &SVMEngine, options: &Options) {
+    if let Some(tracker) = engine.get_code_tracker() {
+        tracker.print_report();
+    }
+    if let Some(profiler) = engine.get_profiler() {
+        profiler.print_report(&engine.get_state().memory, engine.get_symbols(), engine.get_extensions(), 20);
+        if let Some(ref path) = options.folded_stacks {
+            let written = File::create(path).and_then(|file| {
+                let mut writer = BufWriter::new(file);
+                profiler.write_folded_stacks(&mut writer, engine.get_symbols())?;
+                writer.flush()
+            });
+            if let Err(error) = written {
+                println!("Failed to write folded stacks to {}: {}", path.display(), error);
+            }
+        }
+    }
+    if let Some(coverage) = engine.get_coverage() {
+        write_coverage(engine, coverage, options);
+    }
+}
+
+fn write_coverage(engine: &SVMEngine, coverage: &SVMCoverage, options: &Options) {
+    let memory = &engine.get_state().memory;
+    let end = coverage.get_end(memory);
+    if let Some(ref path) = options.coverage {
+        let written = File::create(path).and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            coverage.write_report(&mut writer, memory, end, engine.get_symbols(), engine.get_extensions())?;
+            writer.flush()
+        });
+        if let Err(error) = written {
+            println!("Failed to write the coverage report to {}: {}", path.display(), error);
+        }
+    }
+    if let Some(ref path) = options.coverage_summary {
+        if let Err(error) = fs::write(path, format!("{}\n", coverage.get_summary(end))) {
+            println!("Failed to write the coverage summary to {}: {}", path.display(), error);
+        }
+    }
+}
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    let file = File::open(args[1].clone()).unwrap();
-    let program = SVMProgram::new(&file);
-    // program.print_program();
+    if args.len() == 4 && args[1] == "diff" {
+        if let Err(error) = diff_files(&args[2], &args[3]) {
+            println!("{}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if args.len() == 2 && args[1] == "dap" {
+        //  Requests are read on their own thread, which a locked stdin cannot be sent to
+        let stdout = io::stdout();
+        if let Err(error) = SVMDapServer::new().serve(&mut io::BufReader::new(io::stdin()), &mut stdout.lock()) {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if args.len() >= 3 && args[1] == "explore" {
+        if let Err(error) = explore_program(&args[2..]) {
+            println!("{}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if args.len() >= 3 && args[1] == "serve" {
+        if let Err(error) = serve_program(&args[2..]) {
+            println!("{}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if args.len() >= 3 && args[1] == "disasm" {
+        if let Err(error) = disassemble_file(&args[2..]) {
+            println!("{}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let options = match parse_arguments(&args) {
+        Ok(options) => options,
+        Err(error) => {
+            println!("{}", error);
+            print_usage();
+            std::process::exit(2);
+        }
+    };
+
+    //  Patch files can assemble hcall when the program is given host calls
+    #[cfg(feature = "host-calls")]
+    let extensions = if options.host_call_sandbox.is_some() { ExtensionSet::HOST_CALLS } else { ExtensionSet::STRICT };
+    #[cfg(not(feature = "host-calls"))]
+    let extensions = ExtensionSet::STRICT;
+    let mut program = match SVMProgram::load(Path::new(&options.program_path)) {
+        Ok(program) => program,
+        Err(error) => {
+            println!("{}", error);
+            std::process::exit(1);
+        }
+    };
+    for path in options.patches.iter() {
+        if let Err(error) = SVMPatchFile::load(path, extensions).and_then(|patches| patches.apply(&mut program)) {
+            println!("Failed to apply {}: {}", path.display(), error);
+            std::process::exit(1);
+        }
+    }
+    if options.print_program {
+        program.print_program();
+    }
+    let symbols = match options.symbols {
+        Some(ref path) => match load_symbols(path) {
+            Ok(symbols) => symbols,
+            Err(error) => {
+                println!("{}", error);
+                std::process::exit(1);
+            }
+        },
+        None => program.get_symbols().cloned().unwrap_or_default(),
+    };
+    if options.debug {
+        let mut debugger = SVMDebugger::new(program);
+        configure_engine(debugger.get_engine_mut(), &options, symbols);
+        debugger.run();
+        print_reports(debugger.get_engine_mut(), &options);
+        return;
+    }
+    if options.tui {
+        let mut tui = SVMTui::new(program);
+        configure_engine(tui.get_engine_mut(), &options, symbols);
+        tui.run();
+        print_reports(tui.get_engine_mut(), &options);
+        return;
+    }
+    if let Some(port) = options.gdb_port {
+        let mut engine = SVMEngine::new(program);
+        configure_engine(&mut engine, &options, symbols);
+        let mut stub = SVMGdbStub::new(engine);
+        if let Err(error) = serve_gdb(&mut stub, port) {
+            println!("{}", error);
+            std::process::exit(1);
+        }
+        print_reports(stub.get_engine(), &options);
+        return;
+    }
+
     let mut engine = SVMEngine::new(program);
-    engine.run();
+    configure_engine(&mut engine, &options, symbols);
+
+    let result = engine.run();
+    if let SVMTermination::Error(ref error) = result {
+        engine.print_error(error);
+    }
+    print_reports(&engine, &options);
+    if let Some(ref path) = options.save_snapshot {
+        if let Err(error) = save_snapshot(engine.get_state(), path) {
+            println!("Failed to save snapshot to {}: {}", path.display(), error);
+        }
+    }
+    match result {
+        SVMTermination::Halted => match exit_status(&engine) {
+            Some(status) => std::process::exit(status as i32),
+            None => println!("Halted."),
+        },
+        SVMTermination::Error(_) => std::process::exit(1),
+        limit => {
+            println!("Stopped: {}", limit.get_description());
+            std::process::exit(3);
+        },
+    }
 }
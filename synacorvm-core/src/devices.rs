@@ -0,0 +1,23 @@
+use super::svm_engine_state::SVMEngineState;
+use super::svm_error::SVMError;
+
+//  Lets the host put devices at memory addresses. rmem and wmem ask the bus
+//  first and only fall back to memory when it returns None for the address.
+//  Instruction fetches and the other opcodes always see plain memory.
+pub trait DeviceBus {
+    fn read(&mut self, engine_state: &mut SVMEngineState, address: u16) -> Option<Result<u16, SVMError>>;
+    fn write(&mut self, engine_state: &mut SVMEngineState, address: u16, value: u16) -> Option<Result<(), SVMError>>;
+}
+
+//  A bus without devices, for machines that only have memory
+pub struct NoDevices;
+
+impl DeviceBus for NoDevices {
+    fn read(&mut self, _engine_state: &mut SVMEngineState, _address: u16) -> Option<Result<u16, SVMError>> {
+        None
+    }
+
+    fn write(&mut self, _engine_state: &mut SVMEngineState, _address: u16, _value: u16) -> Option<Result<(), SVMError>> {
+        None
+    }
+}
@@ -7,11 +7,7 @@ pub trait MemoryValue {
 
 impl MemoryValue for u16 {
     fn is_valid_memory_address(&self) -> bool {
-        if *self < (std::i16::MAX as u16 + 1) {
-            true
-        } else {
-            false
-        }
+        *self < (i16::MAX as u16 + 1)
     }
 }
 
@@ -23,23 +19,22 @@ pub trait RegisterValue {
 
 impl RegisterValue for u16 {
     fn get_register_index(&self) -> u16 {
-        *self - (std::i16::MAX as u16 + 1)
+        self.wrapping_sub(i16::MAX as u16 + 1)
     }
 
     fn is_valid_register(&self) -> bool {
         let register = self.get_register_index();
-        if register <= 7 {
-            true
-        } else {
-            false
-        }
+        register <= 7
     }
     
+    //  Literals are 0..32767 and registers 32768..32775, anything above is invalid
     fn unwrap_potential_register(&self, registers: &Registers) -> Result<u16, SVMError> {
         if self.is_valid_register() {
             registers.get_register(*self)
-        } else {
+        } else if self.is_valid_memory_address() {
             Ok(*self)
+        } else {
+            Err(SVMError::InvalidOperand)
         }
     }
 }
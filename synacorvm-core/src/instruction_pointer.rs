@@ -2,6 +2,7 @@ use super::extensions::MemoryValue;
 use super::memory::Memory;
 use super::svm_error::SVMError;
 
+#[derive(Clone, Hash)]
 pub struct InstructionPointer {
     ip: u16,
 }
@@ -27,8 +28,8 @@ impl InstructionPointer {
     }
 
     pub fn get_next_memory_value(&mut self, memory: &Memory) -> Result<u16, SVMError> {
-        let nextValue = memory.load_memory(self.ip);
+        let next_value = memory.load_memory(self.ip);
         self.ip += 1;
-        nextValue
+        next_value
     }
 }
\ No newline at end of file
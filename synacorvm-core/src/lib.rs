@@ -0,0 +1,18 @@
+//  The execution core of the VM: memory, registers and the opcode semantics.
+//  It only needs an allocator, so it builds for microcontrollers and
+//  wasm32-unknown-unknown. Loading programs, terminal I/O and the tools live
+//  in the synacorvm crate.
+#![no_std]
+
+extern crate alloc;
+
+pub mod svm_engine_state;
+pub mod svm_error;
+pub mod svm_constants;
+pub mod opcode;
+pub mod devices;
+
+pub mod memory;
+pub mod extensions;
+mod registers;
+mod instruction_pointer;
@@ -0,0 +1,69 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use super::svm_error::SVMError;
+use super::extensions::MemoryValue;
+use super::svm_constants::MEMORY_SIZE_MAX;
+
+//  Memory is split into reference counted pages so that cloning a machine only
+//  copies page pointers. A page is copied the first time a clone writes to it.
+const PAGE_SIZE: usize = 256;
+const NUM_OF_PAGES: usize = MEMORY_SIZE_MAX.div_ceil(PAGE_SIZE);
+
+type Page = [u16; PAGE_SIZE];
+
+#[derive(Clone, Hash)]
+pub struct Memory {
+    pages: Vec<Arc<Page>>,
+}
+
+impl Memory {
+    pub fn new(data: &[u16]) -> Memory {
+        let zero_page = Arc::new([0; PAGE_SIZE]);
+        let mut pages = Vec::with_capacity(NUM_OF_PAGES);
+        for page_index in 0..NUM_OF_PAGES {
+            let start = (page_index * PAGE_SIZE).min(data.len());
+            let end = ((page_index + 1) * PAGE_SIZE).min(data.len());
+            let chunk = &data[start..end];
+            if chunk.iter().all(|value| *value == 0) {
+                pages.push(zero_page.clone());
+            } else {
+                let mut page = [0; PAGE_SIZE];
+                page[..chunk.len()].copy_from_slice(chunk);
+                pages.push(Arc::new(page));
+            }
+        }
+        Memory {
+            pages,
+        }
+    }
+
+    pub fn store_memory(&mut self, address: u16, value: u16) -> Result<(), SVMError> {
+        if !address.is_valid_memory_address() {
+            Err(SVMError::InvalidMemory)
+        } else {
+            let address_value = address as usize;
+            Arc::make_mut(&mut self.pages[address_value / PAGE_SIZE])[address_value % PAGE_SIZE] = value;
+            Ok(())
+        }
+    }
+
+    pub fn load_memory(&self, address: u16) -> Result<u16, SVMError> {
+        if !address.is_valid_memory_address() {
+            Err(SVMError::InvalidMemory)
+        } else {
+            let address_value = address as usize;
+            Ok(self.pages[address_value / PAGE_SIZE][address_value % PAGE_SIZE])
+        }
+    }
+
+    //  Number of pages shared with another clone (untouched zero pages share one page),
+    //  useful for judging how cheap forks are
+    pub fn get_shared_page_count(&self) -> usize {
+        self.pages.iter().filter(|page| Arc::strong_count(page) > 1).count()
+    }
+
+    //  Number of pages this memory and the other still hold the same copy of
+    pub fn get_pages_shared_with(&self, other: &Memory) -> usize {
+        self.pages.iter().zip(other.pages.iter()).filter(|(page, other_page)| Arc::ptr_eq(page, other_page)).count()
+    }
+}
@@ -1,15 +1,41 @@
-use super::extensions::RegisterValue;
-use super::svm_engine_state::SVMEngineState;
+use super::devices::{DeviceBus, NoDevices};
+use super::extensions::{MemoryValue, RegisterValue};
+use super::svm_engine_state::{SVMEngineState, MemoryWrite, CallFrame};
 use super::svm_error::SVMError;
 
-use std::io::{Read,Write};
+//  Opcodes beyond the spec that a host can turn on. Strict spec mode is the
+//  empty set, which is what an engine decodes with until its host says otherwise.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ExtensionSet(u16);
+
+impl ExtensionSet {
+    pub const STRICT: ExtensionSet = ExtensionSet(0);
+    //  hcall (22), which traps to the host's service table
+    #[cfg(feature = "host-calls")]
+    pub const HOST_CALLS: ExtensionSet = ExtensionSet(1);
+
+    pub fn contains(self, other: ExtensionSet) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn union(self, other: ExtensionSet) -> ExtensionSet {
+        ExtensionSet(self.0 | other.0)
+    }
+}
 
 pub trait OpcodeValue {
-    fn get_opcode(&self) -> Result<SVMOpCode, SVMError>;
+    //  Decodes the spec's opcodes plus those of the extensions in the set
+    fn get_opcode_with(&self, extensions: ExtensionSet) -> Result<SVMOpCode, SVMError>;
+
+    fn get_opcode(&self) -> Result<SVMOpCode, SVMError> {
+        self.get_opcode_with(ExtensionSet::STRICT)
+    }
 }
 
 impl OpcodeValue for u16 {
-    fn get_opcode(&self) -> Result<SVMOpCode, SVMError> {
+    //  Without extensions compiled in, every set decodes the spec alone
+    #[cfg_attr(not(feature = "host-calls"), allow(unused_variables))]
+    fn get_opcode_with(&self, extensions: ExtensionSet) -> Result<SVMOpCode, SVMError> {
         match *self {
             0 => Ok(SVMOpCode::Halt),
             1 => Ok(SVMOpCode::Set),
@@ -33,13 +59,19 @@ impl OpcodeValue for u16 {
             19 => Ok(SVMOpCode::Out),
             20 => Ok(SVMOpCode::In),
             21 => Ok(SVMOpCode::NoOp),
+            #[cfg(feature = "host-calls")]
+            22 if extensions.contains(ExtensionSet::HOST_CALLS) => Ok(SVMOpCode::HostCall),
             _ => Err(SVMError::InvalidOpCode)
         }
     }
 }
 
 pub trait OpCode {
-    fn dispatch(&self, engine_state: &mut SVMEngineState) -> Result<(), SVMError>;
+    fn dispatch_with_devices(&self, engine_state: &mut SVMEngineState, devices: &mut dyn DeviceBus) -> Result<(), SVMError>;
+
+    fn dispatch(&self, engine_state: &mut SVMEngineState) -> Result<(), SVMError> {
+        self.dispatch_with_devices(engine_state, &mut NoDevices)
+    }
 }
 
 pub enum SVMOpCode {
@@ -64,11 +96,99 @@ pub enum SVMOpCode {
     Ret,
     Out,
     In,
-    NoOp
+    NoOp,
+    #[cfg(feature = "host-calls")]
+    HostCall,
+}
+
+//  One more than the highest opcode value, extensions included
+pub const NUM_OF_OPCODES: u16 = 23;
+
+impl SVMOpCode {
+    pub fn get_value(&self) -> u16 {
+        match *self {
+            SVMOpCode::Halt => 0,
+            SVMOpCode::Set => 1,
+            SVMOpCode::Push => 2,
+            SVMOpCode::Pop => 3,
+            SVMOpCode::Eq => 4,
+            SVMOpCode::Gt => 5,
+            SVMOpCode::Jmp => 6,
+            SVMOpCode::Jt => 7,
+            SVMOpCode::Jf => 8,
+            SVMOpCode::Add => 9,
+            SVMOpCode::Mult => 10,
+            SVMOpCode::Mod => 11,
+            SVMOpCode::And => 12,
+            SVMOpCode::Or => 13,
+            SVMOpCode::Not => 14,
+            SVMOpCode::Rmem => 15,
+            SVMOpCode::Wmem => 16,
+            SVMOpCode::Call => 17,
+            SVMOpCode::Ret => 18,
+            SVMOpCode::Out => 19,
+            SVMOpCode::In => 20,
+            SVMOpCode::NoOp => 21,
+            #[cfg(feature = "host-calls")]
+            SVMOpCode::HostCall => 22,
+        }
+    }
+
+    pub fn get_mnemonic(&self) -> &'static str {
+        match *self {
+            SVMOpCode::Halt => "halt",
+            SVMOpCode::Set => "set",
+            SVMOpCode::Push => "push",
+            SVMOpCode::Pop => "pop",
+            SVMOpCode::Eq => "eq",
+            SVMOpCode::Gt => "gt",
+            SVMOpCode::Jmp => "jmp",
+            SVMOpCode::Jt => "jt",
+            SVMOpCode::Jf => "jf",
+            SVMOpCode::Add => "add",
+            SVMOpCode::Mult => "mult",
+            SVMOpCode::Mod => "mod",
+            SVMOpCode::And => "and",
+            SVMOpCode::Or => "or",
+            SVMOpCode::Not => "not",
+            SVMOpCode::Rmem => "rmem",
+            SVMOpCode::Wmem => "wmem",
+            SVMOpCode::Call => "call",
+            SVMOpCode::Ret => "ret",
+            SVMOpCode::Out => "out",
+            SVMOpCode::In => "in",
+            SVMOpCode::NoOp => "noop",
+            #[cfg(feature = "host-calls")]
+            SVMOpCode::HostCall => "hcall",
+        }
+    }
+
+    //  Index of the operand that holds a memory address, if any
+    pub fn get_address_operand(&self) -> Option<usize> {
+        match *self {
+            SVMOpCode::Jmp | SVMOpCode::Call | SVMOpCode::Wmem => Some(0),
+            SVMOpCode::Jt | SVMOpCode::Jf | SVMOpCode::Rmem => Some(1),
+            _ => None,
+        }
+    }
+
+    pub fn get_operand_count(&self) -> u16 {
+        match *self {
+            SVMOpCode::Halt | SVMOpCode::Ret | SVMOpCode::NoOp => 0,
+            #[cfg(feature = "host-calls")]
+            SVMOpCode::HostCall => 0,
+            SVMOpCode::Push | SVMOpCode::Pop | SVMOpCode::Jmp | SVMOpCode::Call
+                | SVMOpCode::Out | SVMOpCode::In => 1,
+            SVMOpCode::Set | SVMOpCode::Jt | SVMOpCode::Jf | SVMOpCode::Not
+                | SVMOpCode::Rmem | SVMOpCode::Wmem => 2,
+            SVMOpCode::Eq | SVMOpCode::Gt | SVMOpCode::Add | SVMOpCode::Mult
+                | SVMOpCode::Mod | SVMOpCode::And | SVMOpCode::Or => 3,
+        }
+    }
 }
 
 impl OpCode for SVMOpCode {
-    fn dispatch(&self, engine_state: &mut SVMEngineState) -> Result<(), SVMError> {
+    fn dispatch_with_devices(&self, engine_state: &mut SVMEngineState, devices: &mut dyn DeviceBus) -> Result<(), SVMError> {
         match *self {
             SVMOpCode::Halt => halt(engine_state),
             SVMOpCode::Set => set(engine_state),
@@ -85,29 +205,32 @@ impl OpCode for SVMOpCode {
             SVMOpCode::And => and(engine_state),
             SVMOpCode::Or => or(engine_state),
             SVMOpCode::Not => not(engine_state),
-            SVMOpCode::Rmem => rmem(engine_state),
-            SVMOpCode::Wmem => wmem(engine_state),
+            SVMOpCode::Rmem => rmem(engine_state, devices),
+            SVMOpCode::Wmem => wmem(engine_state, devices),
             SVMOpCode::Call => call(engine_state),
             SVMOpCode::Ret => ret(engine_state),
             SVMOpCode::Out => output(engine_state),
             SVMOpCode::In => input(engine_state),
             SVMOpCode::NoOp => noop(engine_state),
+            //  The services belong to the host, which handles hcall before dispatching
+            #[cfg(feature = "host-calls")]
+            SVMOpCode::HostCall => Err(SVMError::InvalidOpCode),
         }
     }
 }
 
 //  Opcode Implementations as functions
 //  NOTE: Some of the names are inconsistent. This is due to them being keywords as well
-fn halt(_engine_state: &mut SVMEngineState) -> Result<(), SVMError> {
-    println!("Halted.");
-    std::process::exit(0);
+fn halt(engine_state: &mut SVMEngineState) -> Result<(), SVMError> {
+    engine_state.halted = true;
+    Ok(())
 }
 
 fn set(engine_state: &mut SVMEngineState) -> Result<(), SVMError> {
     let register = engine_state.instruction_pointer.get_next_memory_value(&engine_state.memory)?;
     let potential_register =  engine_state.instruction_pointer.get_next_memory_value(&engine_state.memory)?;
     let value = potential_register.unwrap_potential_register(&engine_state.registers)?;
-    engine_state.registers.set_register(register, value)?;
+    set_register_or_memory(engine_state, register, value)?;
     Ok(())
 }
 
@@ -124,6 +247,8 @@ fn pop(engine_state: &mut SVMEngineState) -> Result<(), SVMError> {
         Some(x) => x,
         None => { return Err(SVMError::StackEmpty); }
     };
+    let pop_address = engine_state.instruction_pointer.get_ip() - 2;
+    engine_state.check_call_stack(pop_address, value, false);
 
     set_register_or_memory(engine_state, destination, value)?;
     Ok(())
@@ -177,7 +302,8 @@ fn jmp(engine_state: &mut SVMEngineState) -> Result<(), SVMError> {
 fn jt(engine_state: &mut SVMEngineState) -> Result<(), SVMError> {
     let value = engine_state.instruction_pointer.get_next_memory_value(&engine_state.memory)?
         .unwrap_potential_register(&engine_state.registers)?;
-    let address = engine_state.instruction_pointer.get_next_memory_value(&engine_state.memory)?;
+    let address = engine_state.instruction_pointer.get_next_memory_value(&engine_state.memory)?
+        .unwrap_potential_register(&engine_state.registers)?;
 
     if value != 0 {
         engine_state.instruction_pointer.set_ip(address)?;
@@ -189,7 +315,8 @@ fn jt(engine_state: &mut SVMEngineState) -> Result<(), SVMError> {
 fn jf(engine_state: &mut SVMEngineState) -> Result<(), SVMError> {
     let value = engine_state.instruction_pointer.get_next_memory_value(&engine_state.memory)?
         .unwrap_potential_register(&engine_state.registers)?;
-    let address = engine_state.instruction_pointer.get_next_memory_value(&engine_state.memory)?;
+    let address = engine_state.instruction_pointer.get_next_memory_value(&engine_state.memory)?
+        .unwrap_potential_register(&engine_state.registers)?;
 
     if value == 0 {
         engine_state.instruction_pointer.set_ip(address)?;
@@ -207,11 +334,12 @@ fn add(engine_state: &mut SVMEngineState) -> Result<(), SVMError> {
     let right = engine_state.instruction_pointer.get_next_memory_value(&engine_state.memory)?
         .unwrap_potential_register(&engine_state.registers)?;
 
-    let result = (left + right) % (std::i16::MAX as u16 + 1);
+    //  Registers loaded with rmem can hold any 16 bit value, so add in 32 bits like mult
+    let result = (left as u32 + right as u32) % (i16::MAX as u32 + 1);
 
     // print!("add: destination={}, left={}, right={}, result={}\n", destination, left, right, result);
 
-    engine_state.registers.set_register(destination, result)?;
+    set_register_or_memory(engine_state, destination, result as u16)?;
 
     Ok(())
 }
@@ -226,8 +354,8 @@ fn mult(engine_state: &mut SVMEngineState) -> Result<(), SVMError> {
         .unwrap_potential_register(&engine_state.registers)?;
     
     // A little messy here but we don't want to overflow
-    let result = (left as u32 * right as u32) % (std::i16::MAX as u32 + 1);
-    engine_state.registers.set_register(destination, result as u16)?;
+    let result = (left as u32 * right as u32) % (i16::MAX as u32 + 1);
+    set_register_or_memory(engine_state, destination, result as u16)?;
 
     Ok(())
 }
@@ -241,9 +369,9 @@ fn modulus(engine_state: &mut SVMEngineState) -> Result<(), SVMError> {
     let right = engine_state.instruction_pointer.get_next_memory_value(&engine_state.memory)?
         .unwrap_potential_register(&engine_state.registers)?;
     
-    let result = left % right;
+    let result = left.checked_rem(right).ok_or(SVMError::DivisionByZero)?;
 
-    engine_state.registers.set_register(destination, result)?;
+    set_register_or_memory(engine_state, destination, result)?;
 
     Ok(())
 }
@@ -259,7 +387,7 @@ fn and(engine_state: &mut SVMEngineState) -> Result<(), SVMError> {
     
     let result = left & right;
 
-    engine_state.registers.set_register(destination, result)?;
+    set_register_or_memory(engine_state, destination, result)?;
 
     Ok(())
 }
@@ -275,7 +403,7 @@ fn or(engine_state: &mut SVMEngineState) -> Result<(), SVMError> {
     
     let result = left | right;
 
-    engine_state.registers.set_register(destination, result)?;
+    set_register_or_memory(engine_state, destination, result)?;
 
     Ok(())
 }
@@ -288,42 +416,60 @@ fn not(engine_state: &mut SVMEngineState) -> Result<(), SVMError> {
 
     let result = !value & 0x7FFF;
 
-    engine_state.registers.set_register(destination, result)?;
+    set_register_or_memory(engine_state, destination, result)?;
 
     Ok(())
 }
 
-fn rmem(engine_state: &mut SVMEngineState) -> Result<(), SVMError> {
+fn rmem(engine_state: &mut SVMEngineState, devices: &mut dyn DeviceBus) -> Result<(), SVMError> {
     let destination_reg = engine_state.instruction_pointer.get_next_memory_value(&engine_state.memory)?;
     let source_address = engine_state.instruction_pointer.get_next_memory_value(&engine_state.memory)?
         .unwrap_potential_register(&engine_state.registers)?;
-    let value = engine_state.memory.load_memory(source_address)?;
-    engine_state.registers.set_register(destination_reg, value)?;
+    let value = match devices.read(engine_state, source_address) {
+        Some(value) => value?,
+        None => engine_state.memory.load_memory(source_address)?,
+    };
+    set_register_or_memory(engine_state, destination_reg, value)?;
     Ok(())
 }
 
-fn wmem(engine_state: &mut SVMEngineState) -> Result<(), SVMError> {
+fn wmem(engine_state: &mut SVMEngineState, devices: &mut dyn DeviceBus) -> Result<(), SVMError> {
     let destination_address = engine_state.instruction_pointer.get_next_memory_value(&engine_state.memory)?
         .unwrap_potential_register(&engine_state.registers)?;
     let value = engine_state.instruction_pointer.get_next_memory_value(&engine_state.memory)?
         .unwrap_potential_register(&engine_state.registers)?;
-    engine_state.memory.store_memory(destination_address, value)?;
-    Ok(())
+    match devices.write(engine_state, destination_address, value) {
+        Some(result) => result,
+        None => store_memory(engine_state, destination_address, value),
+    }
 }
 
 fn call(engine_state: &mut SVMEngineState) -> Result<(), SVMError> {
     let jump_address =  engine_state.instruction_pointer.get_next_memory_value(&engine_state.memory)?
         .unwrap_potential_register(&engine_state.registers)?;
-    engine_state.stack.push(engine_state.instruction_pointer.get_ip());
+    let return_address = engine_state.instruction_pointer.get_ip();
+    //  Frames left at or above this depth had their slot popped and are never returned through
+    let depth = engine_state.stack.len();
+    engine_state.call_stack.retain(|frame| frame.stack_depth < depth);
+    engine_state.call_stack.push(CallFrame {
+        call_site: return_address - 2,
+        target: jump_address,
+        return_address,
+        stack_depth: engine_state.stack.len(),
+    });
+    engine_state.stack.push(return_address);
     engine_state.instruction_pointer.set_ip(jump_address)?;
     Ok(())
 }
 
+//  Returning with an empty stack halts the machine, as the spec requires
 fn ret(engine_state: &mut SVMEngineState) -> Result<(), SVMError> {
     let return_address = match engine_state.stack.pop() {
-        Some(x) => Ok(x),
-        None => Err(SVMError::StackEmpty),
-    }?;
+        Some(x) => x,
+        None => return halt(engine_state),
+    };
+    let ret_address = engine_state.instruction_pointer.get_ip() - 1;
+    engine_state.check_call_stack(ret_address, return_address, true);
 
     engine_state.instruction_pointer.set_ip(return_address)?;
 
@@ -333,30 +479,18 @@ fn ret(engine_state: &mut SVMEngineState) -> Result<(), SVMError> {
 fn output(engine_state: &mut SVMEngineState) -> Result<(), SVMError> {
     let out_char = engine_state.instruction_pointer.get_next_memory_value(&engine_state.memory)?
         .unwrap_potential_register(&engine_state.registers)?;
-    let out_array : [u8; 1] = [out_char as u8; 1];
-    match std::io::stdout().write(&out_array) {
-        Ok(_) => { return Ok(()) },
-        Err(_) => { return Err(SVMError::WriteError); }
-    };
-
+    engine_state.output_buffer.push(out_char as u8);
+    Ok(())
 }
 
+//  The engine fills the input buffer before dispatching `in`
 fn input(engine_state: &mut SVMEngineState) -> Result<(), SVMError> {
     let destination = engine_state.instruction_pointer.get_next_memory_value(&engine_state.memory)?;
-    let mut input_buffer : [u8; 1] = [0; 1];
-    match std::io::stdin().read_exact(&mut input_buffer) {
-        Ok(_) => {},
-        Err(_) => { return Err(SVMError::ReadError); }
+    let value = match engine_state.input_buffer.pop_front() {
+        Some(x) => x,
+        None => { return Err(SVMError::ReadError); }
     };
-    if input_buffer[0] == 13   
-    {
-        // We need to consume carriage returns if we're on Windows
-        match std::io::stdin().read_exact(&mut input_buffer) {
-            Ok(_) => {},
-            Err(_) => { return Err(SVMError::ReadError); }
-        };
-    }
-    engine_state.registers.set_register(destination, input_buffer[0] as u16)?;
+    set_register_or_memory(engine_state, destination, value as u16)?;
     Ok(())
 }
 
@@ -364,11 +498,25 @@ fn noop(_engine_state: &mut SVMEngineState) -> Result<(), SVMError> {
     Ok(())
 }
 
+//  Every destination operand is a register or, failing that, a memory address
 fn set_register_or_memory(engine_state: &mut SVMEngineState, destination: u16, value: u16) -> Result<(), SVMError> {
     if destination.is_valid_register() {
-        engine_state.registers.set_register(destination, value)?;
+        engine_state.registers.set_register(destination, value)
+    } else if destination.is_valid_memory_address() {
+        store_memory(engine_state, destination, value)
     } else {
-        engine_state.memory.store_memory(destination, value)?;
+        Err(SVMError::InvalidOperand)
     }
+}
+
+//  All memory writes made by opcodes go through here so the engine can see what the last instruction modified
+fn store_memory(engine_state: &mut SVMEngineState, address: u16, value: u16) -> Result<(), SVMError> {
+    let old_value = engine_state.memory.load_memory(address)?;
+    engine_state.memory.store_memory(address, value)?;
+    engine_state.last_memory_write = Some(MemoryWrite {
+        address,
+        old_value,
+        new_value: value,
+    });
     Ok(())
 }
\ No newline at end of file
@@ -2,6 +2,7 @@ use super::extensions::RegisterValue;
 use super::svm_constants::NUM_OF_REGISTERS;
 use super::svm_error::SVMError;
 
+#[derive(Clone, Hash)]
 pub struct Registers {
     registers: [u16; NUM_OF_REGISTERS],
 }
@@ -33,4 +34,12 @@ impl Registers {
             Ok(())
         }
     }
-}
\ No newline at end of file
+
+    pub fn get_register_by_index(&self, index: usize) -> u16 {
+        self.registers[index]
+    }
+
+    pub fn set_register_by_index(&mut self, index: usize, value: u16) {
+        self.registers[index] = value;
+    }
+}
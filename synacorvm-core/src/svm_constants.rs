@@ -0,0 +1,4 @@
+
+//  Addresses run from 0 to 32767
+pub const MEMORY_SIZE_MAX: usize = i16::MAX as usize + 1;
+pub const NUM_OF_REGISTERS: usize = 8;
\ No newline at end of file
@@ -0,0 +1,138 @@
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::hash::{Hash, Hasher};
+use super::instruction_pointer::InstructionPointer;
+use super::memory::Memory;
+use super::registers::Registers;
+
+//  Only the most recent stack discipline violations are kept
+const MAX_STACK_VIOLATIONS: usize = 256;
+
+//  FNV-1a, since the standard library's hasher is not available without std
+struct MachineHasher(u64);
+
+impl Hasher for MachineHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x100_0000_01b3);
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct MemoryWrite {
+    pub address: u16,
+    pub old_value: u16,
+    pub new_value: u16,
+}
+
+//  Pushed by call alongside the return address so return addresses can be told apart from data
+#[derive(Clone)]
+pub struct CallFrame {
+    pub call_site: u16,
+    pub target: u16,
+    pub return_address: u16,
+    pub stack_depth: usize,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum StackViolationKind {
+    ReturnedToData,
+    OverwrittenReturn,
+    PoppedReturn,
+}
+
+#[derive(Clone)]
+pub struct StackViolation {
+    pub address: u16,
+    pub kind: StackViolationKind,
+    pub value: u16,
+}
+
+#[derive(Clone)]
+pub struct SVMEngineState {
+    pub instruction_pointer: InstructionPointer,
+    pub registers: Registers,
+    pub memory: Memory,
+    pub stack: Vec<u16>,
+    pub call_stack: Vec<CallFrame>,
+    pub stack_violations: VecDeque<StackViolation>,
+    pub stack_violation_count: usize,
+    pub input_buffer: VecDeque<u8>,
+    pub output_buffer: Vec<u8>,
+    pub halted: bool,
+    pub instruction_count: u64,
+    pub output_bytes: u64,
+    pub input_reads: u64,
+    pub last_memory_write: Option<MemoryWrite>,
+}
+
+impl SVMEngineState {
+    pub fn new(program_data: &[u16]) -> SVMEngineState {
+        SVMEngineState {
+            instruction_pointer: InstructionPointer::new(),
+            registers: Registers::new(),
+            memory: Memory::new(program_data),
+            stack: Vec::new(),
+            call_stack: Vec::new(),
+            stack_violations: VecDeque::new(),
+            stack_violation_count: 0,
+            input_buffer: VecDeque::new(),
+            output_buffer: Vec::new(),
+            halted: false,
+            instruction_count: 0,
+            output_bytes: 0,
+            input_reads: 0,
+            last_memory_write: None,
+        }
+    }
+
+    //  Identifies the machine state, ignoring counters and pending I/O
+    pub fn get_machine_hash(&self) -> u64 {
+        let mut hasher = MachineHasher(0xcbf2_9ce4_8422_2325);
+        self.instruction_pointer.hash(&mut hasher);
+        self.registers.hash(&mut hasher);
+        self.stack.hash(&mut hasher);
+        self.memory.hash(&mut hasher);
+        self.halted.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    //  Drops call frames whose return address slot has been consumed and reports how it happened.
+    //  A frame whose return address was popped stays until its ret, so that a ret
+    //  to something pushed in its place is reported as overwriting it.
+    pub fn check_call_stack(&mut self, address: u16, value: u16, is_return: bool) {
+        let depth = self.stack.len();
+        let mut matched = None;
+        while let Some(frame) = self.call_stack.pop() {
+            if frame.stack_depth < depth {
+                self.call_stack.push(frame);
+                break;
+            }
+            if frame.stack_depth == depth {
+                matched = Some(frame);
+                break;
+            }
+        }
+
+        let kind = match (matched, is_return) {
+            (Some(ref frame), true) if frame.return_address == value => return,
+            (Some(_), true) => StackViolationKind::OverwrittenReturn,
+            (Some(frame), false) => {
+                self.call_stack.push(frame);
+                StackViolationKind::PoppedReturn
+            },
+            (None, true) => StackViolationKind::ReturnedToData,
+            (None, false) => return,
+        };
+        if self.stack_violations.len() == MAX_STACK_VIOLATIONS {
+            self.stack_violations.pop_front();
+        }
+        self.stack_violations.push_back(StackViolation { address, kind, value });
+        self.stack_violation_count += 1;
+    }
+}
@@ -0,0 +1,28 @@
+#[derive(Debug)]
+pub enum SVMError {
+    InvalidMemory,
+    InvalidRegister,
+    InvalidOperand,
+    DivisionByZero,
+    InvalidOpCode,
+    StackEmpty,
+    WriteError,
+    ReadError,
+    AwaitingInput,
+}
+
+impl SVMError {
+    pub fn get_description(&self) -> &'static str {
+        match *self {
+            SVMError::InvalidMemory => "Memory Error",
+            SVMError::InvalidOpCode => "Invalid Opcode",
+            SVMError::InvalidRegister => "Invalid Register",
+            SVMError::InvalidOperand => "Invalid Operand",
+            SVMError::DivisionByZero => "Division by zero",
+            SVMError::ReadError => "Read error",
+            SVMError::StackEmpty => "Stack error",
+            SVMError::WriteError => "Write error",
+            SVMError::AwaitingInput => "Waiting for input",
+        }
+    }
+}
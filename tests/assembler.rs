@@ -0,0 +1,20 @@
+//  Assembling labels, data and .org directives into words
+use synacorvm::engine::svm_assembler::SVMAssembly;
+use synacorvm_core::opcode::ExtensionSet;
+
+#[test]
+fn org_pads_with_zeros_and_moves_labels() {
+    let assembly = SVMAssembly::assemble("jmp end\n.org 5\nend: data \"Hi\" 0\n", ExtensionSet::STRICT).unwrap();
+    assert_eq!(assembly.get_words(), &[6, 5, 0, 0, 0, 72, 105, 0]);
+    assert_eq!(assembly.get_label("end"), Some(5));
+    assert_eq!(assembly.get_line_for_address(6), Some(3));
+}
+
+#[test]
+fn org_must_stay_ahead_and_inside_memory() {
+    assert_eq!(SVMAssembly::assemble("noop\nnoop\n.org 1\n", ExtensionSet::STRICT).err(),
+               Some("line 3: .org 1 is behind the current address 2".to_string()));
+    assert_eq!(SVMAssembly::assemble(".org 40000\n", ExtensionSet::STRICT).err(),
+               Some("line 1: .org 40000 is past the end of memory".to_string()));
+    assert!(SVMAssembly::assemble(".org 32767\nhalt\n", ExtensionSet::STRICT).is_ok());
+}
@@ -0,0 +1,64 @@
+//  The shadow call stack and the stack discipline violations it catches
+use synacorvm::engine::svm_assembler::SVMAssembly;
+use synacorvm::engine::svm_backtrace::format_backtrace;
+use synacorvm::engine::svm_engine::{SVMEngine, SVMTermination};
+use synacorvm_core::opcode::ExtensionSet;
+use synacorvm_core::svm_engine_state::StackViolationKind;
+
+fn run(source: &str) -> (SVMEngine, SVMAssembly) {
+    let assembly = SVMAssembly::assemble(source, ExtensionSet::STRICT).unwrap_or_else(|error| panic!("{}", error));
+    let mut engine = SVMEngine::new(assembly.get_program());
+    engine.detach_console();
+    assert!(matches!(engine.run(), SVMTermination::Halted));
+    (engine, assembly)
+}
+
+fn violations(engine: &SVMEngine) -> Vec<(StackViolationKind, u16, u16)> {
+    engine.get_state().stack_violations.iter().map(|violation| (violation.kind, violation.address, violation.value)).collect()
+}
+
+#[test]
+fn a_clean_call_and_ret_leave_no_frames() {
+    let (engine, _) = run("call f\nhalt\nf: push 1\npop r0\nret");
+    assert!(violations(&engine).is_empty());
+    assert!(engine.get_state().call_stack.is_empty());
+}
+
+#[test]
+fn the_backtrace_lists_the_frames_innermost_first() {
+    let assembly = SVMAssembly::assemble("call f\nhalt\nf: call g\nret\ng: noop\nret", ExtensionSet::STRICT).unwrap();
+    let mut engine = SVMEngine::new(assembly.get_program());
+    let g = assembly.get_label("g").unwrap();
+    engine.run_until(|engine_state| engine_state.instruction_pointer.get_ip() == g);
+    let symbols = assembly.get_symbols();
+    assert_eq!(format_backtrace(engine.get_state(), &symbols), vec![
+        "#0                  g in g".to_string(),
+        "#1                  f in f".to_string(),
+        "#2                  0 in <root>".to_string(),
+    ]);
+}
+
+#[test]
+fn ret_to_a_pushed_value_is_returned_to_data() {
+    let (engine, assembly) = run("push done\nret\ndone: halt");
+    let done = assembly.get_label("done").unwrap();
+    assert!(violations(&engine) == vec![(StackViolationKind::ReturnedToData, 2, done)]);
+}
+
+#[test]
+fn popping_a_return_address_is_reported() {
+    let (engine, assembly) = run("call f\nhalt\nf: pop r0\njmp r0");
+    assert!(violations(&engine) == vec![(StackViolationKind::PoppedReturn, assembly.get_label("f").unwrap(), 2)]);
+}
+
+#[test]
+fn ret_through_a_replaced_return_address_is_overwritten() {
+    let (engine, assembly) = run("call f\nhalt\nf: pop r0\npush elsewhere\nret\nelsewhere: halt");
+    let f = assembly.get_label("f").unwrap();
+    let elsewhere = assembly.get_label("elsewhere").unwrap();
+    assert!(violations(&engine) == vec![
+        (StackViolationKind::PoppedReturn, f, 2),
+        (StackViolationKind::OverwrittenReturn, f + 4, elsewhere),
+    ]);
+    assert!(engine.get_state().call_stack.is_empty());
+}
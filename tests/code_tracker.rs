@@ -0,0 +1,45 @@
+//  Self-modification detection on programs that rewrite code they already ran
+use std::env;
+use std::fs;
+use synacorvm::engine::svm_engine::{SVMEngine, SVMTermination};
+use synacorvm::engine::svm_program::SVMProgram;
+
+//  noop, wmem 0 0 over the noop that already ran, jmp 0 into the halt it wrote
+const REWRITES_ITSELF: [u16; 6] = [21, 16, 0, 0, 6, 0];
+
+fn run_tracked(words: &[u16], dump_directory: Option<std::path::PathBuf>) -> SVMEngine {
+    let mut engine = SVMEngine::new(SVMProgram::from_words(words.to_vec()));
+    engine.detach_console();
+    engine.enable_code_tracking(dump_directory);
+    assert!(matches!(engine.run(), SVMTermination::Halted));
+    engine
+}
+
+#[test]
+fn rewriting_executed_code_is_reported() {
+    let engine = run_tracked(&REWRITES_ITSELF, None);
+    let regions = engine.get_code_tracker().unwrap().get_modified_regions();
+    assert_eq!(regions.len(), 1);
+    let region = &regions[0];
+    assert_eq!((region.start, region.end, region.changed_words), (0, 0, 1));
+    assert_eq!(region.writer_ips.iter().cloned().collect::<Vec<u16>>(), vec![1]);
+    assert!(region.written_after_execution && region.executed_after_write);
+}
+
+#[test]
+fn writes_to_data_are_not_code_modifications() {
+    //  wmem 100 7, halt
+    let engine = run_tracked(&[16, 100, 7, 0], None);
+    assert!(engine.get_code_tracker().unwrap().get_modified_regions().is_empty());
+}
+
+#[test]
+fn the_image_is_dumped_when_written_code_first_runs() {
+    let directory = env::temp_dir().join(format!("synacorvm-code-tracker-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    run_tracked(&REWRITES_ITSELF, Some(directory.clone()));
+    let image = fs::read(directory.join("decrypted_0.bin")).unwrap();
+    assert_eq!(image.len(), 65536);
+    assert_eq!(&image[..4], &[0, 0, 16, 0]);
+    fs::remove_dir_all(directory).ok();
+}
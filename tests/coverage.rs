@@ -0,0 +1,57 @@
+//  Code coverage over executed opcodes, operands and conditional jumps
+use synacorvm::engine::svm_engine::SVMEngine;
+use synacorvm::engine::svm_program::SVMProgram;
+use synacorvm::engine::svm_symbols::SVMSymbols;
+use synacorvm_core::opcode::ExtensionSet;
+
+//  jt 1 7, out 'x', halt, then at 7: jf 0 11 over a halt to a halt, then data and an out that never runs
+fn run_covered() -> SVMEngine {
+    let mut engine = SVMEngine::new(SVMProgram::from_words(vec![7, 1, 7, 19, 120, 0, 0, 8, 0, 11, 0, 0, 9999, 19, 65]));
+    engine.detach_console();
+    engine.enable_coverage();
+    engine.run();
+    engine
+}
+
+#[test]
+fn opcodes_and_operands_are_recorded() {
+    let engine = run_covered();
+    let coverage = engine.get_coverage().unwrap();
+    assert!(coverage.is_executed(0) && coverage.is_executed(7) && coverage.is_executed(11));
+    assert!(coverage.is_operand(1) && coverage.is_operand(2) && coverage.is_operand(8));
+    assert!(!coverage.is_executed(1) && !coverage.is_executed(3) && !coverage.is_executed(10));
+    assert_eq!(coverage.get_code_ranges(15), vec![(0, 3), (7, 10), (11, 12)]);
+}
+
+#[test]
+fn branches_record_their_direction() {
+    let engine = run_covered();
+    let branches = engine.get_coverage().unwrap().get_branches();
+    assert_eq!((branches[&0].taken, branches[&0].not_taken), (1, 0));
+    assert_eq!((branches[&7].taken, branches[&7].not_taken), (1, 0));
+}
+
+#[test]
+fn the_report_marks_unexecuted_code_and_data() {
+    let engine = run_covered();
+    let coverage = engine.get_coverage().unwrap();
+    let memory = &engine.get_state().memory;
+    let end = coverage.get_end(memory);
+    assert_eq!(end, 15);
+
+    let mut report = Vec::new();
+    coverage.write_report(&mut report, memory, end, &SVMSymbols::new(), ExtensionSet::STRICT).unwrap();
+    let report = String::from_utf8(report).unwrap();
+    let lines: Vec<&str> = report.lines().collect();
+    assert_eq!(lines[0], "+                0: jt 1 7  [never falls through]");
+    assert_eq!(lines[1], "-                3: out 'x'");
+    assert_eq!(lines[4], "+                7: jf 0 11  [never falls through]");
+    assert_eq!(lines[5], "-               10: halt");
+    assert_eq!(lines[6], "+               11: halt");
+    assert_eq!(lines[7], "                12: data 9999");
+    assert_eq!(lines[8], "-               13: out 'A'");
+
+    let summary = coverage.get_summary(end);
+    assert_eq!(summary.get("executed_instructions").and_then(|value| value.as_u64()), Some(3));
+    assert_eq!(summary.get("partial_branches").and_then(|value| value.as_array()).map(|branches| branches.len()), Some(2));
+}
@@ -0,0 +1,190 @@
+//  The Debug Adapter Protocol server driven by framed requests the way an editor sends them
+use std::io::{BufRead, BufReader, Cursor, Read};
+use std::path::PathBuf;
+use synacorvm::engine::svm_dap_server::SVMDapServer;
+use synacorvm::engine::svm_json::JsonValue;
+
+fn data_path(name: &str) -> String {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("tests");
+    path.push("data");
+    path.push(name);
+    path.to_string_lossy().into_owned()
+}
+
+//  Frames the requests, one JSON object per line, and returns everything the server sent back
+fn run_session(requests: &str) -> Vec<JsonValue> {
+    let mut input = Vec::new();
+    for request in requests.lines().filter(|line| !line.trim().is_empty()) {
+        let request = request.replace("${PROGRAM}", &data_path("countdown.asm")).replace("${SPIN}", &data_path("spin.asm"));
+        input.extend_from_slice(format!("Content-Length: {}\r\n\r\n{}", request.len(), request).as_bytes());
+    }
+    let mut output = Vec::new();
+    SVMDapServer::new().serve(&mut Cursor::new(input), &mut output).unwrap();
+
+    let mut reader = BufReader::new(Cursor::new(output));
+    let mut messages = Vec::new();
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).unwrap() == 0 {
+            break;
+        }
+        let length: usize = header.trim().strip_prefix("Content-Length: ").unwrap().parse().unwrap();
+        let mut separator = String::new();
+        reader.read_line(&mut separator).unwrap();
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        messages.push(JsonValue::parse(std::str::from_utf8(&body).unwrap()).unwrap());
+    }
+    messages
+}
+
+fn text<'a>(message: &'a JsonValue, path: &[&str]) -> &'a str {
+    lookup(message, path).as_str().unwrap()
+}
+
+fn lookup<'a>(message: &'a JsonValue, path: &[&str]) -> &'a JsonValue {
+    path.iter().fold(message, |value, key| value.get(key).unwrap_or_else(|| panic!("missing {} in {}", key, message)))
+}
+
+//  A short description of each message, the command of a response or the event name with its reason
+fn summarize(messages: &[JsonValue]) -> Vec<String> {
+    messages.iter().map(|message| match text(message, &["type"]) {
+        "response" => format!("response {}", text(message, &["command"])),
+        _ => match message.get("body").and_then(|body| body.get("reason")) {
+            Some(reason) => format!("event {} {}", text(message, &["event"]), reason.as_str().unwrap()),
+            None => format!("event {}", text(message, &["event"])),
+        },
+    }).collect()
+}
+
+fn find_response(messages: &[JsonValue], request_sequence: u64) -> &JsonValue {
+    messages.iter()
+        .find(|message| message.get("request_seq").and_then(|sequence| sequence.as_u64()) == Some(request_sequence))
+        .unwrap()
+}
+
+#[test]
+fn replays_a_recorded_session() {
+    let requests = std::fs::read_to_string(data_path("dap_session.jsonl")).unwrap();
+    let messages = run_session(&requests);
+    assert!(messages.iter().all(|message| message.get("success").and_then(|success| success.as_bool()) != Some(false)));
+    assert_eq!(summarize(&messages), vec![
+        "response initialize", "event initialized", "response launch", "response setBreakpoints",
+        "response setInstructionBreakpoints", "response configurationDone", "event stopped entry",
+        "response threads", "response continue", "event stopped breakpoint", "response stackTrace",
+        "response scopes", "response variables", "response variables", "response evaluate",
+        "response setBreakpoints", "response stepOut", "event output", "event stopped step", "response next",
+        "event stopped step", "response continue", "event output", "event output", "response evaluate",
+        "event continued", "event output", "event exited", "event terminated", "response disconnect",
+        "event terminated",
+    ]);
+
+    let breakpoints = lookup(find_response(&messages, 3), &["body", "breakpoints"]).as_array().unwrap();
+    assert_eq!(lookup(&breakpoints[0], &["verified"]).as_bool(), Some(true));
+    assert_eq!(text(&breakpoints[0], &["instructionReference"]), "21");
+    assert_eq!(lookup(&breakpoints[1], &["verified"]).as_bool(), Some(false));
+
+    let frames = lookup(find_response(&messages, 8), &["body", "stackFrames"]).as_array().unwrap();
+    assert_eq!(frames.len(), 2);
+    assert_eq!(lookup(&frames[0], &["line"]).as_u64(), Some(13));
+    assert_eq!(text(&frames[0], &["source", "name"]), "countdown.asm");
+    assert_eq!(lookup(&frames[1], &["line"]).as_u64(), Some(4));
+
+    let registers = lookup(find_response(&messages, 10), &["body", "variables"]).as_array().unwrap();
+    assert_eq!(text(&registers[0], &["name"]), "ip");
+    assert_eq!(text(&registers[2], &["value"]), "51 (0x0033)");
+    let stack = lookup(find_response(&messages, 11), &["body", "variables"]).as_array().unwrap();
+    assert_eq!(text(&stack[0], &["value"]), "5 (return to loop+2)");
+    assert_eq!(text(find_response(&messages, 12), &["body", "result"]), "3 (0x0003)");
+
+    let output: Vec<&str> = messages.iter()
+        .filter(|message| message.get("event").and_then(|event| event.as_str()) == Some("output"))
+        .filter(|message| text(message, &["body", "category"]) == "stdout")
+        .map(|message| text(message, &["body", "output"]))
+        .collect();
+    assert_eq!(output.concat(), "3\n2\n1\nx");
+}
+
+#[test]
+fn stops_at_instruction_breakpoints_and_disassembles() {
+    let messages = run_session(r#"
+{"command":"initialize","arguments":{"adapterID":"synacorvm"},"type":"request","seq":1}
+{"command":"launch","arguments":{"program":"${PROGRAM}"},"type":"request","seq":2}
+{"command":"setInstructionBreakpoints","arguments":{"breakpoints":[{"instructionReference":"print_digit","offset":4},{"instructionReference":"nowhere"}]},"type":"request","seq":3}
+{"command":"configurationDone","type":"request","seq":4}
+{"command":"evaluate","arguments":{"expression":"pc","context":"hover"},"type":"request","seq":5}
+{"command":"disassemble","arguments":{"memoryReference":"print_digit","instructionOffset":0,"instructionCount":3},"type":"request","seq":6}
+{"command":"variables","arguments":{"variablesReference":1000},"type":"request","seq":7}
+{"command":"pause","arguments":{"threadId":1},"type":"request","seq":8}
+{"command":"unknownRequest","type":"request","seq":9}
+{"command":"disconnect","type":"request","seq":10}
+"#);
+    let breakpoints = lookup(find_response(&messages, 3), &["body", "breakpoints"]).as_array().unwrap();
+    assert_eq!(text(&breakpoints[0], &["instructionReference"]), "21");
+    assert_eq!(lookup(&breakpoints[1], &["verified"]).as_bool(), Some(false));
+    assert!(summarize(&messages).contains(&"event stopped breakpoint".to_string()));
+    assert_eq!(text(find_response(&messages, 5), &["body", "result"]), "21 (0x0015)");
+
+    let instructions = lookup(find_response(&messages, 6), &["body", "instructions"]).as_array().unwrap();
+    let listing: Vec<&str> = instructions.iter().map(|instruction| text(instruction, &["instruction"])).collect();
+    assert_eq!(listing, vec!["add r1 r0 48", "out r1", "out '\\n'"]);
+    assert_eq!(lookup(&instructions[0], &["line"]).as_u64(), Some(12));
+
+    let memory = lookup(find_response(&messages, 7), &["body", "variables"]).as_array().unwrap();
+    assert_eq!(memory.len(), 256);
+    assert_eq!(text(&memory[0], &["value"]), "1 (0x0001)");
+
+    assert_eq!(lookup(find_response(&messages, 8), &["success"]).as_bool(), Some(false));
+    assert_eq!(lookup(find_response(&messages, 9), &["success"]).as_bool(), Some(false));
+}
+
+#[test]
+fn next_steps_over_calls_and_step_in_enters_them() {
+    let messages = run_session(r#"
+{"command":"initialize","arguments":{"adapterID":"synacorvm"},"type":"request","seq":1}
+{"command":"launch","arguments":{"program":"${PROGRAM}","stopOnEntry":true},"type":"request","seq":2}
+{"command":"configurationDone","type":"request","seq":3}
+{"command":"next","arguments":{"threadId":1},"type":"request","seq":4}
+{"command":"next","arguments":{"threadId":1},"type":"request","seq":5}
+{"command":"stackTrace","arguments":{"threadId":1},"type":"request","seq":6}
+{"command":"next","arguments":{"threadId":1},"type":"request","seq":7}
+{"command":"next","arguments":{"threadId":1},"type":"request","seq":8}
+{"command":"stepIn","arguments":{"threadId":1},"type":"request","seq":9}
+{"command":"stackTrace","arguments":{"threadId":1},"type":"request","seq":10}
+{"command":"disconnect","type":"request","seq":11}
+"#);
+    assert_eq!(summarize(&messages), vec![
+        "response initialize", "event initialized", "response launch", "response configurationDone",
+        "event stopped entry", "response next", "event stopped step", "response next", "event output",
+        "event stopped step", "response stackTrace", "response next", "event stopped step", "response next",
+        "event stopped step", "response stepIn", "event stopped step", "response stackTrace",
+        "response disconnect", "event terminated",
+    ]);
+
+    let frames = lookup(find_response(&messages, 6), &["body", "stackFrames"]).as_array().unwrap();
+    assert_eq!(frames.len(), 1);
+    assert_eq!(lookup(&frames[0], &["line"]).as_u64(), Some(5));
+    let frames = lookup(find_response(&messages, 10), &["body", "stackFrames"]).as_array().unwrap();
+    assert_eq!(frames.len(), 2);
+    assert_eq!(lookup(&frames[0], &["line"]).as_u64(), Some(12));
+}
+
+#[test]
+fn pause_stops_a_running_program() {
+    let messages = run_session(r#"
+{"command":"initialize","arguments":{"adapterID":"synacorvm"},"type":"request","seq":1}
+{"command":"launch","arguments":{"program":"${SPIN}"},"type":"request","seq":2}
+{"command":"configurationDone","type":"request","seq":3}
+{"command":"pause","arguments":{"threadId":1},"type":"request","seq":4}
+{"command":"evaluate","arguments":{"expression":"pc","context":"hover"},"type":"request","seq":5}
+{"command":"pause","arguments":{"threadId":1},"type":"request","seq":6}
+{"command":"disconnect","type":"request","seq":7}
+"#);
+    assert_eq!(summarize(&messages), vec![
+        "response initialize", "event initialized", "response launch", "response configurationDone",
+        "response pause", "event stopped pause", "response evaluate", "response pause", "response disconnect",
+        "event terminated",
+    ]);
+    assert_eq!(lookup(find_response(&messages, 6), &["success"]).as_bool(), Some(false));
+}
@@ -0,0 +1,15 @@
+; Counts down from 3, then echoes the first character of a line of input
+function main:
+        set r0 3
+loop:   call print_digit
+        add r0 r0 32767         ; r0 - 1
+        jt r0 loop
+        in r1
+        out r1
+        halt
+
+function print_digit:
+        add r1 r0 '0'
+        out r1
+        out '\n'
+        ret
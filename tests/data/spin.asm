@@ -0,0 +1,4 @@
+; Counts in r0 forever, until a debugger pauses it
+function main:
+loop:   add r0 r0 1
+        jmp loop
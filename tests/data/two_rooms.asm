@@ -0,0 +1,110 @@
+; A two-room adventure: take the key in the hall, go north and use it in the vault.
+; r5 is the room (0 hall, 1 vault), r6 is set once the key is carried.
+function main:
+        set r5 0
+        set r6 0
+        call describe
+prompt: set r0 0
+        set r1 0
+        set r2 0
+        set r3 0
+        set r4 0
+        set r7 ask
+        call print
+; r0 is the first character of the line and r1 the fourth
+read:   in r3
+        eq r4 r3 '\n'
+        jt r4 dispatch
+        jt r2 second
+        set r0 r3
+second: eq r4 r2 3
+        jf r4 next
+        set r1 r3
+next:   add r2 r2 1
+        jmp read
+
+dispatch:
+        eq r4 r0 'l'
+        jt r4 look
+        eq r4 r0 'i'
+        jt r4 inventory
+        eq r4 r0 'g'
+        jt r4 go
+        eq r4 r0 't'
+        jt r4 take
+        eq r4 r0 'u'
+        jt r4 use
+        jmp nothing
+look:   call describe
+        jmp prompt
+inventory:
+        set r7 inventory_text
+        call print
+        jf r6 prompt
+        set r7 key_item
+        call print
+        jmp prompt
+go:     jt r5 go_south
+        eq r4 r1 'n'
+        jf r4 nothing
+        set r5 1
+        call describe
+        jmp prompt
+go_south:
+        eq r4 r1 's'
+        jf r4 nothing
+        set r5 0
+        call describe
+        jmp prompt
+take:   jt r5 nothing
+        jt r6 nothing
+        set r6 1
+        set r7 taken
+        call print
+        jmp prompt
+use:    jf r6 nothing
+        jf r5 nothing
+        set r7 win
+        call print
+        halt
+nothing:
+        set r7 nothing_text
+        call print
+        jmp prompt
+
+function describe:
+        jt r5 describe_vault
+        set r7 hall
+        call print
+        jt r6 hall_exits
+        set r7 hall_items
+        call print
+hall_exits:
+        set r7 hall_exit
+        call print
+        ret
+describe_vault:
+        set r7 vault
+        call print
+        ret
+
+; Prints the zero-terminated string at r7
+function print:
+        rmem r3 r7
+        jf r3 print_done
+        out r3
+        add r7 r7 1
+        jmp print
+print_done:
+        ret
+
+ask:            data "What do you do?\n" 0
+hall:           data "\n== Hall ==\nA dusty hall.\n\n" 0
+hall_items:     data "Things of interest here:\n- key\n\n" 0
+hall_exit:      data "There is 1 exit:\n- north\n\n" 0
+vault:          data "\n== Vault ==\nA locked door.\n\nThere is 1 exit:\n- south\n\n" 0
+inventory_text: data "\nYour inventory:\n" 0
+key_item:       data "- key\n" 0
+taken:          data "Taken.\n" 0
+win:            data "The door opens. You win!\n" 0
+nothing_text:   data "Nothing happens.\n" 0
@@ -0,0 +1,94 @@
+//  Devices mapped over memory and reached through rmem and wmem
+use synacorvm::engine::svm_devices::{create_device, CycleCounter, RandomSource, SVMDeviceMap, NO_INPUT};
+use synacorvm::engine::svm_engine::{SVMEngine, SVMTermination};
+use synacorvm::engine::svm_program::SVMProgram;
+use synacorvm_core::svm_error::SVMError;
+
+const R0: u16 = 32768;
+const R1: u16 = 32769;
+const R2: u16 = 32770;
+
+fn run_with_device(words: Vec<u16>, name: &str, input: &[u8]) -> (SVMEngine, SVMTermination) {
+    let mut engine = SVMEngine::new(SVMProgram::from_words(words));
+    engine.detach_console();
+    engine.map_device(1000, create_device(name).unwrap()).unwrap();
+    engine.push_input(input);
+    let termination = engine.run();
+    (engine, termination)
+}
+
+fn register(engine: &SVMEngine, index: usize) -> u16 {
+    engine.get_state().registers.get_register_by_index(index)
+}
+
+#[test]
+fn the_cycle_counter_latches_on_its_first_word() {
+    //  noop, noop, rmem r0 1000, rmem r1 1001, rmem r2 1002, halt
+    let (engine, termination) = run_with_device(vec![21, 21, 15, R0, 1000, 15, R1, 1001, 15, R2, 1002, 0], "cycles", b"");
+    assert!(matches!(termination, SVMTermination::Halted));
+    assert_eq!((register(&engine, 0), register(&engine, 1), register(&engine, 2)), (2, 0, 0));
+}
+
+#[test]
+fn the_cycle_counter_is_read_only() {
+    //  wmem 1000 1
+    let (engine, termination) = run_with_device(vec![16, 1000, 1, 0], "cycles", b"");
+    assert!(matches!(termination, SVMTermination::Error(SVMError::InvalidMemory)));
+    assert_eq!(engine.get_state().memory.load_memory(1000).ok(), Some(0));
+}
+
+#[test]
+fn reseeding_the_random_source_repeats_its_numbers() {
+    //  wmem 1000 42, rmem r0 1000, rmem r1 1000, wmem 1000 42, rmem r2 1000, halt
+    let words = vec![16, 1000, 42, 15, R0, 1000, 15, R1, 1000, 16, 1000, 42, 15, R2, 1000, 0];
+    let (engine, _) = run_with_device(words, "random", b"");
+    assert_eq!(register(&engine, 0), register(&engine, 2));
+    assert_ne!(register(&engine, 0), register(&engine, 1));
+    assert!((0..3).all(|index| register(&engine, index) < 32768));
+}
+
+#[test]
+fn a_fork_keeps_its_devices_in_their_state() {
+    //  wmem 1000 42, rmem r0 1000, halt
+    let mut engine = SVMEngine::new(SVMProgram::from_words(vec![16, 1000, 42, 15, R0, 1000, 0]));
+    engine.detach_console();
+    engine.map_device(1000, create_device("random").unwrap()).unwrap();
+    engine.step().unwrap();
+    let mut fork = engine.clone();
+    fork.detach_console();
+    assert!(matches!(engine.run(), SVMTermination::Halted));
+    assert!(matches!(fork.run(), SVMTermination::Halted));
+    assert_eq!(fork.get_devices().unwrap().get_mappings(), vec![(1000, 1, "random".to_string())]);
+    assert_eq!(register(&fork, 0), register(&engine, 0));
+    assert_eq!(fork.get_state().memory.load_memory(1000).ok(), Some(0));
+}
+
+#[test]
+fn the_console_device_writes_output_and_polls_input() {
+    //  wmem 1000 'h', wmem 1000 'i', rmem r0 1000, rmem r1 1000, halt
+    let words = vec![16, 1000, 104, 16, 1000, 105, 15, R0, 1000, 15, R1, 1000, 0];
+    let (mut engine, _) = run_with_device(words, "console", b"x");
+    assert_eq!(engine.take_output(), b"hi");
+    assert_eq!(engine.get_state().output_bytes, 2);
+    assert_eq!((register(&engine, 0), register(&engine, 1)), (b'x' as u16, NO_INPUT));
+}
+
+#[test]
+fn unmapped_addresses_are_still_memory() {
+    //  wmem 2000 5, rmem r0 2000, halt
+    let (engine, _) = run_with_device(vec![16, 2000, 5, 15, R0, 2000, 0], "random", b"");
+    assert_eq!(register(&engine, 0), 5);
+    assert_eq!(engine.get_state().memory.load_memory(2000).ok(), Some(5));
+}
+
+#[test]
+fn devices_cannot_overlap_or_run_past_memory() {
+    let mut devices = SVMDeviceMap::new();
+    devices.map(10, Box::new(CycleCounter::new())).unwrap();
+    assert!(devices.map(12, Box::new(RandomSource::new(1))).is_err());
+    assert!(devices.map(9, Box::new(CycleCounter::new())).is_err());
+    devices.map(13, Box::new(RandomSource::new(1))).unwrap();
+    assert!(devices.map(32766, Box::new(CycleCounter::new())).is_err());
+    assert_eq!(devices.get_mappings(), vec![(10, 3, "cycles".to_string()), (13, 1, "random".to_string())]);
+    assert!(create_device("disk").is_none());
+}
@@ -0,0 +1,16 @@
+//  Runs random programs in the engine and the reference interpreter side by side
+use synacorvm::engine::svm_differential::{run_differential, CaseGenerator};
+
+const CASES: u64 = 500;
+
+#[test]
+fn engine_matches_the_reference_on_random_programs() {
+    for seed in 0..CASES {
+        let mut generator = CaseGenerator::new(seed);
+        let program = generator.generate_program(40);
+        let input = generator.generate_input(8);
+        if let Err(divergence) = run_differential(&program, &input, 2000) {
+            panic!("seed {} diverged at {}\nprogram: {:?}", seed, divergence, program);
+        }
+    }
+}
@@ -0,0 +1,49 @@
+//  Breadth-first exploration of a toy text adventure
+use std::fs;
+use std::path::PathBuf;
+use synacorvm::engine::svm_assembler::SVMAssembly;
+use synacorvm::engine::svm_engine::SVMEngine;
+use synacorvm::engine::svm_explorer::{parse_room, ExplorerOptions, SVMExplorer};
+use synacorvm_core::opcode::ExtensionSet;
+
+fn explore_two_rooms() -> SVMExplorer {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("tests/data/two_rooms.asm");
+    let source = fs::read_to_string(path).unwrap();
+    let assembly = SVMAssembly::assemble(&source, ExtensionSet::STRICT).unwrap_or_else(|error| panic!("{}", error));
+    let mut explorer = SVMExplorer::new(ExplorerOptions::default());
+    explorer.explore(SVMEngine::new(assembly.get_program()));
+    explorer
+}
+
+#[test]
+fn the_shortest_winning_input_is_found() {
+    let explorer = explore_two_rooms();
+    let endings = explorer.get_endings();
+    assert_eq!(endings.len(), 1);
+    assert_eq!(endings[0].commands, vec!["take key", "go north", "use key"]);
+    assert_eq!(endings[0].reason, "halted");
+    assert!(endings[0].output.contains("You win!"));
+}
+
+#[test]
+fn rooms_and_exits_are_mapped() {
+    let explorer = explore_two_rooms();
+    let names: Vec<&str> = explorer.get_rooms().iter().map(|room| room.name.as_str()).collect();
+    assert_eq!(names, vec!["Hall", "Vault"]);
+    assert_eq!(explorer.get_states().len(), 4);
+
+    let mut dot = Vec::new();
+    explorer.write_dot(&mut dot).unwrap();
+    let dot = String::from_utf8(dot).unwrap();
+    assert!(dot.contains("room0 -> room1 [label=\"north\"];"));
+    assert!(dot.contains("room1 -> room0 [label=\"south\"];"));
+}
+
+#[test]
+fn a_room_description_is_parsed() {
+    let room = parse_room("Taken.\n\n== Hall ==\nA dusty hall.\n\nThings of interest here:\n- key\n\nThere is 1 exit:\n- north\n").unwrap();
+    assert_eq!((room.name.as_str(), room.description.as_str()), ("Hall", "A dusty hall."));
+    assert_eq!((room.items, room.exits), (vec!["key".to_string()], vec!["north".to_string()]));
+    assert!(parse_room("Nothing happens.\n").is_none());
+}
@@ -0,0 +1,44 @@
+//  The report printed when a program faults
+use synacorvm::engine::svm_assembler::SVMAssembly;
+use synacorvm::engine::svm_engine::{SVMEngine, SVMTermination};
+use synacorvm_core::opcode::ExtensionSet;
+
+const DIVIDES_BY_ZERO: &str = "function main:
+set r0 5
+push r0
+call divide
+halt
+function divide:
+mod r1 r0 r2
+ret";
+
+#[test]
+fn a_fault_is_reported_with_its_context() {
+    let assembly = SVMAssembly::assemble(DIVIDES_BY_ZERO, ExtensionSet::STRICT).unwrap();
+    let mut engine = SVMEngine::new(assembly.get_program());
+    engine.detach_console();
+    engine.set_symbols(assembly.get_symbols());
+    let error = match engine.run() {
+        SVMTermination::Error(error) => error,
+        _ => panic!("the program should fault"),
+    };
+    assert_eq!(engine.get_fault_report(&error), vec![
+        "Error at instruction: divide (8), opcode: 11 ",
+        "Division by zero",
+        "",
+        "Faulting instruction:  mod r1 r0 r2",
+        "Operands:  r1 = 0, r0 = 5, r2 = 0",
+        "Registers:  r0=5 r1=0 r2=0 r3=0 r4=0 r5=0 r6=0 r7=0",
+        "Stack depth 2",
+        "  [1]: 7 <- return to main+7",
+        "  [0]: 5",
+        "Call stack:",
+        "  #0             divide in divide",
+        "  #1             main+5 in <root>",
+        "Last 4 instructions:",
+        "              main: set r0 5",
+        "            main+3: push r0",
+        "            main+5: call divide",
+        "            divide: mod r1 r0 r2",
+    ]);
+}
@@ -0,0 +1,55 @@
+//  Forked engines share memory pages until one side writes to them
+use synacorvm::engine::svm_assembler::SVMAssembly;
+use synacorvm::engine::svm_engine::{SVMEngine, SVMTermination};
+use synacorvm_core::opcode::ExtensionSet;
+
+//  32768 words of memory in 256-word pages
+const NUM_OF_PAGES: usize = 128;
+
+fn start(source: &str) -> SVMEngine {
+    let assembly = SVMAssembly::assemble(source, ExtensionSet::STRICT).unwrap_or_else(|error| panic!("{}", error));
+    let mut engine = SVMEngine::new(assembly.get_program());
+    engine.detach_console();
+    engine
+}
+
+#[test]
+fn a_write_in_the_fork_is_invisible_to_the_parent() {
+    let parent = start("wmem value 9\nhalt\nvalue: data 1");
+    let mut fork = parent.clone();
+    fork.detach_console();
+    assert!(matches!(fork.run(), SVMTermination::Halted));
+
+    assert_eq!(fork.get_state().memory.load_memory(4).ok(), Some(9));
+    assert_eq!(parent.get_state().memory.load_memory(4).ok(), Some(1));
+    assert_eq!(parent.get_state().instruction_count, 0);
+}
+
+#[test]
+fn unmodified_pages_stay_shared() {
+    let parent = start("wmem 1000 7\nhalt");
+    assert_eq!(parent.get_state().memory.get_shared_page_count(), NUM_OF_PAGES - 1);
+
+    let mut fork = parent.clone();
+    fork.detach_console();
+    assert_eq!(parent.get_state().memory.get_shared_page_count(), NUM_OF_PAGES);
+    assert!(matches!(fork.run(), SVMTermination::Halted));
+
+    //  Only the zero page under address 1000 was copied, and only in the fork
+    assert_eq!(fork.get_state().memory.get_shared_page_count(), NUM_OF_PAGES - 1);
+    assert_eq!(parent.get_state().memory.get_shared_page_count(), NUM_OF_PAGES);
+    assert_eq!(parent.get_state().memory.load_memory(1000).ok(), Some(0));
+}
+
+#[test]
+fn pages_shared_with_a_fork_are_counted_by_pointer() {
+    let parent = start("wmem 1000 7\nhalt");
+    let mut fork = parent.clone();
+    fork.detach_console();
+    let unrelated = start("wmem 1000 7\nhalt");
+    assert_eq!(fork.get_state().memory.get_pages_shared_with(&parent.get_state().memory), NUM_OF_PAGES);
+    assert_eq!(unrelated.get_state().memory.get_pages_shared_with(&parent.get_state().memory), 0);
+
+    assert!(matches!(fork.run(), SVMTermination::Halted));
+    assert_eq!(fork.get_state().memory.get_pages_shared_with(&parent.get_state().memory), NUM_OF_PAGES - 1);
+}
@@ -0,0 +1,83 @@
+//  Minimized inputs that made the fuzz targets panic, plus a quick random run
+//  of the same targets for builds without the fuzzer
+use std::io::Cursor;
+use synacorvm::engine::svm_engine::SVMEngine;
+use synacorvm::engine::svm_fuzz::{fuzz_execute, fuzz_load};
+use synacorvm::engine::svm_program::SVMProgram;
+use synacorvm::engine::svm_snapshot::{read_snapshot, write_snapshot, SNAPSHOT_MAGIC};
+use synacorvm_core::svm_engine_state::SVMEngineState;
+
+//  Input length 0 followed by the program words
+fn execute_words(words: &[u16]) {
+    let mut data = vec![0];
+    for word in words {
+        data.extend_from_slice(&word.to_le_bytes());
+    }
+    fuzz_execute(&data);
+}
+
+#[test]
+fn odd_length_images_load() {
+    fuzz_load(&[0]);
+    fuzz_load(&[0x13, 0x00, 0x41]);
+    let engine = SVMEngine::new(SVMProgram::from_bytes(&[0x13, 0x00, 0x41]));
+    assert_eq!(engine.get_state().memory.load_memory(1).unwrap(), 65);
+}
+
+#[test]
+fn add_with_registers_loaded_from_memory_does_not_overflow() {
+    //  rmem r0 7, add r1 r0 r0, then 65535 as data
+    execute_words(&[15, 32768, 7, 9, 32769, 32768, 32768, 65535]);
+}
+
+#[test]
+fn mod_by_zero_does_not_panic() {
+    execute_words(&[11, 32768, 1, 0]);
+}
+
+#[test]
+fn code_at_the_last_address_runs() {
+    //  jmp 32767, where a noop runs and the fetch after it fails cleanly
+    let mut words = vec![0; 32768];
+    words[..2].copy_from_slice(&[6, 32767]);
+    words[32767] = 21;
+    execute_words(&words);
+}
+
+#[test]
+fn snapshots_keep_the_last_word_of_memory() {
+    let mut words = vec![0; 32768];
+    words[32767] = 1234;
+    let mut data = Vec::new();
+    write_snapshot(&SVMEngineState::new(&words), &mut data).unwrap();
+    let state = read_snapshot(&mut Cursor::new(data)).unwrap();
+    assert_eq!(state.memory.load_memory(32767).unwrap(), 1234);
+}
+
+#[test]
+fn other_snapshot_versions_are_rejected() {
+    for version in [1u16, 2, 4] {
+        let mut data = SNAPSHOT_MAGIC.to_vec();
+        data.extend_from_slice(&version.to_le_bytes());
+        data.extend_from_slice(&vec![0; 2 + 2 + 16 + 4 + 32768 * 2 + 4]);
+        assert!(read_snapshot(&mut Cursor::new(data)).is_err());
+    }
+}
+
+#[test]
+fn random_data_does_not_panic() {
+    let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+    for _ in 0..300 {
+        let mut data = Vec::new();
+        let length = (state % 200) as usize;
+        for _ in 0..length {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            //  Keep most words small so the programs get past their first instruction
+            data.push(if state.is_multiple_of(4) { (state >> 8) as u8 } else { (state >> 8) as u8 % 22 });
+        }
+        fuzz_load(&data);
+        fuzz_execute(&data);
+    }
+}
@@ -0,0 +1,207 @@
+//  The GDB remote stub driven over a socket the way gdb drives it
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use synacorvm::engine::svm_engine::SVMEngine;
+use synacorvm::engine::svm_gdb_stub::SVMGdbStub;
+use synacorvm::engine::svm_program::SVMProgram;
+
+const R0: u16 = 32768;
+const R1: u16 = 32769;
+const R2: u16 = 32770;
+
+//  0: set r0 5
+//  3: add r1 r0 1
+//  7: wmem 100 r1
+// 10: rmem r2 100
+// 13: halt
+const PROGRAM: [u16; 14] = [1, R0, 5, 9, R1, R0, 1, 16, 100, R1, 15, R2, 100, 0];
+
+struct Client {
+    stream: TcpStream,
+    server: Option<JoinHandle<()>>,
+}
+
+impl Client {
+    fn start() -> Client {
+        Client::start_with(&PROGRAM)
+    }
+
+    fn start_with(program: &[u16]) -> Client {
+        let bytes: Vec<u8> = program.iter().flat_map(|word| word.to_le_bytes()).collect();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.set_nodelay(true).unwrap();
+            let mut engine = SVMEngine::new(SVMProgram::from_bytes(&bytes));
+            engine.detach_console();
+            let mut stub = SVMGdbStub::new(engine);
+            stub.serve(&mut stream).unwrap();
+        });
+        let stream = TcpStream::connect(address).unwrap();
+        stream.set_nodelay(true).unwrap();
+        Client {
+            stream,
+            server: Some(server),
+        }
+    }
+
+    fn send(&mut self, data: &str) -> String {
+        let checksum = data.bytes().fold(0u8, |sum, byte| sum.wrapping_add(byte));
+        self.stream.write_all(format!("${}#{:02x}", data, checksum).as_bytes()).unwrap();
+        assert_eq!(self.read_byte(), b'+');
+        self.read_reply()
+    }
+
+    fn read_reply(&mut self) -> String {
+        assert_eq!(self.read_byte(), b'$');
+        let mut reply = Vec::new();
+        let mut checksum = 0u8;
+        loop {
+            let byte = self.read_byte();
+            if byte == b'#' {
+                break;
+            }
+            checksum = checksum.wrapping_add(byte);
+            reply.push(byte);
+        }
+        let expected = [self.read_byte(), self.read_byte()];
+        assert_eq!(u8::from_str_radix(std::str::from_utf8(&expected).unwrap(), 16).unwrap(), checksum);
+        self.stream.write_all(b"+").unwrap();
+        String::from_utf8(reply).unwrap()
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        let mut byte = [0; 1];
+        self.stream.read_exact(&mut byte).unwrap();
+        byte[0]
+    }
+
+    fn pc(&mut self) -> String {
+        self.send("p8")
+    }
+
+    fn finish(mut self) {
+        assert_eq!(self.send("D"), "OK");
+        self.server.take().unwrap().join().unwrap();
+    }
+}
+
+#[test]
+fn reads_registers_and_steps() {
+    let mut client = Client::start();
+    assert_eq!(client.send("?"), "S05");
+    assert_eq!(client.send("g"), "0000".repeat(9));
+    assert_eq!(client.send("s"), "S05");
+    assert_eq!(client.send("g"), format!("0500{}0600", "0000".repeat(7)));
+    assert_eq!(client.send("s"), "S05");
+    assert_eq!(client.send("p1"), "0600");
+    assert_eq!(client.pc(), "0e00");
+    client.finish();
+}
+
+#[test]
+fn writes_registers() {
+    let mut client = Client::start();
+    assert_eq!(client.send("P0=2a00"), "OK");
+    assert_eq!(client.send("p0"), "2a00");
+    assert_eq!(client.send("P8=0600"), "OK");
+    assert_eq!(client.send("s"), "S05");
+    assert_eq!(client.send("p1"), "2b00");
+    assert_eq!(client.send(&format!("G{}", "0100".repeat(8) + "1400")), "OK");
+    assert_eq!(client.send("p7"), "0100");
+    assert_eq!(client.pc(), "1400");
+    assert_eq!(client.send("P8=0300"), "E02");
+    assert_eq!(client.send("p9"), "E01");
+    client.finish();
+}
+
+#[test]
+fn reads_and_writes_memory_in_bytes() {
+    let mut client = Client::start();
+    assert_eq!(client.send("m0,6"), "010000800500");
+    assert_eq!(client.send("m1,3"), "000080");
+    assert_eq!(client.send("M4,2:0700"), "OK");
+    assert_eq!(client.send("m4,2"), "0700");
+    assert_eq!(client.send("s"), "S05");
+    assert_eq!(client.send("p0"), "0700");
+    client.finish();
+}
+
+#[test]
+fn stops_at_breakpoints() {
+    let mut client = Client::start();
+    assert_eq!(client.send("Z0,7,2"), "E01");
+    assert_eq!(client.send("Z0,e,2"), "OK");
+    assert_eq!(client.send("c"), "S05");
+    assert_eq!(client.pc(), "0e00");
+    //  The pc is a byte address like the ones memory is read at
+    assert_eq!(client.send("me,2"), "1000");
+    assert_eq!(client.send("z0,e,2"), "OK");
+    assert_eq!(client.send("c"), "W00");
+    assert_eq!(client.send("?"), "W00");
+    client.finish();
+}
+
+#[test]
+fn stops_at_watchpoints() {
+    let mut client = Client::start();
+    assert_eq!(client.send("Z2,c8,2"), "OK");
+    assert_eq!(client.send("c"), "T05watch:c8;");
+    assert_eq!(client.pc(), "1400");
+    assert_eq!(client.send("mc8,2"), "0600");
+    assert_eq!(client.send("z2,c8,2"), "OK");
+
+    assert_eq!(client.send("Z3,c8,2"), "OK");
+    assert_eq!(client.send("P8=0000"), "OK");
+    assert_eq!(client.send("c"), "T05rwatch:c8;");
+    assert_eq!(client.pc(), "1400");
+    assert_eq!(client.send("c"), "W00");
+    client.finish();
+}
+
+#[test]
+fn describes_the_target() {
+    let mut client = Client::start();
+    assert!(client.send("qSupported:multiprocess+").contains("qXfer:features:read+"));
+    let mut description = String::new();
+    loop {
+        let reply = client.send(&format!("qXfer:features:read:target.xml:{:x},80", description.len()));
+        description.push_str(&reply[1..]);
+        if reply.starts_with('l') {
+            break;
+        }
+        assert!(reply.starts_with('m'));
+    }
+    assert!(description.contains("<reg name=\"r0\" bitsize=\"16\""));
+    assert!(description.contains("<reg name=\"pc\" bitsize=\"16\" type=\"code_ptr\"/>"));
+    assert!(description.ends_with("</target>\n"));
+    assert_eq!(client.send("vMustReplyEmpty"), "");
+    client.finish();
+}
+
+#[test]
+fn an_interrupt_stops_a_program_in_a_loop() {
+    //  jmp 0
+    let mut client = Client::start_with(&[6, 0]);
+    client.stream.write_all(b"$c#63").unwrap();
+    assert_eq!(client.read_byte(), b'+');
+    thread::sleep(Duration::from_millis(50));
+    client.stream.write_all(&[0x03]).unwrap();
+    assert_eq!(client.read_reply(), "S02");
+    assert_eq!(client.pc(), "0000");
+    client.finish();
+}
+
+#[test]
+fn waiting_for_input_is_a_plain_stop() {
+    //  in r0, halt
+    let mut client = Client::start_with(&[20, R0, 0]);
+    assert_eq!(client.send("s"), "S05");
+    assert_eq!(client.pc(), "0000");
+    assert_eq!(client.send("c"), "S05");
+    assert_eq!(client.pc(), "0000");
+    client.finish();
+}
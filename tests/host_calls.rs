@@ -0,0 +1,128 @@
+//  The hcall extension opcode and the sandboxed services behind it
+#![cfg(feature = "host-calls")]
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use synacorvm::engine::svm_assembler::SVMAssembly;
+use synacorvm::engine::svm_engine::{SVMEngine, SVMTermination};
+use synacorvm::engine::svm_host_calls::HOST_CALL_FAILED;
+use synacorvm::engine::svm_program::SVMProgram;
+use synacorvm_core::opcode::ExtensionSet;
+use synacorvm_core::svm_error::SVMError;
+
+fn sandbox(name: &str) -> PathBuf {
+    let directory = env::temp_dir().join(format!("synacorvm-host-calls-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+fn run_with_host_calls(source: &str, sandbox: PathBuf) -> (SVMEngine, SVMTermination) {
+    let assembly = SVMAssembly::assemble(source, ExtensionSet::HOST_CALLS).unwrap_or_else(|error| panic!("{}", error));
+    let mut engine = SVMEngine::new(assembly.get_program());
+    engine.detach_console();
+    engine.enable_host_calls(sandbox);
+    let termination = engine.run();
+    (engine, termination)
+}
+
+fn register(engine: &SVMEngine, index: usize) -> u16 {
+    engine.get_state().registers.get_register_by_index(index)
+}
+
+#[test]
+fn files_written_in_the_sandbox_can_be_read_back() {
+    let directory = sandbox("files");
+    let source = "
+        set r0 2
+        set r1 name
+        set r2 1
+        hcall
+        set r1 r0
+        set r0 5
+        set r2 text
+        set r3 5
+        hcall
+        set r7 r0
+        set r0 3
+        hcall
+        set r0 2
+        set r1 name
+        set r2 0
+        hcall
+        set r1 r0
+        set r0 4
+        set r2 buffer
+        set r3 16
+        hcall
+        halt
+        name:   data \"notes.txt\" 0
+        text:   data \"hello\"
+        buffer: data 0
+    ";
+    let (engine, termination) = run_with_host_calls(source, directory.clone());
+    assert!(matches!(termination, SVMTermination::Halted));
+    assert_eq!(fs::read(directory.join("notes.txt")).unwrap(), b"hello");
+    assert_eq!((register(&engine, 7), register(&engine, 0)), (5, 5));
+    let buffer = register(&engine, 2);
+    let read: Vec<u16> = (buffer..buffer + 5).map(|address| engine.get_state().memory.load_memory(address).unwrap()).collect();
+    assert_eq!(read, b"hello".iter().map(|byte| *byte as u16).collect::<Vec<u16>>());
+    fs::remove_dir_all(directory).ok();
+}
+
+#[test]
+fn names_outside_the_sandbox_fail() {
+    let directory = sandbox("escape");
+    for name in ["../escape.txt", "/tmp/escape.txt", "", "a/../../escape.txt"].iter() {
+        let source = format!("set r0 2\nset r1 name\nset r2 1\nhcall\nhalt\nname: data \"{}\" 0", name);
+        let (engine, _) = run_with_host_calls(&source, directory.clone());
+        assert_eq!(register(&engine, 0), HOST_CALL_FAILED, "{} was opened", name);
+    }
+    assert!(!directory.parent().unwrap().join("escape.txt").exists());
+    fs::remove_dir_all(directory).ok();
+}
+
+#[test]
+fn exit_halts_with_a_status() {
+    let (engine, termination) = run_with_host_calls("set r0 0\nset r1 3\nhcall\nout 'x'\nhalt", sandbox("exit"));
+    assert!(matches!(termination, SVMTermination::Halted));
+    assert_eq!(engine.get_host_calls().and_then(|host_calls| host_calls.get_exit_status()), Some(3));
+    assert!(engine.get_state().output_buffer.is_empty());
+}
+
+#[test]
+fn bad_handles_fail_and_unknown_services_fault() {
+    let (engine, _) = run_with_host_calls("set r0 4\nset r1 9\nset r2 100\nset r3 1\nhcall\nhalt", sandbox("handles"));
+    assert_eq!(register(&engine, 0), HOST_CALL_FAILED);
+    let (_, termination) = run_with_host_calls("set r0 99\nhcall\nhalt", sandbox("unknown"));
+    assert!(matches!(termination, SVMTermination::Error(SVMError::InvalidOperand)));
+}
+
+#[test]
+fn strict_engines_reject_hcall_beside_engines_with_host_calls() {
+    let (host, termination) = run_with_host_calls("set r0 0\nset r1 3\nhcall", sandbox("strict"));
+    assert!(matches!(termination, SVMTermination::Halted));
+    assert_eq!(host.get_extensions(), ExtensionSet::HOST_CALLS);
+
+    let mut engine = SVMEngine::new(SVMProgram::from_words(vec![22, 0]));
+    engine.detach_console();
+    assert_eq!(engine.get_extensions(), ExtensionSet::STRICT);
+    assert!(matches!(engine.run(), SVMTermination::Error(SVMError::InvalidOpCode)));
+    assert!(SVMAssembly::assemble("hcall", ExtensionSet::STRICT).is_err());
+}
+
+#[test]
+fn forks_keep_host_calls_in_the_same_sandbox() {
+    let directory = sandbox("fork");
+    let assembly = SVMAssembly::assemble("set r0 0\nset r1 7\nhcall", ExtensionSet::HOST_CALLS).unwrap();
+    let mut engine = SVMEngine::new(assembly.get_program());
+    engine.detach_console();
+    engine.enable_host_calls(directory.clone());
+    let mut fork = engine.clone();
+    fork.detach_console();
+    assert!(matches!(fork.run(), SVMTermination::Halted));
+    let host_calls = fork.get_host_calls().unwrap();
+    assert_eq!(host_calls.get_sandbox(), directory.as_path());
+    assert_eq!(host_calls.get_exit_status(), Some(7));
+    assert_eq!(engine.get_host_calls().unwrap().get_exit_status(), None);
+    fs::remove_dir_all(directory).ok();
+}
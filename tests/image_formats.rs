@@ -0,0 +1,102 @@
+//  Program images other than raw words, and telling the formats apart
+use synacorvm::engine::svm_engine::SVMEngine;
+use synacorvm::engine::svm_image_formats::{detect_format, parse_intel_hex, ContainerImage, ImageFormat, Segment};
+use synacorvm::engine::svm_program::SVMProgram;
+
+fn run(program: SVMProgram) -> (SVMEngine, String) {
+    let mut engine = SVMEngine::new(program);
+    engine.detach_console();
+    engine.run();
+    let output = String::from_utf8(engine.take_output()).unwrap();
+    (engine, output)
+}
+
+//  An Intel HEX record with its checksum
+fn record(kind: u8, offset: u16, payload: &[u8]) -> String {
+    let mut bytes = vec![payload.len() as u8, (offset >> 8) as u8, offset as u8, kind];
+    bytes.extend_from_slice(payload);
+    let checksum = bytes.iter().fold(0u8, |sum, byte| sum.wrapping_sub(*byte));
+    bytes.push(checksum);
+    let digits: Vec<String> = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+    format!(":{}\n", digits.concat())
+}
+
+#[test]
+fn formats_are_detected_from_the_contents() {
+    assert_eq!(detect_format(&[0x13, 0x00, 0x41, 0x00]), ImageFormat::Raw);
+    assert_eq!(detect_format(b"SVMI\x01\x00"), ImageFormat::Container);
+    assert_eq!(detect_format(b"0013 0041\n0000\n"), ImageFormat::HexText);
+    assert_eq!(detect_format(b"\n:00000001FF\n"), ImageFormat::IntelHex);
+    assert_eq!(detect_format(b""), ImageFormat::Raw);
+}
+
+#[test]
+fn container_images_load_segments_registers_and_symbols() {
+    //  At 100: out r0, jmp 200; at 200: out 'k', halt
+    let image = ContainerImage {
+        entry_point: 100,
+        initial_registers: [b'o' as u16, 0, 0, 0, 0, 0, 0, 7],
+        segments: vec![
+            Segment { load_address: 100, words: vec![19, 32768, 6, 200] },
+            Segment { load_address: 200, words: vec![19, b'k' as u16, 0] },
+        ],
+        symbols: vec![(100, "start".to_string()), (200, "finish".to_string())],
+    };
+    let mut data = Vec::new();
+    image.write(&mut data).unwrap();
+
+    let program = SVMProgram::from_image(&data).unwrap();
+    assert_eq!(program.get_entry_point(), 100);
+    assert_eq!(program.get_symbols().unwrap().find_address("finish"), Some(200));
+    let (engine, output) = run(program);
+    assert_eq!(output, "ok");
+    assert_eq!(engine.get_state().registers.get_register_by_index(7), 7);
+    assert_eq!(engine.get_symbols().format_address(201), "finish+1");
+}
+
+#[test]
+fn broken_container_images_are_rejected() {
+    let overlapping = ContainerImage {
+        segments: vec![Segment { load_address: 0, words: vec![0, 0] }, Segment { load_address: 1, words: vec![0] }],
+        ..ContainerImage::default()
+    };
+    let mut data = Vec::new();
+    overlapping.write(&mut data).unwrap();
+    assert_eq!(SVMProgram::from_image(&data).err().unwrap(), "address 1 is loaded twice");
+    data.truncate(data.len() - 3);
+    assert_eq!(SVMProgram::from_image(&data).err().unwrap(), "the container image is truncated");
+}
+
+#[test]
+fn hex_text_images_follow_load_address_markers() {
+    let source = "// jump over a gap\n0006 0100 # jmp 256\n@100\n0013 0041 0000\n";
+    let (engine, output) = run(SVMProgram::from_image(source.as_bytes()).unwrap());
+    assert_eq!(output, "A");
+    assert_eq!(engine.get_state().memory.load_memory(2).unwrap(), 0);
+    assert!(SVMProgram::from_image(b"0013 zz").err().unwrap().starts_with("line 1: zz"));
+}
+
+#[test]
+fn intel_hex_images_load_with_their_start_address() {
+    //  out 'H', halt at word 0x10, which is byte 0x20
+    let mut source = record(0x00, 0x0020, &[0x13, 0x00, 0x48, 0x00, 0x00, 0x00]);
+    source.push_str(&record(0x05, 0, &[0, 0, 0, 0x20]));
+    source.push_str(&record(0x01, 0, &[]));
+    let program = SVMProgram::from_image(source.as_bytes()).unwrap();
+    assert_eq!(program.get_entry_point(), 0x10);
+    assert_eq!(run(program).1, "H");
+
+    let corrupted = source.replacen("48", "49", 1);
+    assert_eq!(SVMProgram::from_image(corrupted.as_bytes()).err().unwrap(), "line 1: bad checksum");
+    let unterminated = record(0x00, 0, &[0x15, 0x00]);
+    assert_eq!(SVMProgram::from_image(unterminated.as_bytes()).err().unwrap(), "missing the end of file record");
+    let non_ascii = ":00000001\u{20AC}F\n";
+    assert_eq!(parse_intel_hex(non_ascii.as_bytes()).err().unwrap(), "line 1: a record has a character that is not hex");
+}
+
+#[test]
+fn raw_images_larger_than_memory_are_rejected() {
+    assert!(SVMProgram::from_image(&vec![0; 65536]).is_ok());
+    assert!(SVMProgram::from_image(&vec![0; 65537]).is_err());
+    assert!(SVMProgram::from_image(&vec![0; 70000]).is_err());
+}
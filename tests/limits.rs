@@ -0,0 +1,59 @@
+//  Resource limits that stop untrusted programs before they exceed them
+use std::time::Duration;
+use synacorvm::engine::svm_assembler::SVMAssembly;
+use synacorvm::engine::svm_engine::{SVMEngine, SVMTermination};
+use synacorvm::engine::svm_limits::SVMLimits;
+use synacorvm_core::opcode::ExtensionSet;
+
+fn start(source: &str, limits: SVMLimits) -> SVMEngine {
+    let assembly = SVMAssembly::assemble(source, ExtensionSet::STRICT).unwrap_or_else(|error| panic!("{}", error));
+    let mut engine = SVMEngine::new(assembly.get_program());
+    engine.detach_console();
+    engine.set_limits(limits);
+    engine
+}
+
+#[test]
+fn a_push_loop_hits_the_stack_limit() {
+    let mut engine = start("loop: push 1\njmp loop", SVMLimits { max_stack_depth: Some(10), ..SVMLimits::default() });
+    assert!(matches!(engine.run(), SVMTermination::StackLimit));
+    assert_eq!(engine.get_state().stack.len(), 10);
+}
+
+#[test]
+fn a_jmp_loop_hits_the_instruction_limit() {
+    let mut engine = start("loop: jmp loop", SVMLimits { max_instructions: Some(1001), ..SVMLimits::default() });
+    assert!(matches!(engine.run(), SVMTermination::InstructionLimit));
+    assert_eq!(engine.get_state().instruction_count, 1001);
+}
+
+#[test]
+fn a_jmp_loop_hits_the_time_limit() {
+    let mut engine = start("loop: jmp loop", SVMLimits { max_duration: Some(Duration::from_millis(20)), ..SVMLimits::default() });
+    assert!(matches!(engine.run(), SVMTermination::TimeLimit));
+}
+
+#[test]
+fn the_clock_is_checked_from_the_start_of_each_run() {
+    let mut engine = start("loop: jmp loop", SVMLimits { max_instructions: Some(1001), ..SVMLimits::default() });
+    engine.run();
+    engine.set_limits(SVMLimits { max_duration: Some(Duration::ZERO), ..SVMLimits::default() });
+    assert!(matches!(engine.run(), SVMTermination::TimeLimit));
+    assert_eq!(engine.get_state().instruction_count, 1001);
+}
+
+#[test]
+fn an_out_loop_hits_the_output_limit() {
+    let mut engine = start("loop: out 'x'\njmp loop", SVMLimits { max_output_bytes: Some(5), ..SVMLimits::default() });
+    assert!(matches!(engine.run(), SVMTermination::OutputLimit));
+    assert_eq!(engine.take_output(), b"xxxxx");
+}
+
+#[test]
+fn an_in_loop_hits_the_input_limit() {
+    let mut engine = start("loop: in r0\njmp loop", SVMLimits { max_input_reads: Some(3), ..SVMLimits::default() });
+    engine.push_input(b"abcdef");
+    assert!(matches!(engine.run(), SVMTermination::InputLimit));
+    assert_eq!(engine.get_state().input_reads, 3);
+    assert_eq!(engine.get_state().registers.get_register_by_index(0), b'c' as u16);
+}
@@ -0,0 +1,43 @@
+//  Narrowing down where a variable lives by how its value changes
+use synacorvm::engine::svm_memory_search::{SearchFilter, SVMMemorySearch};
+use synacorvm_core::memory::Memory;
+
+fn addresses(search: &SVMMemorySearch) -> Vec<u16> {
+    search.get_candidates().iter().map(|candidate| candidate.address).collect()
+}
+
+#[test]
+fn an_exact_scan_refines_to_the_changed_word() {
+    let mut memory = Memory::new(&[5, 1, 5, 5]);
+    let mut search = SVMMemorySearch::scan_exact(&memory, 5);
+    assert_eq!(addresses(&search), vec![0, 2, 3]);
+
+    memory.store_memory(2, 4).unwrap();
+    search.refine(&memory, &SearchFilter::Decreased);
+    assert_eq!(addresses(&search), vec![2]);
+    assert_eq!(search.get_candidates()[0].value, 4);
+}
+
+#[test]
+fn an_unknown_value_is_found_by_how_it_changes() {
+    let mut memory = Memory::new(&[10, 20, 30]);
+    let mut search = SVMMemorySearch::scan_all(&memory);
+    memory.store_memory(1, 21).unwrap();
+    memory.store_memory(100, 3).unwrap();
+    search.refine(&memory, &SearchFilter::Increased);
+    assert_eq!(addresses(&search), vec![1, 100]);
+
+    memory.store_memory(100, 2).unwrap();
+    search.refine(&memory, &SearchFilter::parse(&["unchanged"]).unwrap());
+    assert_eq!(addresses(&search), vec![1]);
+    assert!(SearchFilter::parse(&["equals", "x"]).is_err());
+}
+
+#[test]
+fn equals_takes_hex_and_rejects_values_memory_cannot_hold() {
+    assert!(matches!(SearchFilter::parse(&["equals", "0x7fff"]), Ok(SearchFilter::Equals(32767))));
+    assert!(matches!(SearchFilter::parse(&["eq", "12"]), Ok(SearchFilter::Equals(12))));
+    assert!(SearchFilter::parse(&["equals", "32768"]).is_err());
+    assert!(SearchFilter::parse(&["equals", "0x8000"]).is_err());
+    assert!(SearchFilter::parse(&["equals", "twelve"]).is_err());
+}
@@ -0,0 +1,159 @@
+//  Host commands typed at the console instead of program input
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::env;
+use std::io;
+use std::rc::Rc;
+use synacorvm::engine::svm_checkpoints::SVMCheckpoints;
+use synacorvm::engine::svm_console::SVMConsole;
+use synacorvm::engine::svm_engine::{SVMEngine, SVMTermination};
+use synacorvm::engine::svm_program::SVMProgram;
+use synacorvm::engine::svm_save_slots::SVMSaveSlots;
+use synacorvm_core::svm_engine_state::SVMEngineState;
+use synacorvm_core::svm_error::SVMError;
+
+//  Feeds the engine prepared lines and keeps what it writes where the test can see it
+struct ScriptedConsole {
+    lines: VecDeque<String>,
+    output: Rc<RefCell<Vec<u8>>>,
+}
+
+impl SVMConsole for ScriptedConsole {
+    fn write_output(&mut self, data: &[u8]) -> io::Result<()> {
+        self.output.borrow_mut().extend_from_slice(data);
+        Ok(())
+    }
+
+    fn read_input(&mut self) -> io::Result<Option<Vec<u8>>> {
+        Ok(self.lines.pop_front().map(String::into_bytes))
+    }
+}
+
+//  Fails the first write, as a closed terminal would
+struct FailingConsole {
+    lines: VecDeque<String>,
+    failed: bool,
+}
+
+impl SVMConsole for FailingConsole {
+    fn write_output(&mut self, _: &[u8]) -> io::Result<()> {
+        if self.failed {
+            return Ok(());
+        }
+        self.failed = true;
+        Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed"))
+    }
+
+    fn read_input(&mut self) -> io::Result<Option<Vec<u8>>> {
+        Ok(self.lines.pop_front().map(String::into_bytes))
+    }
+}
+
+//  Runs in r0, out r0, halt with the given console lines and returns the console output
+fn run_echo(lines: &[&str]) -> (SVMEngine, String) {
+    run_with_console(vec![20, 32768, 19, 32768, 0], lines)
+}
+
+fn run_with_console(program: Vec<u16>, lines: &[&str]) -> (SVMEngine, String) {
+    let mut engine = SVMEngine::new(SVMProgram::from_words(program));
+    let output = Rc::new(RefCell::new(Vec::new()));
+    engine.set_console(Box::new(ScriptedConsole { lines: lines.iter().map(|line| line.to_string()).collect(), output: output.clone() }));
+    engine.set_meta_prefix("!".to_string());
+    engine.enable_undo(4);
+    engine.set_save_directory(env::temp_dir());
+    let termination = engine.run();
+    assert!(matches!(termination, SVMTermination::Halted | SVMTermination::AwaitingInput));
+    let output = String::from_utf8_lossy(&output.borrow()).to_string();
+    (engine, output)
+}
+
+#[test]
+fn meta_commands_are_not_program_input() {
+    let (engine, output) = run_echo(&["!set r7 25734\n", "!regs\n", "x\n"]);
+    assert!(output.contains("r7: 25734"));
+    assert!(output.ends_with('x'));
+    assert!(engine.get_state().halted);
+    assert_eq!(engine.get_state().registers.get_register_by_index(0), b'x' as u16);
+}
+
+#[test]
+fn memory_can_be_set_and_shown() {
+    let (_, output) = run_echo(&["!set 100 7\n", "!mem 100 2\n", "!set r9 1\n", "!frobnicate\n", "x\n"]);
+    assert!(output.contains("  100:     7     0"));
+    assert!(output.contains("Invalid register r9"));
+    assert!(output.contains("Unknown command frobnicate"));
+}
+
+#[test]
+fn load_restores_a_saved_slot() {
+    let name = format!("synacorvm-meta-{}", std::process::id());
+    let save = format!("!save {}\n", name);
+    let load = format!("!load {}\n", name);
+    let (engine, output) = run_echo(&[&save, "!set r3 9\n", "!slots\n", &load, "x\n"]);
+    let slots = SVMSaveSlots::new(env::temp_dir());
+    let info = slots.read_info(&name).unwrap();
+    std::fs::remove_file(env::temp_dir().join(format!("{}.snapshot", name))).ok();
+    std::fs::remove_file(env::temp_dir().join(format!("{}.meta", name))).ok();
+    assert!(output.contains(&format!("  {}", name)));
+    assert!(output.contains("Loaded"));
+    assert_eq!(info.instruction_count, 0);
+    assert_eq!(engine.get_state().registers.get_register_by_index(3), 0);
+    assert!(engine.get_state().halted);
+}
+
+#[test]
+fn undo_takes_back_whole_input_lines() {
+    //  in r0, out r0, add r1 r1 1, jmp 0: r1 counts the input bytes read
+    let program = vec![20, 32768, 19, 32768, 9, 32769, 32769, 1, 6, 0];
+    let (engine, output) = run_with_console(program, &["a\n", "bb\n", "!undo\n", "c\n", "!undo 3\n"]);
+    assert_eq!(output, "a\nbb\nUndid 1 command(s)\nc\nCan only undo up to 2 command(s)\n");
+    assert_eq!(engine.get_state().registers.get_register_by_index(1), 4);
+    assert_eq!(engine.get_checkpoints().unwrap().len(), 2);
+}
+
+#[test]
+fn checkpoints_keep_the_most_recent_states() {
+    let mut checkpoints = SVMCheckpoints::new(2);
+    for count in 0..3 {
+        let mut engine_state = SVMEngineState::new(&[]);
+        engine_state.instruction_count = count;
+        checkpoints.record(&engine_state);
+    }
+    assert_eq!(checkpoints.len(), 2);
+    assert!(checkpoints.undo(3).is_none());
+    assert_eq!(checkpoints.undo(2).unwrap().instruction_count, 1);
+    assert!(checkpoints.is_empty());
+}
+
+#[test]
+fn lines_without_the_prefix_reach_the_program() {
+    let (engine, output) = run_echo(&["regs\n"]);
+    assert_eq!(output, "r");
+    assert!(engine.get_state().halted);
+}
+
+#[test]
+fn the_console_survives_a_failed_response() {
+    let mut engine = SVMEngine::new(SVMProgram::from_words(vec![20, 32768, 19, 32768, 0]));
+    engine.set_console(Box::new(FailingConsole { lines: vec!["!regs\n".to_string(), "x\n".to_string()].into(), failed: false }));
+    engine.set_meta_prefix("!".to_string());
+    assert!(matches!(engine.run(), SVMTermination::Error(SVMError::WriteError)));
+    assert!(matches!(engine.run(), SVMTermination::Halted));
+    assert_eq!(engine.get_state().registers.get_register_by_index(0), b'x' as u16);
+}
+
+#[test]
+fn slot_names_cannot_leave_the_save_directory() {
+    let directory = env::temp_dir().join(format!("synacorvm-slots-{}", std::process::id()));
+    std::fs::create_dir_all(directory.join("nested")).unwrap();
+    let slots = SVMSaveSlots::new(directory.clone());
+    let engine_state = SVMEngineState::new(&[]);
+    for name in ["", "..", ".", "../escape", "nested/slot", "/tmp/slot", "slot/"] {
+        assert!(slots.save(name, &engine_state, "").is_err(), "{:?} was accepted", name);
+        assert!(slots.load(name).is_err());
+        assert!(slots.read_info(name).is_err());
+    }
+    assert!(slots.save("slot", &engine_state, "").is_ok());
+    assert_eq!(slots.list().unwrap().len(), 1);
+    std::fs::remove_dir_all(directory).ok();
+}
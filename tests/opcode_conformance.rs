@@ -0,0 +1,246 @@
+//  Checks every opcode against the architecture spec with tiny assembled programs
+use synacorvm::engine::svm_assembler::SVMAssembly;
+use synacorvm::engine::svm_engine::{SVMEngine, SVMTermination};
+use synacorvm_core::opcode::ExtensionSet;
+use synacorvm_core::svm_error::SVMError;
+
+fn start(source: &str, input: &[u8]) -> SVMEngine {
+    let assembly = SVMAssembly::assemble(source, ExtensionSet::STRICT).unwrap_or_else(|error| panic!("{}", error));
+    let mut engine = SVMEngine::new(assembly.get_program());
+    engine.detach_console();
+    engine.push_input(input);
+    engine
+}
+
+fn run(source: &str) -> SVMEngine {
+    run_with_input(source, b"")
+}
+
+fn run_with_input(source: &str, input: &[u8]) -> SVMEngine {
+    let mut engine = start(source, input);
+    match engine.run() {
+        SVMTermination::Halted => engine,
+        termination => panic!("program did not halt: {}", termination.get_description()),
+    }
+}
+
+fn run_to_error(source: &str) -> SVMError {
+    match start(source, b"").run() {
+        SVMTermination::Error(error) => error,
+        termination => panic!("program did not fail: {}", termination.get_description()),
+    }
+}
+
+fn registers(engine: &SVMEngine) -> Vec<u16> {
+    (0..8).map(|index| engine.get_state().registers.get_register_by_index(index)).collect()
+}
+
+fn memory(engine: &SVMEngine, address: u16) -> u16 {
+    engine.get_state().memory.load_memory(address).unwrap()
+}
+
+#[test]
+fn halt_stops_before_the_next_instruction() {
+    let mut engine = run("halt\nout 'x'");
+    assert!(engine.get_state().halted);
+    assert_eq!(engine.get_state().instruction_pointer.get_ip(), 1);
+    assert!(engine.take_output().is_empty());
+}
+
+#[test]
+fn set_takes_literals_and_registers() {
+    let engine = run("set r0 5\nset r1 r0\nset r7 32767\nhalt");
+    assert_eq!(registers(&engine), vec![5, 5, 0, 0, 0, 0, 0, 32767]);
+}
+
+#[test]
+fn push_and_pop_are_last_in_first_out() {
+    let engine = run("set r0 2\npush 1\npush r0\npop r1\npop r2\nhalt");
+    assert_eq!(registers(&engine)[1..3], [2, 1]);
+    assert!(engine.get_state().stack.is_empty());
+}
+
+#[test]
+fn pop_on_an_empty_stack_is_an_error() {
+    assert!(matches!(run_to_error("pop r0\nhalt"), SVMError::StackEmpty));
+}
+
+#[test]
+fn eq_and_gt_compare_literals_and_registers() {
+    let engine = run("set r7 9
+        eq r0 9 r7
+        eq r1 r7 8
+        gt r2 r7 8
+        gt r3 9 r7
+        gt r4 32767 0
+        halt");
+    assert_eq!(registers(&engine)[0..5], [1, 0, 1, 0, 1]);
+}
+
+#[test]
+fn jmp_takes_literal_and_register_targets() {
+    let engine = run("        jmp first
+        halt
+first:  set r0 skip
+        jmp r0
+        halt
+skip:   set r1 1
+        halt");
+    assert_eq!(registers(&engine)[1], 1);
+}
+
+#[test]
+fn jt_and_jf_branch_on_nonzero_and_zero() {
+    let engine = run("        set r7 taken
+        jt 0 wrong
+        jf 1 wrong
+        jt 7 r7
+        halt
+taken:  jf r0 done
+wrong:  set r1 1
+done:   set r2 1
+        halt");
+    assert_eq!(registers(&engine)[1..3], [0, 1]);
+}
+
+#[test]
+fn add_wraps_at_15_bits() {
+    let engine = run("add r0 32758 15\nset r1 32767\nadd r2 r1 r1\nadd r3 r1 1\nhalt");
+    assert_eq!(registers(&engine)[0..4], [5, 32767, 32766, 0]);
+}
+
+#[test]
+fn mult_wraps_at_15_bits() {
+    let engine = run("mult r0 32767 2\nmult r1 1000 1000\nset r7 181\nmult r2 r7 r7\nhalt");
+    assert_eq!(registers(&engine)[0..3], [32766, 16960, 32761]);
+}
+
+#[test]
+fn mod_is_the_remainder_of_unsigned_division() {
+    let engine = run("mod r0 10 3\nmod r1 3 10\nset r7 32767\nmod r2 r7 2\nmod r3 r7 r7\nhalt");
+    assert_eq!(registers(&engine)[0..4], [1, 3, 1, 0]);
+}
+
+#[test]
+fn mod_by_zero_is_an_error() {
+    assert!(matches!(run_to_error("mod r0 5 r1\nhalt"), SVMError::DivisionByZero));
+}
+
+#[test]
+fn and_and_or_are_bitwise() {
+    let engine = run("set r7 0x5555\nand r0 r7 0x0FF0\nor r1 r7 0x2AAA\nor r2 0 0\nhalt");
+    assert_eq!(registers(&engine)[0..3], [0x0550, 0x7FFF, 0]);
+}
+
+#[test]
+fn not_inverts_only_15_bits() {
+    let engine = run("not r0 0\nnot r1 32767\nset r7 0x5555\nnot r2 r7\nhalt");
+    assert_eq!(registers(&engine)[0..3], [0x7FFF, 0, 0x2AAA]);
+}
+
+#[test]
+fn rmem_and_wmem_take_literal_and_register_addresses() {
+    let engine = run("wmem 200 42
+        rmem r0 200
+        set r1 201
+        wmem r1 r0
+        rmem r2 r1
+        rmem r3 0
+        halt");
+    assert_eq!(registers(&engine)[0..4], [42, 201, 42, 16]);
+    assert_eq!(memory(&engine, 200), 42);
+    assert_eq!(memory(&engine, 201), 42);
+}
+
+#[test]
+fn the_last_word_of_memory_is_addressable() {
+    let engine = run("wmem 32767 7\nrmem r0 32767\nhalt");
+    assert_eq!(registers(&engine)[0], 7);
+}
+
+#[test]
+fn destinations_that_are_not_registers_are_memory_addresses() {
+    let engine = run_with_input("set 300 1
+        eq 301 1 1
+        gt 302 2 1
+        add 303 1 2
+        mult 304 2 2
+        mod 305 11 6
+        and 306 6 3
+        or 307 6 1
+        not 308 32766
+        rmem 309 0
+        push 10
+        pop 310
+        in 311
+        halt", b"z");
+    let values: Vec<u16> = (300..312).map(|address| memory(&engine, address)).collect();
+    assert_eq!(values, vec![1, 1, 1, 3, 4, 5, 2, 7, 1, 1, 10, 'z' as u16]);
+    assert_eq!(registers(&engine), vec![0; 8]);
+}
+
+#[test]
+fn call_pushes_the_return_address_and_ret_jumps_to_it() {
+    let engine = run("        call first
+        set r7 function
+        call r7
+        halt
+first:  pop r0
+        push r0
+        ret
+function:
+        pop r1
+        push r1
+        ret");
+    assert_eq!(registers(&engine)[0..2], [2, 7]);
+    assert!(engine.get_state().stack.is_empty());
+}
+
+#[test]
+fn ret_on_an_empty_stack_halts() {
+    let mut engine = run("ret\nout 'x'");
+    assert!(engine.take_output().is_empty());
+}
+
+#[test]
+fn out_writes_one_character() {
+    let mut engine = run("out 'H'\nset r0 105\nout r0\nout 10\nhalt");
+    assert_eq!(engine.take_output(), b"Hi\n");
+}
+
+#[test]
+fn in_reads_one_character_at_a_time() {
+    let engine = run_with_input("in r0\nin r1\nin r2\nhalt", b"ab\n");
+    assert_eq!(registers(&engine)[0..3], [97, 98, 10]);
+}
+
+#[test]
+fn in_waits_when_there_is_no_input() {
+    let mut engine = start("in r0\nhalt", b"");
+    assert!(matches!(engine.run(), SVMTermination::AwaitingInput));
+    assert_eq!(engine.get_state().instruction_pointer.get_ip(), 0);
+    engine.push_input(b"q");
+    assert!(matches!(engine.run(), SVMTermination::Halted));
+    assert_eq!(registers(&engine)[0], 'q' as u16);
+}
+
+#[test]
+fn noop_does_nothing() {
+    let engine = run("noop\nnoop\nhalt");
+    assert_eq!(engine.get_state().instruction_pointer.get_ip(), 3);
+    assert_eq!(registers(&engine), vec![0; 8]);
+}
+
+#[test]
+fn operands_above_the_registers_are_invalid() {
+    assert!(matches!(run_to_error("data 9 32768 65535 1"), SVMError::InvalidOperand));
+    assert!(matches!(run_to_error("data 19 32776"), SVMError::InvalidOperand));
+    assert!(matches!(run_to_error("data 1 32776 5"), SVMError::InvalidOperand));
+    assert!(matches!(run_to_error("data 6 40000"), SVMError::InvalidOperand));
+}
+
+#[test]
+fn unknown_opcodes_are_invalid() {
+    assert!(matches!(run_to_error("data 22"), SVMError::InvalidOpCode));
+    assert!(matches!(run_to_error("noop\ndata 65535"), SVMError::InvalidOpCode));
+}
@@ -0,0 +1,54 @@
+//  Patch files applied to program images before they run
+use synacorvm::engine::svm_engine::SVMEngine;
+use synacorvm::engine::svm_patch::SVMPatchFile;
+use synacorvm::engine::svm_program::SVMProgram;
+use synacorvm_core::opcode::ExtensionSet;
+
+//  jt 1 6, out 'A', halt at 5, out 'B' at 6, halt
+fn program() -> SVMProgram {
+    SVMProgram::from_words(vec![7, 1, 6, 19, 65, 0, 19, 66, 0])
+}
+
+fn run(program: SVMProgram) -> String {
+    let mut engine = SVMEngine::new(program);
+    engine.detach_console();
+    engine.run();
+    String::from_utf8(engine.take_output()).unwrap()
+}
+
+#[test]
+fn words_replace_the_expected_words() {
+    let patches = SVMPatchFile::parse("at 0\nexpect 7 1 6 ; jt 1 6\nwords 21 21 21\n", ExtensionSet::STRICT).unwrap();
+    let mut program = program();
+    patches.apply(&mut program).unwrap();
+    assert_eq!(run(program), "A");
+}
+
+#[test]
+fn assembly_is_placed_at_the_patch_address() {
+    let source = "; print a semicolon instead\nat 6\nexpect 19 66\nasm here: out ';' ; comment\nasm jmp here+4\nat 10\nexpect 0\nasm halt\n";
+    let patches = SVMPatchFile::parse(source, ExtensionSet::STRICT).unwrap();
+    assert_eq!(patches.get_patches()[0].replacement, vec![19, 59, 6, 10]);
+    let mut program = program();
+    patches.apply(&mut program).unwrap();
+    assert_eq!(run(program), ";");
+}
+
+#[test]
+fn patches_for_another_image_fail() {
+    let patches = SVMPatchFile::parse("at 3\nexpect 19 66\nwords 21 21\n", ExtensionSet::STRICT).unwrap();
+    let mut program = program();
+    let error = patches.apply(&mut program).err().unwrap();
+    assert_eq!(error, "line 1: expected [19, 66] at 3 but found [19, 65]");
+    assert_eq!(run(program), "B");
+}
+
+#[test]
+fn malformed_patch_files_are_rejected() {
+    assert!(SVMPatchFile::parse("expect 1\n", ExtensionSet::STRICT).err().unwrap().contains("before the first at"));
+    assert!(SVMPatchFile::parse("at 0\nwords 1\n", ExtensionSet::STRICT).err().unwrap().contains("no expect line"));
+    assert!(SVMPatchFile::parse("at 0\nexpect 7\n", ExtensionSet::STRICT).err().unwrap().contains("no words or asm"));
+    assert!(SVMPatchFile::parse("at 0\nexpect 7\nasm frob\n", ExtensionSet::STRICT).err().unwrap().starts_with("line 3: unknown instruction"));
+    assert!(SVMPatchFile::parse("at 0\nexpect 7\nwords 1\nasm jmp nowhere\n", ExtensionSet::STRICT).err().unwrap().starts_with("line 4: unknown label"));
+    assert!(SVMPatchFile::parse("at 32767\nexpect 0\nwords 1 2\n", ExtensionSet::STRICT).is_err());
+}
@@ -0,0 +1,45 @@
+//  Instruction, opcode and per-function counts from the profiler
+use synacorvm::engine::svm_engine::SVMEngine;
+use synacorvm::engine::svm_program::SVMProgram;
+use synacorvm::engine::svm_symbols::SVMSymbols;
+use synacorvm_core::opcode::SVMOpCode;
+
+//  call helper twice and halt, helper at 5 is noop, ret
+fn run_profiled() -> SVMEngine {
+    let mut engine = SVMEngine::new(SVMProgram::from_words(vec![17, 5, 17, 5, 0, 21, 18]));
+    engine.detach_console();
+    engine.enable_profiler();
+    engine.run();
+    engine
+}
+
+#[test]
+fn opcodes_are_counted() {
+    let engine = run_profiled();
+    let profiler = engine.get_profiler().unwrap();
+    assert_eq!(profiler.get_total_cycles(), 7);
+    let counts: Vec<u64> = [SVMOpCode::Call, SVMOpCode::NoOp, SVMOpCode::Ret, SVMOpCode::Halt].iter()
+        .map(|opcode| profiler.get_opcode_count(opcode.get_value()))
+        .collect();
+    assert_eq!(counts, vec![2, 2, 2, 1]);
+}
+
+#[test]
+fn instructions_are_attributed_to_functions() {
+    let engine = run_profiled();
+    let statistics = engine.get_profiler().unwrap().get_function_statistics();
+    let root = &statistics[&None];
+    assert_eq!((root.calls, root.inclusive_cycles, root.exclusive_cycles), (1, 7, 3));
+    let helper = &statistics[&Some(5)];
+    assert_eq!((helper.calls, helper.inclusive_cycles, helper.exclusive_cycles), (2, 4, 4));
+}
+
+#[test]
+fn folded_stacks_name_the_call_paths() {
+    let engine = run_profiled();
+    let mut symbols = SVMSymbols::new();
+    symbols.set_function(5, "helper");
+    let mut folded = Vec::new();
+    engine.get_profiler().unwrap().write_folded_stacks(&mut folded, &symbols).unwrap();
+    assert_eq!(String::from_utf8(folded).unwrap(), "<root> 3\n<root>;helper 4\n");
+}
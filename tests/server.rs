@@ -0,0 +1,101 @@
+//  Multi-session TCP server, one machine per client
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+use synacorvm::engine::svm_assembler::SVMAssembly;
+use synacorvm::engine::svm_server::{SVMServer, ServerOptions};
+use synacorvm_core::opcode::ExtensionSet;
+
+//  Echoes every line back after the number of the line, counted in r1
+const ECHO: &str = "line: add r1 r1 1
+add r2 r1 '0'
+out r2
+read: in r0
+out r0
+eq r3 r0 '\\n'
+jf r3 read
+jmp line";
+
+fn start_server(options: ServerOptions) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let program = SVMAssembly::assemble(ECHO, ExtensionSet::STRICT).unwrap().get_program();
+    thread::spawn(move || {
+        SVMServer::new(program, options).serve(listener).unwrap();
+    });
+    address
+}
+
+fn connect(address: SocketAddr) -> (TcpStream, BufReader<TcpStream>) {
+    let stream = TcpStream::connect(address).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    let reader = BufReader::new(stream.try_clone().unwrap());
+    (stream, reader)
+}
+
+fn exchange(stream: &mut TcpStream, reader: &mut BufReader<TcpStream>, line: &str) -> String {
+    stream.write_all(line.as_bytes()).unwrap();
+    let mut reply = String::new();
+    reader.read_line(&mut reply).unwrap();
+    reply
+}
+
+fn read_to_end(reader: &mut BufReader<TcpStream>) -> String {
+    let mut text = String::new();
+    reader.read_to_string(&mut text).unwrap();
+    text
+}
+
+#[test]
+fn concurrent_sessions_have_their_own_machines() {
+    let address = start_server(ServerOptions::default());
+    let (mut first, mut first_reader) = connect(address);
+    let (mut second, mut second_reader) = connect(address);
+
+    assert_eq!(exchange(&mut first, &mut first_reader, "one\n"), "1one\n");
+    assert_eq!(exchange(&mut second, &mut second_reader, "two\n"), "1two\n");
+    assert_eq!(exchange(&mut first, &mut first_reader, "three\n"), "2three\n");
+    assert_eq!(exchange(&mut second, &mut second_reader, "four\n"), "2four\n");
+}
+
+#[test]
+fn an_idle_session_is_closed() {
+    let address = start_server(ServerOptions { idle_timeout: Some(Duration::from_millis(100)), ..ServerOptions::default() });
+    let (_stream, mut reader) = connect(address);
+    assert_eq!(read_to_end(&mut reader), "1\nIdle timeout, closing the session.\n");
+}
+
+#[test]
+fn clients_past_max_sessions_are_refused() {
+    let address = start_server(ServerOptions { max_sessions: 1, ..ServerOptions::default() });
+    let (mut first, mut first_reader) = connect(address);
+    assert_eq!(exchange(&mut first, &mut first_reader, "hello\n"), "1hello\n");
+
+    let (_second, mut second_reader) = connect(address);
+    assert_eq!(read_to_end(&mut second_reader), "The server is full, try again later.\n");
+    assert_eq!(exchange(&mut first, &mut first_reader, "still here\n"), "2still here\n");
+}
+
+#[test]
+fn a_line_sent_a_byte_at_a_time_still_times_out() {
+    let address = start_server(ServerOptions { idle_timeout: Some(Duration::from_millis(300)), ..ServerOptions::default() });
+    let (mut stream, mut reader) = connect(address);
+    let mut prompt = [0; 1];
+    reader.read_exact(&mut prompt).unwrap();
+    for _ in 0..10 {
+        thread::sleep(Duration::from_millis(50));
+        if stream.write_all(b"x").is_err() {
+            break;
+        }
+    }
+    assert_eq!(read_to_end(&mut reader), "\nIdle timeout, closing the session.\n");
+}
+
+#[test]
+fn an_overlong_line_closes_the_session() {
+    let address = start_server(ServerOptions::default());
+    let (mut stream, mut reader) = connect(address);
+    stream.write_all(&[b'x'; 5000]).unwrap();
+    assert_eq!(read_to_end(&mut reader), "1\nLine too long, closing the session.\n");
+}
@@ -0,0 +1,39 @@
+//  Snapshots and the diffs between machine states
+use synacorvm::engine::svm_program::SVMProgram;
+use synacorvm::engine::svm_snapshot::{is_snapshot, read_snapshot, write_snapshot};
+use synacorvm::engine::svm_state_diff::SVMStateDiff;
+
+fn diff_text(diff: &SVMStateDiff) -> String {
+    let mut text = Vec::new();
+    diff.write(&mut text).unwrap();
+    String::from_utf8(text).unwrap()
+}
+
+#[test]
+fn a_snapshot_restores_the_same_state() {
+    let program = SVMProgram::from_words(vec![9, 32768, 32768, 1, 0]);
+    let mut state = program.create_state();
+    state.registers.set_register_by_index(3, 77);
+    state.stack.push(12);
+    state.memory.store_memory(32767, 5).unwrap();
+
+    let mut data = Vec::new();
+    write_snapshot(&state, &mut data).unwrap();
+    assert!(is_snapshot(&data));
+    let restored = read_snapshot(&mut data.as_slice()).unwrap();
+    assert!(SVMStateDiff::between(&state, &restored).is_empty());
+    assert_eq!(diff_text(&SVMStateDiff::between(&state, &restored)), "No differences.\n");
+}
+
+#[test]
+fn one_register_and_one_word_are_reported() {
+    let program = SVMProgram::from_words(vec![9, 32768, 32768, 1, 0]);
+    let mut state = program.create_state();
+    state.registers.set_register_by_index(2, 9);
+    state.memory.store_memory(3, 4).unwrap();
+
+    let diff = SVMStateDiff::from_program(&program, &state);
+    assert_eq!(diff.registers.len(), 1);
+    assert_eq!(diff.memory.len(), 1);
+    assert_eq!(diff_text(&diff), "r2: 0 -> 9\nMemory: 1 changed range(s), 1 word(s)\n  3-3: 1 -> 4\n");
+}
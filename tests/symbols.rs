@@ -0,0 +1,56 @@
+//  Symbol and annotation files shared by the tools
+use std::env;
+use std::fs;
+use synacorvm::engine::svm_symbols::{DataType, SVMSymbols};
+
+const SYMBOL_FILE: &str = "# the entry code
+0 function main
+0x10 label loop
+0x10 comment runs until r0 is zero
+100 string 12
+120 table
+";
+
+#[test]
+fn a_symbol_file_is_parsed_and_saved_back() {
+    let path = env::temp_dir().join(format!("synacorvm-symbols-{}.sym", std::process::id()));
+    fs::write(&path, SYMBOL_FILE).unwrap();
+    let mut symbols = SVMSymbols::load(&path).unwrap();
+
+    assert_eq!(symbols.get(0).and_then(|annotation| annotation.function.clone()), Some("main".to_string()));
+    let loop_annotation = symbols.get(16).unwrap();
+    assert_eq!(loop_annotation.get_name(), Some("loop"));
+    assert_eq!(loop_annotation.comment.as_deref(), Some("runs until r0 is zero"));
+    assert!(symbols.get(100).unwrap().data_type == Some((DataType::String, 12)));
+    assert!(symbols.get(120).unwrap().data_type == Some((DataType::Table, 1)));
+
+    symbols.save(None).unwrap();
+    let saved = fs::read_to_string(&path).unwrap();
+    assert_eq!(saved, "0 function main\n16 label loop\n16 comment runs until r0 is zero\n100 string 12\n120 table 1\n");
+    fs::remove_file(path).ok();
+}
+
+#[test]
+fn addresses_are_named_by_the_closest_symbol() {
+    let mut symbols = SVMSymbols::new();
+    symbols.parse_line("0x10 label loop").unwrap();
+    assert_eq!(symbols.format_address(16), "loop");
+    assert_eq!(symbols.format_address(19), "loop+3");
+    assert_eq!(symbols.format_address(3), "3");
+    assert_eq!(symbols.parse_address("loop+2"), Ok(18));
+    assert_eq!(symbols.parse_address("0x20"), Ok(32));
+    assert!(symbols.parse_address("missing").is_err());
+}
+
+#[test]
+fn bad_lines_are_rejected() {
+    let mut symbols = SVMSymbols::new();
+    assert!(symbols.parse_line("12 label two words").is_err());
+    assert!(symbols.parse_line("12 colour red").is_err());
+    assert!(symbols.parse_line("banana label x").is_err());
+    let path = env::temp_dir().join(format!("synacorvm-bad-symbols-{}.sym", std::process::id()));
+    fs::write(&path, "0 label start\n7 nonsense\n").unwrap();
+    let error = SVMSymbols::load(&path).err().unwrap();
+    assert!(error.to_string().starts_with("line 2:"));
+    fs::remove_file(path).ok();
+}
@@ -0,0 +1,49 @@
+//  Commands and screen contents of the full screen debugger
+use synacorvm::engine::svm_program::SVMProgram;
+use synacorvm::engine::svm_tui::SVMTui;
+
+//  out 'H', out 'i', in r0, out r0, halt
+fn start() -> SVMTui {
+    SVMTui::new(SVMProgram::from_words(vec![19, 72, 19, 105, 20, 32768, 19, 32768, 0]))
+}
+
+#[test]
+fn breakpoints_stop_and_input_resumes() {
+    let mut tui = start();
+    assert!(tui.execute_command("b 4"));
+    tui.execute_command("c");
+    assert_eq!(tui.get_status(), "Breakpoint");
+    assert_eq!(tui.get_engine_mut().get_state().instruction_pointer.get_ip(), 4);
+
+    tui.execute_command("b 4");
+    tui.execute_command("c");
+    assert_eq!(tui.get_status(), "Waiting for input, type > followed by a line");
+    tui.execute_command("> x");
+    assert_eq!(tui.get_status(), "Stopped: halted");
+    assert!(!tui.execute_command("q"));
+}
+
+#[test]
+fn the_screen_shows_the_machine_and_console() {
+    let mut tui = start();
+    tui.execute_command("s 2");
+    tui.execute_command("m 4");
+    let screen = tui.render(30, 100);
+    let lines: Vec<&str> = screen.split("\r\n").collect();
+    assert!(lines[0].contains("ip 4   instructions 2   stack 0   call depth 0"));
+    assert!(lines.iter().any(|line| line.contains("in r0")));
+    assert!(lines.iter().any(|line| line.starts_with("     4: 0014 8000 0013 8000 0000")));
+    assert!(lines.iter().any(|line| line.trim_end() == "Hi"));
+    assert!(screen.ends_with("> "));
+
+    tui.execute_command("frobnicate");
+    assert_eq!(tui.get_status(), "Unknown command frobnicate");
+}
+
+#[test]
+fn a_screen_with_no_columns_still_renders() {
+    let mut tui = start();
+    tui.execute_command("s");
+    assert!(tui.render(0, 0).ends_with("> "));
+    assert!(tui.render(1, 2).ends_with("> "));
+}